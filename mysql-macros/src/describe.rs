@@ -0,0 +1,206 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Query metadata, either fetched from a live server or loaded from the offline cache.
+
+use mysql_common::{constants::ColumnFlags, named_params::ParsedNamedParams};
+use serde::{Deserialize, Serialize};
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::client::Client;
+
+/// Name of a directory (relative to `CARGO_MANIFEST_DIR`) that holds offline query data.
+pub const OFFLINE_DIR: &str = ".mysql";
+
+/// Connection reused by all macro invocations within a single compiler process.
+static CONN: Mutex<Option<(String, Client)>> = Mutex::new(None);
+
+/// Serializable metadata of a query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryDescription {
+    pub query: String,
+    pub params: usize,
+    pub named_params: Option<Vec<String>>,
+    pub columns: Vec<ColumnDescription>,
+}
+
+/// Serializable metadata of a result set column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnDescription {
+    pub name: String,
+    /// Raw `ColumnType` value.
+    pub column_type: u8,
+    pub character_set: u16,
+    pub nullable: bool,
+}
+
+impl QueryDescription {
+    /// Returns metadata of the given query.
+    ///
+    /// A live server is used if `DATABASE_URL` is set and `MYSQL_OFFLINE` is not,
+    /// otherwise the metadata is loaded from the offline data directory.
+    /// Metadata fetched from a live server is saved to the offline data directory
+    /// if this directory exists.
+    pub fn load(query: &str) -> Result<Self, String> {
+        let offline = matches!(env::var("MYSQL_OFFLINE").as_deref(), Ok("1") | Ok("true"));
+        let offline_dir = env::var_os("CARGO_MANIFEST_DIR")
+            .map(|dir| Path::new(&dir).join(OFFLINE_DIR))
+            .filter(|dir| dir.is_dir());
+
+        match env::var("DATABASE_URL") {
+            Ok(url) if !offline => {
+                let description = Self::fetch(&url, query)?;
+                if let Some(dir) = offline_dir {
+                    description.save(&dir)?;
+                }
+                Ok(description)
+            }
+            _ => match offline_dir {
+                Some(dir) => Self::read(&dir, query),
+                None => Err(format!(
+                    "`DATABASE_URL` must be set to check this query \
+                    (or offline data must be available in the `{}` directory)",
+                    OFFLINE_DIR
+                )),
+            },
+        }
+    }
+
+    /// Fetches metadata from a live server.
+    fn fetch(url: &str, query: &str) -> Result<Self, String> {
+        let mut guard = CONN.lock().unwrap_or_else(|e| e.into_inner());
+        if guard.as_ref().map(|(u, _)| u != url).unwrap_or(true) {
+            *guard = Some((url.into(), Client::connect(url)?));
+        }
+        let (_, client) = guard.as_mut().expect("connection is established above");
+
+        let parsed = ParsedNamedParams::parse(query.as_bytes())
+            .map_err(|_| "can not mix named and positional parameters in one statement")?;
+        let prepared = match client.prepare(parsed.query()) {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                // the connection might be unusable, so reconnect on the next invocation
+                *guard = None;
+                return Err(err);
+            }
+        };
+
+        let named_params = parsed
+            .params()
+            .iter()
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            query: query.into(),
+            params: prepared.params.len(),
+            named_params: Some(named_params).filter(|x| !x.is_empty()),
+            columns: prepared
+                .columns
+                .iter()
+                .map(|column| ColumnDescription {
+                    name: column.name_str().into_owned(),
+                    column_type: column.column_type() as u8,
+                    character_set: column.character_set(),
+                    nullable: !column.flags().contains(ColumnFlags::NOT_NULL_FLAG),
+                })
+                .collect(),
+        })
+    }
+
+    /// Reads metadata from the offline data directory.
+    fn read(dir: &Path, query: &str) -> Result<Self, String> {
+        let path = file_path(dir, query);
+        let data = fs::read(&path).map_err(|_| {
+            format!(
+                "offline data for this query is not found in `{}`, \
+                build with `DATABASE_URL` set to generate it",
+                dir.display()
+            )
+        })?;
+        let description: Self = serde_json::from_slice(&data)
+            .map_err(|e| format!("malformed offline data in `{}`: {}", path.display(), e))?;
+        if description.query != query {
+            return Err(format!(
+                "offline data in `{}` belongs to another query",
+                path.display()
+            ));
+        }
+        Ok(description)
+    }
+
+    /// Writes metadata to the offline data directory (unless it's already there).
+    fn save(&self, dir: &Path) -> Result<(), String> {
+        let path = file_path(dir, &self.query);
+        let data = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        if fs::read(&path).ok().as_deref() == Some(&data[..]) {
+            return Ok(());
+        }
+        fs::write(&path, data).map_err(|e| {
+            format!(
+                "unable to write offline data to `{}`: {}",
+                path.display(),
+                e
+            )
+        })
+    }
+}
+
+/// Returns path to the offline data file of the given query.
+fn file_path(dir: &Path, query: &str) -> PathBuf {
+    dir.join(format!("query-{:016x}.json", fnv1a(query.as_bytes())))
+}
+
+/// 64-bit FNV-1a hash (stable across compiler versions, unlike `DefaultHasher`).
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fnv1a, ColumnDescription, QueryDescription};
+
+    #[test]
+    fn should_hash_queries_stably() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn should_roundtrip_offline_data() {
+        let dir = std::env::temp_dir().join(format!("mysql-macros-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let description = QueryDescription {
+            query: "SELECT id FROM foo WHERE id = :id".into(),
+            params: 1,
+            named_params: Some(vec!["id".into()]),
+            columns: vec![ColumnDescription {
+                name: "id".into(),
+                column_type: 8,
+                character_set: 63,
+                nullable: false,
+            }],
+        };
+        description.save(&dir).unwrap();
+        assert_eq!(
+            QueryDescription::read(&dir, &description.query).unwrap(),
+            description
+        );
+        assert!(QueryDescription::read(&dir, "SELECT 1").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,462 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Compile-time checked queries for the [`mysql`](https://docs.rs/mysql) crate.
+//!
+//! The `query_as!` macro is also available as `mysql::query_as!` if the `macros` feature
+//! of the `mysql` crate is enabled.
+//!
+//! ### Checking modes
+//!
+//! *   **online** – if `DATABASE_URL` environment variable is set during the build, then
+//!     every query gets prepared on this server;
+//! *   **offline** – if `DATABASE_URL` is not set (or `MYSQL_OFFLINE=true`), then query metadata
+//!     is loaded from the `.mysql` directory in the root of your crate.
+//!
+//! To generate offline data create the `.mysql` directory and build your crate
+//! with `DATABASE_URL` set – metadata of every checked query will be saved there
+//! as a JSON file, so it could be committed alongside with the code.
+//!
+//! ### Connection
+//!
+//! The online mode uses a minimal built-in client with the following limitations:
+//!
+//! *   TLS is not supported (`require_ssl=true` is an error), so use a unix socket
+//!     (the `socket` URL parameter) or the offline mode if the server requires secure transport;
+//! *   only `mysql_native_password` and `caching_sha2_password` authentication is supported;
+//! *   URL parameters other than `user`, `password`, `host`, `port`, `socket`
+//!     and `db_name` are ignored.
+
+use mysql_common::constants::ColumnType;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    token, Expr, Ident, LitStr, Path, Token,
+};
+
+use std::collections::HashSet;
+
+use crate::describe::{ColumnDescription, QueryDescription};
+
+mod client;
+mod describe;
+
+/// Collation id of the `binary` character set.
+const BINARY_CHARSET: u16 = 63;
+
+/// Prepared statement query checked against the database schema at compile time.
+///
+/// ```ignore
+/// #[derive(Debug, PartialEq, Eq, mysql::prelude::FromRow)]
+/// struct Payment {
+///     customer_id: i32,
+///     amount: i32,
+///     account_name: Option<String>,
+/// }
+///
+/// // positional parameters
+/// let payments: Vec<Payment> = mysql_macros::query_as!(
+///     Payment,
+///     "SELECT customer_id, amount, account_name FROM payment WHERE amount > ?",
+///     100
+/// )
+/// .fetch(&mut conn)?;
+///
+/// // named parameters
+/// let payment: Option<Payment> = mysql_macros::query_as!(
+///     Payment,
+///     "SELECT customer_id, amount, account_name FROM payment WHERE customer_id = :id",
+///     id = 1,
+/// )
+/// .first(&mut conn)?;
+/// ```
+///
+/// The macro expands to `mysql::TypedQuery<Payment>` and checks that:
+///
+/// *   the query is valid (i.e. it could be prepared);
+/// *   number of arguments matches the number of parameters (or that every named
+///     parameter is given exactly once);
+/// *   every result set column corresponds to a field of the row type and vice versa
+///     (use SQL aliases to rename columns);
+/// *   type of every field is compatible with the column type (e.g. `DATE` column
+///     can't be read into an `i64`) and nullable columns are read into `Option<T>`
+///     (or `Value`).
+///
+/// Columns are matched with fields by name, so fields renamed with
+/// `#[mysql(rename = "..")]` (or `#[mysql(rename_all = "..")]`) must be listed
+/// after the row type with the same column names:
+///
+/// ```ignore
+/// #[derive(mysql::prelude::FromRow)]
+/// struct Payment {
+///     customer_id: i32,
+///     #[mysql(rename = "account_name")]
+///     name: Option<String>,
+/// }
+///
+/// let payments: Vec<Payment> = mysql_macros::query_as!(
+///     Payment { name: "account_name" },
+///     "SELECT customer_id, account_name FROM payment",
+/// )
+/// .fetch(&mut conn)?;
+/// ```
+///
+/// Queries are checked against `DATABASE_URL` or offline data (see the [crate docs](crate)).
+/// Note that connections to `DATABASE_URL` don't support TLS, and only `user`, `password`,
+/// `host`, `port`, `socket` and `db_name` URL parameters are honored.
+#[proc_macro]
+pub fn query_as(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as QueryAsInput);
+    match expand_query_as(input) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Argument of the `query_as!` macro.
+enum Arg {
+    Positional(Expr),
+    Named(Ident, Expr),
+}
+
+/// Field renamed with `#[mysql(rename = "..")]`, i.e. `field: "column"`.
+struct Rename {
+    field: Ident,
+    column: LitStr,
+}
+
+impl Parse for Rename {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let field = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let column = input.parse()?;
+        Ok(Self { field, column })
+    }
+}
+
+struct QueryAsInput {
+    row: Path,
+    renames: Vec<Rename>,
+    query: LitStr,
+    args: Vec<Arg>,
+}
+
+impl Parse for QueryAsInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let row = input.parse()?;
+        let mut renames = Vec::new();
+        if input.peek(token::Brace) {
+            let content;
+            braced!(content in input);
+            renames.extend(Punctuated::<Rename, Token![,]>::parse_terminated(&content)?);
+        }
+        input.parse::<Token![,]>()?;
+        let query = input.parse()?;
+        let mut args = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            if input.peek(Ident) && input.peek2(Token![=]) && !input.peek2(Token![==]) {
+                let name = input.parse()?;
+                input.parse::<Token![=]>()?;
+                args.push(Arg::Named(name, input.parse()?));
+            } else {
+                args.push(Arg::Positional(input.parse()?));
+            }
+        }
+        Ok(Self {
+            row,
+            renames,
+            query,
+            args,
+        })
+    }
+}
+
+fn expand_query_as(input: QueryAsInput) -> syn::Result<TokenStream2> {
+    let QueryAsInput {
+        row,
+        renames,
+        query,
+        args,
+    } = input;
+    let span = query.span();
+    let description =
+        QueryDescription::load(&query.value()).map_err(|msg| syn::Error::new(span, msg))?;
+
+    let arg_names = args
+        .iter()
+        .map(|arg| match arg {
+            Arg::Positional(_) => None,
+            Arg::Named(name, _) => Some(name.to_string()),
+        })
+        .collect::<Vec<_>>();
+    check_params(&description, &arg_names).map_err(|msg| syn::Error::new(span, msg))?;
+    let renames = renames
+        .iter()
+        .map(|x| (x.field.clone(), x.column.value()))
+        .collect::<Vec<_>>();
+    let fields = column_fields(&description, &renames).map_err(|msg| syn::Error::new(span, msg))?;
+
+    let field_checks = description
+        .columns
+        .iter()
+        .zip(&fields)
+        .map(|(column, field)| {
+            let check = match column_kind(column) {
+                Some(kind) if column.nullable => {
+                    let kind = Ident::new(kind, span);
+                    quote_spanned!(span=> ::mysql::__macros::nullable_column::<::mysql::__macros::#kind, _>())
+                }
+                Some(kind) => {
+                    let kind = Ident::new(kind, span);
+                    quote_spanned!(span=> ::mysql::__macros::column::<::mysql::__macros::#kind, _>())
+                }
+                None => quote_spanned!(span=> ::mysql::__macros::any_column()),
+            };
+            quote!(#field: #check)
+        });
+
+    let params = if args.is_empty() {
+        quote!(::mysql::Params::Empty)
+    } else if description.named_params.is_some() {
+        let inserts = args.iter().filter_map(|arg| match arg {
+            Arg::Named(name, value) => {
+                let name = name.to_string();
+                Some(quote! {
+                    __params.insert(
+                        ::std::string::String::from(#name).into_bytes(),
+                        ::mysql::prelude::ToValue::to_value(&(#value)),
+                    );
+                })
+            }
+            Arg::Positional(_) => None,
+        });
+        quote! {
+            ::mysql::Params::Named({
+                let mut __params = ::std::collections::HashMap::new();
+                #( #inserts )*
+                __params
+            })
+        }
+    } else {
+        let values = args.iter().filter_map(|arg| match arg {
+            Arg::Positional(value) => Some(quote!(::mysql::prelude::ToValue::to_value(&(#value)))),
+            Arg::Named(..) => None,
+        });
+        quote!(::mysql::Params::Positional(::std::vec![#( #values ),*]))
+    };
+
+    Ok(quote! {{
+        #[allow(dead_code, unreachable_code, clippy::diverging_sub_expression)]
+        fn __mysql_check_row() -> #row {
+            #row { #( #field_checks, )* }
+        }
+        ::mysql::TypedQuery::<#row>::new(#query, #params)
+    }})
+}
+
+/// Checks arguments against query parameters (`None` stands for a positional argument).
+fn check_params(description: &QueryDescription, args: &[Option<String>]) -> Result<(), String> {
+    match description.named_params {
+        None => {
+            if args.iter().any(Option::is_some) {
+                return Err(
+                    "named arguments are given for a query with positional parameters".into(),
+                );
+            }
+            if args.len() != description.params {
+                return Err(format!(
+                    "expected {} argument(s), got {}",
+                    description.params,
+                    args.len()
+                ));
+            }
+        }
+        Some(ref named_params) => {
+            let mut given = HashSet::new();
+            for arg in args {
+                match arg {
+                    Some(name) if !given.insert(name.as_str()) => {
+                        return Err(format!("argument `{}` is given more than once", name));
+                    }
+                    Some(name) if !named_params.contains(name) => {
+                        return Err(format!("query has no `:{}` parameter", name));
+                    }
+                    Some(_) => (),
+                    None => {
+                        return Err(
+                            "positional arguments are given for a query with named parameters"
+                                .into(),
+                        );
+                    }
+                }
+            }
+            if let Some(missing) = named_params.iter().find(|x| !given.contains(x.as_str())) {
+                return Err(format!("missing argument for the `:{}` parameter", missing));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Converts result set columns into field names of the row type
+/// (`renames` maps renamed fields to column names).
+fn column_fields(
+    description: &QueryDescription,
+    renames: &[(Ident, String)],
+) -> Result<Vec<Ident>, String> {
+    let mut seen = HashSet::new();
+    let mut fields = Vec::with_capacity(description.columns.len());
+    for column in &description.columns {
+        if !seen.insert(column.name.as_str()) {
+            return Err(format!("duplicate column `{}`", column.name));
+        }
+        let renamed = renames.iter().find(|(_, name)| *name == column.name);
+        let field = match renamed {
+            Some((field, _)) => field.clone(),
+            None => syn::parse_str::<Ident>(&column.name).map_err(|_| {
+                format!(
+                    "column `{}` is not a valid field name, please use an alias",
+                    column.name
+                )
+            })?,
+        };
+        fields.push(field);
+    }
+    if let Some((_, name)) = renames
+        .iter()
+        .find(|(_, name)| !seen.contains(name.as_str()))
+    {
+        return Err(format!("query has no `{}` column", name));
+    }
+    Ok(fields)
+}
+
+/// Returns the name of the `mysql::__macros` kind of the given column
+/// (`None` if the column type is not checked).
+fn column_kind(column: &ColumnDescription) -> Option<&'static str> {
+    use ColumnType::*;
+    let binary = column.character_set == BINARY_CHARSET;
+    let kind = match ColumnType::try_from(column.column_type).ok()? {
+        MYSQL_TYPE_TINY | MYSQL_TYPE_SHORT | MYSQL_TYPE_INT24 | MYSQL_TYPE_LONG
+        | MYSQL_TYPE_LONGLONG | MYSQL_TYPE_YEAR => "Integer",
+        MYSQL_TYPE_FLOAT | MYSQL_TYPE_DOUBLE => "Float",
+        MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => "Decimal",
+        MYSQL_TYPE_DATE | MYSQL_TYPE_NEWDATE => "Date",
+        MYSQL_TYPE_DATETIME
+        | MYSQL_TYPE_DATETIME2
+        | MYSQL_TYPE_TIMESTAMP
+        | MYSQL_TYPE_TIMESTAMP2 => "DateTime",
+        MYSQL_TYPE_TIME | MYSQL_TYPE_TIME2 => "Time",
+        MYSQL_TYPE_JSON => "Json",
+        MYSQL_TYPE_BIT | MYSQL_TYPE_GEOMETRY => "Bytes",
+        MYSQL_TYPE_ENUM | MYSQL_TYPE_SET => "Text",
+        MYSQL_TYPE_VARCHAR
+        | MYSQL_TYPE_VAR_STRING
+        | MYSQL_TYPE_STRING
+        | MYSQL_TYPE_TINY_BLOB
+        | MYSQL_TYPE_MEDIUM_BLOB
+        | MYSQL_TYPE_LONG_BLOB
+        | MYSQL_TYPE_BLOB => {
+            if binary {
+                "Bytes"
+            } else {
+                "Text"
+            }
+        }
+        _ => return None,
+    };
+    Some(kind)
+}
+
+#[cfg(test)]
+mod test {
+    use proc_macro2::{Ident, Span};
+
+    use super::{check_params, column_fields, column_kind};
+    use crate::describe::{ColumnDescription, QueryDescription};
+
+    fn description(params: usize, named: Option<&[&str]>, columns: &[&str]) -> QueryDescription {
+        QueryDescription {
+            query: String::new(),
+            params,
+            named_params: named.map(|names| names.iter().map(|x| x.to_string()).collect()),
+            columns: columns
+                .iter()
+                .map(|name| ColumnDescription {
+                    name: name.to_string(),
+                    column_type: 3,
+                    character_set: 63,
+                    nullable: true,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_check_positional_params() {
+        let desc = description(2, None, &[]);
+        assert!(check_params(&desc, &[None, None]).is_ok());
+        assert!(check_params(&desc, &[None]).is_err());
+        assert!(check_params(&desc, &[None, Some("foo".into())]).is_err());
+    }
+
+    #[test]
+    fn should_check_named_params() {
+        // `:foo` is used twice
+        let desc = description(3, Some(&["foo", "bar", "foo"]), &[]);
+        assert!(check_params(&desc, &[Some("bar".into()), Some("foo".into())]).is_ok());
+        assert!(check_params(&desc, &[Some("foo".into())]).is_err());
+        assert!(check_params(&desc, &[Some("foo".into()), Some("foo".into())]).is_err());
+        assert!(check_params(
+            &desc,
+            &[Some("foo".into()), Some("bar".into()), Some("baz".into())]
+        )
+        .is_err());
+        assert!(check_params(&desc, &[None, None]).is_err());
+    }
+
+    #[test]
+    fn should_convert_columns_to_fields() {
+        let fields = column_fields(&description(0, None, &["id", "name"]), &[]).unwrap();
+        assert_eq!(fields[0], "id");
+        assert_eq!(fields[1], "name");
+        assert!(column_fields(&description(0, None, &["COUNT(*)"]), &[]).is_err());
+        assert!(column_fields(&description(0, None, &["id", "id"]), &[]).is_err());
+    }
+
+    #[test]
+    fn should_map_renamed_fields() {
+        let renames = [(Ident::new("count", Span::call_site()), "COUNT(*)".into())];
+        let fields = column_fields(&description(0, None, &["id", "COUNT(*)"]), &renames).unwrap();
+        assert_eq!(fields[0], "id");
+        assert_eq!(fields[1], "count");
+        assert!(column_fields(&description(0, None, &["id"]), &renames).is_err());
+    }
+
+    #[test]
+    fn should_classify_columns() {
+        let mut desc = description(0, None, &["a"]);
+        let column = &mut desc.columns[0];
+        assert_eq!(column_kind(column), Some("Integer"));
+        column.column_type = 253;
+        assert_eq!(column_kind(column), Some("Bytes"));
+        column.character_set = 255;
+        assert_eq!(column_kind(column), Some("Text"));
+        column.column_type = 6;
+        assert_eq!(column_kind(column), None);
+    }
+}
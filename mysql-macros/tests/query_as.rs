@@ -0,0 +1,79 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! These queries are checked against the offline data in `.mysql` unless `DATABASE_URL` is set.
+
+use mysql::{prelude::FromRow, Params, Value};
+use mysql_macros::query_as;
+
+#[derive(Debug, PartialEq, Eq, FromRow)]
+struct Foo {
+    id: Option<i64>,
+    name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, FromRow)]
+struct Account {
+    id: Option<i64>,
+    #[mysql(rename = "account_name")]
+    name: Option<String>,
+}
+
+#[test]
+fn should_expand_positional_query() {
+    let query = query_as!(
+        Foo,
+        "SELECT CAST(? AS SIGNED) AS id, CAST(? AS CHAR) AS name",
+        42,
+        "foo",
+    )
+    .into_inner();
+    assert_eq!(
+        query.params,
+        Params::Positional(vec![Value::Int(42), Value::from("foo")])
+    );
+}
+
+#[test]
+fn should_expand_named_query() {
+    let query = query_as!(
+        Foo,
+        "SELECT CAST(:id AS SIGNED) AS id, CAST(:name AS CHAR) AS name",
+        name = "foo",
+        id = 42,
+    )
+    .into_inner();
+    assert_eq!(
+        query.params,
+        Params::Named(
+            vec![
+                (b"id".to_vec(), Value::Int(42)),
+                (b"name".to_vec(), Value::from("foo"))
+            ]
+            .into_iter()
+            .collect()
+        )
+    );
+}
+
+#[test]
+fn should_expand_query_with_renamed_fields() {
+    let query = query_as!(
+        Account {
+            name: "account_name"
+        },
+        "SELECT CAST(? AS SIGNED) AS id, CAST(? AS CHAR) AS account_name",
+        42,
+        "foo",
+    )
+    .into_inner();
+    assert_eq!(
+        query.params,
+        Params::Positional(vec![Value::Int(42), Value::from("foo")])
+    );
+}
@@ -0,0 +1,470 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Offline binlog files (see [`BinlogFile`] and [`BinlogWriter`]).
+
+use mysql_common::binlog::{
+    consts::{BinlogVersion::Version4, EventType},
+    events::{BinlogEventHeader, Event, RotateEvent, TableMapEvent},
+    BinlogFileHeader, EventStreamReader,
+};
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::BinlogStream;
+
+/// `HEARTBEAT_LOG_EVENT_V2` (MySql 8.0.26+) isn't known to [`EventType`].
+const HEARTBEAT_LOG_EVENT_V2: u8 = 0x29;
+
+/// Binlog file reader.
+///
+/// It's an iterator over events of a binlog file (i.e. a file written by the server,
+/// by `mysqlbinlog --raw` or by [`BinlogWriter`]). Events are parsed using the same
+/// reader as the one used by [`BinlogStream`], so it's possible to process
+/// archived binlogs the same way as a live stream.
+///
+/// ```no_run
+/// # use mysql::*;
+/// # fn main() -> Result<()> {
+/// for event in BinlogFile::open("binlog.000001")? {
+///     let event = event?;
+///     println!("{:?}", event.header().event_type());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+#[derive(Debug)]
+pub struct BinlogFile<R = BufReader<File>> {
+    read: R,
+    esr: EventStreamReader,
+    terminated: bool,
+}
+
+impl BinlogFile {
+    /// Opens a binlog file at the given path.
+    ///
+    /// Returns an error if the file doesn't start with the binlog file header (`\xfebin`).
+    pub fn open<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let file = File::open(path)?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: BufRead> BinlogFile<R> {
+    /// Creates a binlog file reader on top of the given reader
+    /// (e.g. on top of a fixture included using `include_bytes!`).
+    ///
+    /// Returns an error if the input doesn't start with the binlog file header (`\xfebin`).
+    pub fn new(mut read: R) -> crate::Result<Self> {
+        BinlogFileHeader::read(&mut read)?;
+        Ok(Self {
+            read,
+            esr: EventStreamReader::new(Version4),
+            terminated: false,
+        })
+    }
+
+    /// Returns a table map event for the given table id.
+    pub fn get_tme(&self, table_id: u64) -> Option<&TableMapEvent<'static>> {
+        self.esr.get_tme(table_id)
+    }
+
+    /// Reads the next event (`None` means the end of the file).
+    fn read_event(&mut self) -> io::Result<Option<Event>> {
+        if self.read.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        // The whole event is read upfront, so that a truncated file results in an error.
+        let mut buf = vec![0_u8; BinlogEventHeader::LEN];
+        self.read.read_exact(&mut buf)?;
+        let event_size = u32::from_le_bytes(buf[9..13].try_into().unwrap()) as usize;
+        if event_size < BinlogEventHeader::LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid binlog event size: {}", event_size),
+            ));
+        }
+        buf.resize(event_size, 0);
+        self.read
+            .read_exact(&mut buf[BinlogEventHeader::LEN..])
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated binlog event")
+                }
+                _ => err,
+            })?;
+
+        self.esr.read(&buf[..])
+    }
+}
+
+impl<R: BufRead> Iterator for BinlogFile<R> {
+    type Item = crate::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.terminated {
+            return None;
+        }
+
+        match self.read_event() {
+            Ok(event) => event.map(Ok),
+            Err(err) => {
+                self.terminated = true;
+                Some(Err(err.into()))
+            }
+        }
+    }
+}
+
+/// Writes binlog events into local binlog files (much like `mysqlbinlog --raw` does).
+///
+/// Files are created within the given directory and named after the server's binlog files.
+/// Rotate events are used to switch to the next file, heartbeat events are skipped.
+///
+/// If a rotate event points into an existing file (i.e. the stream was resumed),
+/// then this file is truncated to the given position and new events are appended to it.
+///
+/// ```no_run
+/// # use mysql::*;
+/// # fn main() -> Result<()> {
+/// # let pool = Pool::new("mysql://root@localhost:3307/mysql")?;
+/// let conn = pool.get_conn()?.unwrap();
+/// let mut stream = conn.get_binlog_stream(BinlogRequest::new(42))?;
+///
+/// let mut writer = BinlogWriter::new("binlogs");
+/// writer.write_stream(&mut stream)?;
+///
+/// // later on
+/// for event in BinlogFile::open("binlogs/binlog.000001")? {
+///     // ...
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+#[derive(Debug)]
+pub struct BinlogWriter {
+    dir: PathBuf,
+    current: Option<(PathBuf, File)>,
+}
+
+impl BinlogWriter {
+    /// Creates a writer, that will write binlog files into the given directory.
+    ///
+    /// The directory must exist.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            current: None,
+        }
+    }
+
+    /// Returns the path of the file currently being written.
+    pub fn current_file(&self) -> Option<&Path> {
+        self.current.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// Writes every event of the stream until the stream ends (or until an error).
+    ///
+    /// Events are written as soon as they arrive, so use
+    /// [`BinlogStream::position`] to resume the stream after an error.
+    pub fn write_stream(&mut self, stream: &mut BinlogStream) -> crate::Result<()> {
+        for event in stream {
+            self.write_event(&event?)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a single event.
+    ///
+    /// The first event must be a rotate event (the server always starts a binlog stream
+    /// with a rotate event), otherwise an error is returned.
+    pub fn write_event(&mut self, event: &Event) -> io::Result<()> {
+        let header = event.header();
+        if header.event_type_raw() == HEARTBEAT_LOG_EVENT_V2 {
+            return Ok(());
+        }
+        match header.event_type() {
+            Ok(EventType::HEARTBEAT_EVENT) => Ok(()),
+            Ok(EventType::ROTATE_EVENT) => {
+                let rotate = event.read_event::<RotateEvent>()?;
+                // A real rotate event closes the current file. A fake one
+                // (with zero log position) is not a part of the binlog.
+                if header.log_pos() > 0 {
+                    self.append(event)?;
+                }
+                self.rotate(rotate.name_raw(), rotate.position())
+            }
+            Ok(EventType::FORMAT_DESCRIPTION_EVENT) if header.log_pos() == 0 => {
+                // An artificial format description event is sent if the stream starts
+                // in the middle of a file. It's only needed if the file is a new one.
+                let (_, file) = self.current()?;
+                if file.metadata()?.len() == BinlogFileHeader::LEN as u64 {
+                    self.append(event)?;
+                }
+                Ok(())
+            }
+            _ => self.append(event),
+        }
+    }
+
+    /// Switches to the given file and position.
+    fn rotate(&mut self, name: &[u8], position: u64) -> io::Result<()> {
+        let name = std::str::from_utf8(name)
+            .ok()
+            .filter(|name| Path::new(name).file_name() == Some(name.as_ref()))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "invalid binlog file name: {}",
+                        String::from_utf8_lossy(name)
+                    ),
+                )
+            })?;
+        let path = self.dir.join(name);
+        if self.current_file() == Some(path.as_path()) {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let len = file.metadata()?.len();
+        let position = position.max(BinlogFileHeader::LEN as u64);
+        if len == 0 {
+            BinlogFileHeader.write(Version4, &mut file)?;
+        } else if len < position {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "binlog file {} is shorter than the requested position {}",
+                    path.display(),
+                    position
+                ),
+            ));
+        } else {
+            BinlogFileHeader::read(&mut file)?;
+            file.set_len(position)?;
+            file.seek(SeekFrom::End(0))?;
+        }
+
+        self.current = Some((path, file));
+        Ok(())
+    }
+
+    /// Appends the event to the current file.
+    fn append(&mut self, event: &Event) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(event.header().event_size() as usize);
+        event.write(Version4, &mut buf)?;
+        let (_, file) = self.current()?;
+        file.write_all(&buf)
+    }
+
+    fn current(&mut self) -> io::Result<&mut (PathBuf, File)> {
+        self.current.as_mut().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "binlog stream must start with a rotate event",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        binlog::{
+            consts::{BinlogVersion::Version4, EventFlags, EventType},
+            events::{BinlogEventHeader, Event, FormatDescriptionEvent, QueryEvent, RotateEvent},
+            BinlogFileHeader, EventStreamReader,
+        },
+        proto::MySerialize,
+    };
+
+    use std::fs;
+
+    use super::{BinlogFile, BinlogWriter};
+
+    /// Serializes an event (`log_pos` is the position right after the event).
+    fn event<T: MySerialize>(event_type: EventType, start: u32, data: T) -> Vec<u8> {
+        let mut body = Vec::new();
+        data.serialize(&mut body);
+        let size = (BinlogEventHeader::LEN + body.len()) as u32;
+        let log_pos = if start == 0 { 0 } else { start + size };
+        let mut buf = Vec::new();
+        BinlogEventHeader::new(0, event_type, 1, size, log_pos, EventFlags::empty())
+            .serialize(&mut buf);
+        buf.extend(body);
+        buf
+    }
+
+    fn fde(start: u32) -> Vec<u8> {
+        event(
+            EventType::FORMAT_DESCRIPTION_EVENT,
+            start,
+            FormatDescriptionEvent::new(Version4),
+        )
+    }
+
+    fn query(start: u32, query: &'static str) -> Vec<u8> {
+        event(
+            EventType::QUERY_EVENT,
+            start,
+            QueryEvent::new(&b""[..], &b"db"[..]).with_query(query.as_bytes()),
+        )
+    }
+
+    fn rotate(start: u32, name: &'static str, position: u64) -> Vec<u8> {
+        event(
+            EventType::ROTATE_EVENT,
+            start,
+            RotateEvent::new(position, name.as_bytes()),
+        )
+    }
+
+    fn queries<R: std::io::BufRead>(file: BinlogFile<R>) -> Vec<String> {
+        file.map(|event| event.unwrap())
+            .filter(|event| event.header().event_type() == Ok(EventType::QUERY_EVENT))
+            .map(|event| {
+                let event = event.read_event::<QueryEvent>().unwrap();
+                event.query().into_owned()
+            })
+            .collect()
+    }
+
+    /// Fake binlog file with a format description event and two queries.
+    fn fixture() -> Vec<u8> {
+        let mut bytes = BinlogFileHeader::VALUE.to_vec();
+        bytes.extend(fde(bytes.len() as u32));
+        bytes.extend(query(bytes.len() as u32, "BEGIN"));
+        bytes.extend(query(bytes.len() as u32, "COMMIT"));
+        bytes
+    }
+
+    #[test]
+    fn should_read_binlog_file() {
+        let bytes = fixture();
+        let events = BinlogFile::new(&bytes[..])
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        let types = events
+            .iter()
+            .map(|event| event.header().event_type().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                EventType::FORMAT_DESCRIPTION_EVENT,
+                EventType::QUERY_EVENT,
+                EventType::QUERY_EVENT
+            ]
+        );
+        assert_eq!(events[2].header().log_pos() as usize, bytes.len());
+        assert_eq!(
+            queries(BinlogFile::new(&bytes[..]).unwrap()),
+            vec!["BEGIN", "COMMIT"]
+        );
+
+        assert!(BinlogFile::new(&bytes[1..]).is_err());
+
+        let mut file = BinlogFile::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(file.next().unwrap().is_ok());
+        assert!(file.next().unwrap().is_ok());
+        assert!(file.next().unwrap().is_err());
+        assert!(file.next().is_none());
+    }
+
+    #[test]
+    fn should_write_rotating_binlog_files() {
+        let dir = std::env::temp_dir().join(format!("mysql-binlog-writer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // Events in the order they are sent by the server.
+        let first = fixture();
+        let mut stream = vec![rotate(0, "binlog.000001", 4)];
+        stream.push(first[4..].to_vec());
+        stream.push(rotate(first.len() as u32, "binlog.000002", 4));
+        stream.push(rotate(0, "binlog.000002", 4));
+        stream.push(fde(4));
+        let second_start = 4 + stream.last().unwrap().len() as u32;
+        stream.push(query(second_start, "INSERT"));
+        stream.push(event(
+            EventType::HEARTBEAT_EVENT,
+            0,
+            RotateEvent::new(0, &b"x"[..]),
+        ));
+
+        let mut reader = EventStreamReader::new(Version4);
+        let mut events: Vec<Event> = Vec::new();
+        for bytes in &stream {
+            let mut input = &bytes[..];
+            while let Some(event) = reader.read(&mut input).unwrap() {
+                events.push(event);
+            }
+        }
+
+        let mut writer = BinlogWriter::new(&dir);
+        assert!(writer.write_event(&events[1]).is_err());
+        for event in &events {
+            writer.write_event(event).unwrap();
+        }
+        assert_eq!(
+            writer.current_file(),
+            Some(dir.join("binlog.000002").as_path())
+        );
+        drop(writer);
+
+        assert_eq!(
+            queries(BinlogFile::open(dir.join("binlog.000001")).unwrap()),
+            vec!["BEGIN", "COMMIT"]
+        );
+        assert_eq!(
+            queries(BinlogFile::open(dir.join("binlog.000002")).unwrap()),
+            vec!["INSERT"]
+        );
+
+        // resume in the middle of the second file
+        let mut writer = BinlogWriter::new(&dir);
+        let resume = [
+            rotate(0, "binlog.000002", second_start as u64),
+            fde(0),
+            query(second_start, "UPDATE"),
+        ];
+        let mut reader = EventStreamReader::new(Version4);
+        for bytes in &resume {
+            let event = reader.read(&bytes[..]).unwrap().unwrap();
+            writer.write_event(&event).unwrap();
+        }
+        drop(writer);
+        assert_eq!(
+            queries(BinlogFile::open(dir.join("binlog.000002")).unwrap()),
+            vec!["UPDATE"]
+        );
+
+        let mut writer = BinlogWriter::new(&dir);
+        let event = reader
+            .read(&rotate(0, "../escape", 4)[..])
+            .unwrap()
+            .unwrap();
+        assert!(writer.write_event(&event).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
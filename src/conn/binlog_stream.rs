@@ -8,31 +8,183 @@
 
 use mysql_common::{
     binlog::{
-        consts::BinlogVersion::Version4,
-        events::{Event, TableMapEvent},
+        consts::{BinlogVersion::Version4, EventType},
+        events::{Event, EventData, GtidEvent, QueryEvent, RotateEvent, TableMapEvent},
+        row::BinlogRow,
         EventStreamReader,
     },
     io::ParseBuf,
-    packets::{ErrPacket, NetworkStreamTerminator, OkPacketDeserializer},
+    packets::{
+        binlog_request::BinlogRequest, ErrPacket, NetworkStreamTerminator, OkPacketDeserializer,
+        Sid,
+    },
 };
 
-use crate::Conn;
+use mysql_common::row::new_row_raw;
+
+use std::{collections::HashMap, io, mem, time::Duration};
+
+use crate::{prelude::*, Column, Conn, GtidSet, Opts, Row};
+
+/// Binlog stream options.
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BinlogStreamOpts {
+    heartbeat_period: Option<Duration>,
+}
+
+impl BinlogStreamOpts {
+    /// Calls `Self::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines the heartbeat period (defaults to `None`, i.e. the server default).
+    ///
+    /// The server will send a heartbeat event if there were no events for the given
+    /// period of time, so it's possible to detect a broken connection using read timeout
+    /// (see [`crate::OptsBuilder::read_timeout`]).
+    pub fn with_heartbeat_period(mut self, heartbeat_period: Option<Duration>) -> Self {
+        self.heartbeat_period = heartbeat_period;
+        self
+    }
+
+    /// Returns the heartbeat period.
+    pub fn heartbeat_period(&self) -> Option<Duration> {
+        self.heartbeat_period
+    }
+}
+
+/// Position of the last committed transaction in the binlog.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct PositionTracker {
+    filename: Vec<u8>,
+    position: u64,
+    gtid_set: GtidSet,
+    /// An event group (started by a GTID event or by `BEGIN`) is not committed yet.
+    in_transaction: bool,
+    /// The group was started by `BEGIN` (or `XA START`), so only `COMMIT`, `ROLLBACK`,
+    /// `XA COMMIT` or `XA ROLLBACK` ends it.
+    explicit: bool,
+    pending_gtid: Option<([u8; 16], u64)>,
+    /// Number of commits seen so far.
+    commits: u64,
+}
+
+impl PositionTracker {
+    fn new(request: &BinlogRequest<'_>) -> Self {
+        Self {
+            filename: request.filename_raw().to_vec(),
+            position: request.pos(),
+            gtid_set: GtidSet::from_sids(request.sids()),
+            ..Default::default()
+        }
+    }
+
+    /// Binlog file was switched.
+    fn rotate(&mut self, filename: &[u8], position: u64) {
+        self.filename = filename.to_vec();
+        self.position = position;
+    }
+
+    /// Transaction with the given GTID has started.
+    fn gtid(&mut self, sid: [u8; 16], gno: u64) {
+        self.in_transaction = true;
+        self.pending_gtid = Some((sid, gno));
+    }
+
+    /// Transaction without GTID has started (i.e. anonymous GTID event).
+    fn anonymous_gtid(&mut self) {
+        self.in_transaction = true;
+    }
+
+    /// `BEGIN` or `XA START` query event.
+    fn begin(&mut self) {
+        self.in_transaction = true;
+        self.explicit = true;
+    }
+
+    /// Query event other than `BEGIN`.
+    ///
+    /// Inside of an explicit transaction it's a statement-based event unless it ends
+    /// the transaction, otherwise it's a self-contained statement (e.g. DDL).
+    fn query(&mut self, query: &[u8], log_pos: u32) {
+        if !self.explicit || ends_transaction(query) {
+            self.commit(log_pos);
+        } else {
+            self.event(log_pos);
+        }
+    }
+
+    /// Transaction was committed.
+    fn commit(&mut self, log_pos: u32) {
+        if log_pos > 0 {
+            self.position = log_pos.into();
+        }
+        if let Some((sid, gno)) = self.pending_gtid.take() {
+            self.gtid_set.add(sid, gno);
+        }
+        self.in_transaction = false;
+        self.explicit = false;
+        self.commits += 1;
+    }
+
+    /// Any other event.
+    fn event(&mut self, log_pos: u32) {
+        if !self.in_transaction && log_pos > 0 {
+            self.position = log_pos.into();
+        }
+    }
+
+    /// Forgets the uncommitted transaction.
+    fn rollback(&mut self) {
+        self.in_transaction = false;
+        self.explicit = false;
+        self.pending_gtid = None;
+    }
+}
 
 /// Binlog event stream.
 ///
 /// Stream initialization is lazy, i.e. binlog won't be requested until this stream is polled.
+///
+/// The stream keeps track of the position (and of the executed GTID set) of the last committed
+/// transaction, so that it's possible to [resume](BinlogStream::resume) it after an error.
 #[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
 pub struct BinlogStream {
     conn: Option<Conn>,
     esr: EventStreamReader,
+    opts: Opts,
+    request: BinlogRequest<'static>,
+    stream_opts: BinlogStreamOpts,
+    tracker: PositionTracker,
 }
 
 impl BinlogStream {
     /// `conn` is a `Conn` with `request_binlog` executed on it.
-    pub(super) fn new(conn: Conn) -> Self {
+    pub(super) fn new(
+        conn: Conn,
+        request: &BinlogRequest<'_>,
+        stream_opts: BinlogStreamOpts,
+    ) -> Self {
+        let request = BinlogRequest::new(request.server_id())
+            .with_use_gtid(request.use_gtid())
+            .with_flags(request.flags())
+            .with_filename(request.filename_raw().to_vec())
+            .with_pos(request.pos())
+            .with_sids(
+                request
+                    .sids()
+                    .iter()
+                    .map(|sid| Sid::new(sid.uuid()).with_intervals(sid.intervals().to_vec())),
+            );
         BinlogStream {
+            opts: conn.0.opts.clone(),
             conn: Some(conn),
             esr: EventStreamReader::new(Version4),
+            tracker: PositionTracker::new(&request),
+            request,
+            stream_opts,
         }
     }
 
@@ -40,6 +192,91 @@ impl BinlogStream {
     pub fn get_tme(&self, table_id: u64) -> Option<&TableMapEvent<'static>> {
         self.esr.get_tme(table_id)
     }
+
+    /// Returns the name of the binlog file of the last committed transaction.
+    pub fn filename(&self) -> &[u8] {
+        &self.tracker.filename
+    }
+
+    /// Returns the position right after the last committed transaction.
+    pub fn position(&self) -> u64 {
+        self.tracker.position
+    }
+
+    /// Returns the requested GTID set extended with GTIDs of committed transactions.
+    pub fn gtid_set(&self) -> &GtidSet {
+        &self.tracker.gtid_set
+    }
+
+    /// Returns `true` if the stream was terminated (i.e. because of an error).
+    pub fn is_terminated(&self) -> bool {
+        self.conn.is_none()
+    }
+
+    /// Reconnects to the server and requests the binlog starting right after
+    /// the last committed transaction.
+    ///
+    /// Events of a transaction, that wasn't committed at the moment of the error,
+    /// will be received again. If the stream was requested using GTIDs, then the
+    /// [executed GTID set](BinlogStream::gtid_set) is used to resume it.
+    pub fn resume(&mut self) -> crate::Result<()> {
+        self.conn = None;
+
+        let mut request = self
+            .request
+            .clone()
+            .with_filename(self.tracker.filename.clone())
+            .with_pos(self.tracker.position);
+        if request.use_gtid() {
+            request = request.with_sids(self.tracker.gtid_set.to_sids());
+        }
+
+        let mut conn = Conn::new(self.opts.clone())?;
+        conn.request_binlog(&request, &self.stream_opts)?;
+
+        self.conn = Some(conn);
+        self.esr = EventStreamReader::new(Version4);
+        self.tracker.rollback();
+        Ok(())
+    }
+
+    /// Turns this stream into an iterator over row changes grouped by transaction
+    /// (see [`RowChanges`]).
+    pub fn row_changes(self) -> RowChanges {
+        RowChanges {
+            stream: self,
+            side_conn: None,
+            column_names: HashMap::new(),
+            pending: Vec::new(),
+            discard: false,
+        }
+    }
+
+    /// Updates the position according to the event.
+    fn track(&mut self, event: &Event) {
+        let log_pos = event.header().log_pos();
+        match event.header().event_type() {
+            Ok(EventType::ROTATE_EVENT) => {
+                if let Ok(rotate) = event.read_event::<RotateEvent<'_>>() {
+                    self.tracker.rotate(rotate.name_raw(), rotate.position());
+                }
+            }
+            Ok(EventType::GTID_EVENT) => match event.read_event::<GtidEvent>() {
+                Ok(gtid) => self.tracker.gtid(gtid.sid(), gtid.gno()),
+                Err(_) => self.tracker.anonymous_gtid(),
+            },
+            Ok(EventType::ANONYMOUS_GTID_EVENT) => self.tracker.anonymous_gtid(),
+            Ok(EventType::QUERY_EVENT) => match event.read_event::<QueryEvent<'_>>() {
+                Ok(query) if starts_transaction(query.query_raw()) => self.tracker.begin(),
+                Ok(query) => self.tracker.query(query.query_raw(), log_pos),
+                Err(_) => self.tracker.commit(log_pos),
+            },
+            Ok(EventType::XID_EVENT) | Ok(EventType::XA_PREPARE_LOG_EVENT) => {
+                self.tracker.commit(log_pos)
+            }
+            _ => self.tracker.event(log_pos),
+        }
+    }
 }
 
 impl Iterator for BinlogStream {
@@ -78,7 +315,11 @@ impl Iterator for BinlogStream {
         if first_byte == Some(0) {
             let event_data = &packet[1..];
             match self.esr.read(event_data) {
-                Ok(event) => Some(Ok(event?)),
+                Ok(Some(event)) => {
+                    self.track(&event);
+                    Some(Ok(event))
+                }
+                Ok(None) => None,
                 Err(err) => Some(Err(err.into())),
             }
         } else {
@@ -87,3 +328,415 @@ impl Iterator for BinlogStream {
         }
     }
 }
+
+/// Kind of a row change.
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RowChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Change of a single row.
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowChange {
+    /// Database name.
+    pub schema: String,
+    /// Table name.
+    pub table: String,
+    /// Kind of the change.
+    pub kind: RowChangeKind,
+    /// Row before the change (`None` for inserts).
+    pub before: Option<Row>,
+    /// Row after the change (`None` for deletes).
+    pub after: Option<Row>,
+}
+
+/// Row changes of a single committed transaction.
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionChanges {
+    /// Row changes in the order of execution.
+    pub changes: Vec<RowChange>,
+    /// Name of the binlog file containing the commit.
+    pub filename: Vec<u8>,
+    /// Position right after the commit.
+    pub position: u64,
+}
+
+/// Iterator over row changes of a [`BinlogStream`], grouped by transaction.
+///
+/// Rows events are combined with corresponding table map events. Column names are taken from
+/// the binlog if the server is running with `binlog_row_metadata=FULL`, otherwise they are
+/// queried from `information_schema` using a side connection (note that in this case names
+/// reflect the current table definition rather than the one at the moment of the change).
+/// Columns, that couldn't be named, are named `@<offset>`.
+///
+/// Transaction without row changes (i.e. DDL) are skipped. If an error occurs while decoding
+/// a transaction, then the error is returned and the rest of the transaction is discarded.
+/// Partial JSON updates (`binlog_row_value_options=PARTIAL_JSON`) are not supported.
+///
+/// ```no_run
+/// # use mysql::*;
+/// # fn main() -> Result<()> {
+/// # let opts = Opts::from_url("mysql://root@localhost").unwrap();
+/// let mut conn = Conn::new(opts)?;
+/// let request = conn.gtid_executed()?.binlog_request(42);
+/// for transaction in conn.get_binlog_stream(request)?.row_changes() {
+///     for change in transaction?.changes {
+///         println!("{:?} {}.{}: {:?}", change.kind, change.schema, change.table, change.after);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+pub struct RowChanges {
+    stream: BinlogStream,
+    side_conn: Option<Conn>,
+    column_names: HashMap<(String, String), Vec<String>>,
+    pending: Vec<RowChange>,
+    /// Changes of the current transaction should be discarded.
+    discard: bool,
+}
+
+impl RowChanges {
+    /// Returns the underlying stream.
+    pub fn stream(&self) -> &BinlogStream {
+        &self.stream
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> BinlogStream {
+        self.stream
+    }
+
+    /// Resumes the underlying stream (see [`BinlogStream::resume`]).
+    ///
+    /// Changes of the uncommitted transaction are discarded, because they will be received again.
+    pub fn resume(&mut self) -> crate::Result<()> {
+        self.pending.clear();
+        self.discard = false;
+        self.stream.resume()
+    }
+
+    /// Handles a single binlog event.
+    fn handle(&mut self, event: &Event) -> crate::Result<()> {
+        use EventType::*;
+
+        match event.header().event_type() {
+            Ok(QUERY_EVENT) => {
+                let query = event.read_event::<QueryEvent<'_>>()?;
+                if query.query_raw() == b"BEGIN" {
+                    self.pending.clear();
+                    self.discard = false;
+                } else if query.query_raw() != b"COMMIT" {
+                    // table definitions might have changed
+                    self.column_names.clear();
+                }
+                Ok(())
+            }
+            Ok(WRITE_ROWS_EVENT_V1 | UPDATE_ROWS_EVENT_V1 | DELETE_ROWS_EVENT_V1)
+            | Ok(WRITE_ROWS_EVENT | UPDATE_ROWS_EVENT | DELETE_ROWS_EVENT)
+            | Ok(PARTIAL_UPDATE_ROWS_EVENT)
+                if !self.discard =>
+            {
+                self.handle_rows(event).inspect_err(|_| {
+                    self.pending.clear();
+                    self.discard = true;
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Decodes rows of a rows event.
+    fn handle_rows(&mut self, event: &Event) -> crate::Result<()> {
+        let rows_event = match event.read_data()? {
+            Some(EventData::RowsEvent(rows_event)) => rows_event,
+            _ => return Ok(()),
+        };
+        let tme = self.stream.get_tme(rows_event.table_id()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown table id {}", rows_event.table_id()),
+            )
+        })?;
+        let schema = tme.database_name().into_owned();
+        let table = tme.table_name().into_owned();
+        let rows = rows_event.rows(tme).collect::<io::Result<Vec<_>>>()?;
+
+        let unnamed = rows
+            .first()
+            .and_then(|(before, after)| before.as_ref().or(after.as_ref()))
+            .map(|row| {
+                row.columns_ref()
+                    .iter()
+                    .any(|c| unnamed_offset(c).is_some())
+            })
+            .unwrap_or(false);
+        let names = if unnamed {
+            self.column_names(&schema, &table)?
+        } else {
+            Vec::new()
+        };
+
+        for (before, after) in rows {
+            let kind = match (&before, &after) {
+                (Some(_), Some(_)) => RowChangeKind::Update,
+                (None, Some(_)) => RowChangeKind::Insert,
+                _ => RowChangeKind::Delete,
+            };
+            self.pending.push(RowChange {
+                schema: schema.clone(),
+                table: table.clone(),
+                kind,
+                before: before.map(|row| to_row(row, &names)).transpose()?,
+                after: after.map(|row| to_row(row, &names)).transpose()?,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns column names of the given table (queried using the side connection).
+    fn column_names(&mut self, schema: &str, table: &str) -> crate::Result<Vec<String>> {
+        let key = (schema.to_owned(), table.to_owned());
+        if let Some(names) = self.column_names.get(&key) {
+            return Ok(names.clone());
+        }
+
+        let conn = match self.side_conn {
+            Some(ref mut conn) => conn,
+            None => self.side_conn.insert(Conn::new(self.stream.opts.clone())?),
+        };
+        let names: Vec<String> = conn.exec(
+            "SELECT COLUMN_NAME FROM information_schema.COLUMNS \
+             WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+            (schema, table),
+        )?;
+        self.column_names.insert(key, names.clone());
+        Ok(names)
+    }
+}
+
+impl Iterator for RowChanges {
+    type Item = crate::Result<TransactionChanges>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let commits = self.stream.tracker.commits;
+            let event = match self.stream.next()? {
+                Ok(event) => event,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Err(err) = self.handle(&event) {
+                return Some(Err(err));
+            }
+            if self.stream.tracker.commits != commits {
+                self.discard = false;
+                if !self.pending.is_empty() {
+                    return Some(Ok(TransactionChanges {
+                        changes: mem::take(&mut self.pending),
+                        filename: self.stream.filename().to_vec(),
+                        position: self.stream.position(),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Returns the column offset if the column wasn't named (i.e. it's named `@<offset>`).
+fn unnamed_offset(column: &Column) -> Option<usize> {
+    column.name_str().strip_prefix('@')?.parse().ok()
+}
+
+/// Converts a binlog row into a `Row`, naming unnamed columns using the given names.
+fn to_row(row: BinlogRow, names: &[String]) -> crate::Result<Row> {
+    let row = Row::try_from(row).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    if names.is_empty() {
+        return Ok(row);
+    }
+    let columns = row
+        .columns_ref()
+        .iter()
+        .map(
+            |column| match unnamed_offset(column).and_then(|i| names.get(i)) {
+                Some(name) => column
+                    .clone()
+                    .with_name(name.as_bytes())
+                    .with_org_name(name.as_bytes()),
+                None => column.clone(),
+            },
+        )
+        .collect::<Vec<_>>();
+    Ok(new_row_raw(row.unwrap_raw(), columns.into()))
+}
+
+/// Returns the first two words of a query in upper case.
+fn leading_words(query: &[u8]) -> (String, String) {
+    let query = String::from_utf8_lossy(query);
+    let mut words = query
+        .split(|c: char| c.is_ascii_whitespace() || c == ';')
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_uppercase());
+    (
+        words.next().unwrap_or_default(),
+        words.next().unwrap_or_default(),
+    )
+}
+
+/// Returns `true` for `BEGIN` and `XA START` queries.
+fn starts_transaction(query: &[u8]) -> bool {
+    match leading_words(query) {
+        (first, _) if first == "BEGIN" => true,
+        (first, second) => first == "XA" && (second == "START" || second == "BEGIN"),
+    }
+}
+
+/// Returns `true` for `COMMIT`, `ROLLBACK` (but not `ROLLBACK TO SAVEPOINT`),
+/// `XA COMMIT` and `XA ROLLBACK` queries.
+fn ends_transaction(query: &[u8]) -> bool {
+    let (first, second) = leading_words(query);
+    match &*first {
+        "COMMIT" => true,
+        "ROLLBACK" => second != "TO",
+        "XA" => second == "COMMIT" || second == "ROLLBACK",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        binlog::{row::BinlogRow, value::BinlogValue},
+        constants::ColumnType,
+        packets::binlog_request::BinlogRequest,
+    };
+
+    use super::{starts_transaction, to_row, unnamed_offset, PositionTracker};
+    use crate::{Column, Value};
+
+    #[test]
+    fn should_name_unnamed_columns() {
+        let columns = vec![
+            Column::new(ColumnType::MYSQL_TYPE_LONG).with_name(b"@0"),
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_name(b"name"),
+            Column::new(ColumnType::MYSQL_TYPE_LONG).with_name(b"@2"),
+        ];
+        assert_eq!(unnamed_offset(&columns[0]), Some(0));
+        assert_eq!(unnamed_offset(&columns[1]), None);
+
+        let values = vec![
+            Some(BinlogValue::Value(Value::Int(1))),
+            Some(BinlogValue::Value(Value::from("foo"))),
+            Some(BinlogValue::Value(Value::NULL)),
+        ];
+        let binlog_row = BinlogRow::new(values, columns.into());
+
+        let row = to_row(binlog_row.clone(), &[]).unwrap();
+        assert_eq!(row.columns_ref()[0].name_str(), "@0");
+
+        let names = vec!["id".to_string(), "title".to_string()];
+        let row = to_row(binlog_row, &names).unwrap();
+        let names = row
+            .columns_ref()
+            .iter()
+            .map(|column| column.name_str().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["id", "name", "@2"]);
+        assert_eq!(row.get::<i32, _>("id"), Some(1));
+    }
+
+    #[test]
+    fn should_track_committed_position() {
+        let sid = [1; 16];
+        let mut tracker = PositionTracker::new(
+            &BinlogRequest::new(1)
+                .with_filename(&b"binlog.000001"[..])
+                .with_pos(4_u64),
+        );
+        assert_eq!(tracker.filename, b"binlog.000001");
+        assert_eq!(tracker.position, 4);
+
+        // format description event
+        tracker.event(126);
+        assert_eq!(tracker.position, 126);
+
+        // DML transaction
+        tracker.gtid(sid, 1);
+        tracker.begin();
+        tracker.event(300);
+        tracker.event(400);
+        assert_eq!(tracker.position, 126);
+        assert!(!tracker.gtid_set.contains(sid, 1));
+        tracker.commit(431);
+        assert_eq!(tracker.position, 431);
+        assert!(tracker.gtid_set.contains(sid, 1));
+
+        // DDL
+        tracker.gtid(sid, 2);
+        tracker.query(b"CREATE TABLE foo (id INT)", 520);
+        assert_eq!(tracker.position, 520);
+        assert!(tracker.gtid_set.contains(sid, 2));
+
+        // interrupted transaction
+        tracker.gtid(sid, 3);
+        tracker.event(600);
+        tracker.rollback();
+        assert_eq!(tracker.position, 520);
+        assert!(!tracker.gtid_set.contains(sid, 3));
+
+        // binlog rotation
+        tracker.rotate(b"binlog.000002", 4);
+        assert_eq!(tracker.filename, b"binlog.000002");
+        assert_eq!(tracker.position, 4);
+    }
+
+    #[test]
+    fn should_track_statement_based_transactions() {
+        let sid = [1; 16];
+        let mut tracker = PositionTracker::new(&BinlogRequest::new(1).with_pos(4_u64));
+
+        // BEGIN; INSERT; XID
+        tracker.gtid(sid, 1);
+        assert!(starts_transaction(b"BEGIN"));
+        tracker.begin();
+        tracker.query(b"INSERT INTO foo VALUES (1)", 300);
+        tracker.query(b"ROLLBACK TO SAVEPOINT sp", 350);
+        assert_eq!(tracker.position, 4);
+        assert!(!tracker.gtid_set.contains(sid, 1));
+        assert_eq!(tracker.commits, 0);
+        tracker.commit(400);
+        assert_eq!(tracker.position, 400);
+        assert!(tracker.gtid_set.contains(sid, 1));
+        assert_eq!(tracker.commits, 1);
+
+        // BEGIN; INSERT; COMMIT (non-transactional table)
+        tracker.anonymous_gtid();
+        tracker.begin();
+        tracker.query(b"INSERT INTO bar VALUES (1)", 500);
+        assert_eq!(tracker.position, 400);
+        tracker.query(b"COMMIT", 550);
+        assert_eq!(tracker.position, 550);
+        assert_eq!(tracker.commits, 2);
+
+        // XA START; INSERT; XA END; XA PREPARE
+        tracker.gtid(sid, 2);
+        assert!(starts_transaction(b"XA START X'01',X'',1"));
+        tracker.begin();
+        tracker.query(b"INSERT INTO foo VALUES (2)", 600);
+        tracker.query(b"XA END X'01',X'',1", 650);
+        assert_eq!(tracker.position, 550);
+        tracker.commit(700);
+        assert_eq!(tracker.position, 700);
+
+        // XA COMMIT is a separate group
+        tracker.gtid(sid, 3);
+        tracker.query(b"XA COMMIT X'01',X'',1", 750);
+        assert_eq!(tracker.position, 750);
+        assert!(tracker.gtid_set.contains(sid, 3));
+    }
+}
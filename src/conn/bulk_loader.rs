@@ -0,0 +1,424 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::constants::ColumnType;
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+use crate::{
+    conn::{
+        local_infile::{quote_table, LoadDataFormat, LoadDataInfo},
+        quote_identifier, ConnMut,
+    },
+    prelude::*,
+    DriverError, Error, Params, Result,
+    Value::{self, Bytes, Date, Double, Float, Int, Time, UInt, NULL},
+};
+
+/// Target column of the bulk load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Target {
+    name: String,
+    /// Binary column, that is loaded from hex (see [`BulkLoader`]).
+    hex: bool,
+    /// `BIT` column (integers are loaded as big-endian numbers rather than as text).
+    bit: bool,
+}
+
+/// Bulk loader, that streams rows into a table using `LOAD DATA LOCAL INFILE`.
+///
+/// Rows are serialized into the `LOAD DATA` format on the fly and streamed into
+/// the connection, so there is no temporary file and the local infile handler is not used.
+/// This is much faster than [`Queryable::exec_batch`] for large amounts of data.
+///
+/// *   `NULL` is sent as `\N`;
+/// *   tabs, newlines, backslashes and zero bytes are escaped;
+/// *   values of binary columns (`BINARY`, `VARBINARY`, `BLOB`, `BIT`, `GEOMETRY`) are sent
+///     as hex and decoded using `UNHEX`, so arbitrary bytes are loaded as is;
+/// *   integers are loaded into `BIT` columns as numbers (i.e. `5` is stored as `b'101'`).
+///
+/// Target columns and their types are queried from the server before the first load.
+///
+/// Note that rows are loaded as they go, so use a transaction if partially loaded
+/// data is not acceptable (i.e. if the iterator yields an invalid row).
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// use mysql::*;
+/// use mysql::prelude::*;
+///
+/// let pool = Pool::new(get_opts())?;
+/// let mut conn = pool.get_conn()?;
+///
+/// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT, name TEXT, data BLOB)")?;
+///
+/// let rows = (0..1000).map(|i| (i, format!("name\t{}", i), vec![0_u8, 0xff, i as u8]));
+/// match BulkLoader::new(&mut conn, "mysql.tbl").load_rows(rows) {
+///     Ok(info) => assert_eq!(info.records, 1000),
+///     Err(Error::MySqlError(ref e)) if e.code == 1148 || e.code == 3948 => {
+///         // functionality is not supported by the server
+///         return Ok(());
+///     }
+///     Err(err) => return Err(err.into()),
+/// }
+///
+/// let row: Option<(String, Vec<u8>)> = conn.query_first("SELECT name, data FROM mysql.tbl WHERE id = 42")?;
+/// assert_eq!(row, Some(("name\t42".into(), vec![0, 0xff, 42])));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct BulkLoader<'c, 't, 'tc> {
+    conn: ConnMut<'c, 't, 'tc>,
+    table: String,
+    columns: Vec<String>,
+    targets: Option<Vec<Target>>,
+}
+
+impl<'c, 't, 'tc> BulkLoader<'c, 't, 'tc> {
+    /// Creates a loader for the given table (it may be qualified with a database name,
+    /// i.e. `db.table`).
+    pub fn new<C, T>(conn: C, table: T) -> Self
+    where
+        C: Into<ConnMut<'c, 't, 'tc>>,
+        T: Into<String>,
+    {
+        Self {
+            conn: conn.into(),
+            table: table.into(),
+            columns: Vec::new(),
+            targets: None,
+        }
+    }
+
+    /// Defines the list of columns (defaults to all columns of the table).
+    ///
+    /// Positional rows must contain a value for every column, named rows
+    /// are matched against column names.
+    pub fn columns<I, T>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.columns = columns.into_iter().map(Into::into).collect();
+        self.targets = None;
+        self
+    }
+
+    /// Loads the given rows.
+    ///
+    /// Every row is converted to `Params`, so it could be a tuple, a `Vec<Value>`
+    /// or named params (see [`crate::params!`]).
+    pub fn load_rows<I, P>(&mut self, rows: I) -> Result<LoadDataInfo>
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Params>,
+    {
+        let targets = match self.targets.take() {
+            Some(targets) => targets,
+            None => self.describe_targets()?,
+        };
+        let stmt = statement(&self.table, &targets, self.conn.no_backslash_escape());
+
+        let mut row_error = None;
+        let result = self.conn.query_local_infile(&stmt, |_, writer| {
+            for row in rows {
+                match row_values(row.into(), &targets) {
+                    Ok(values) => write_row(writer, &values, &targets)?,
+                    Err(err) => {
+                        row_error = Some(err);
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid row"));
+                    }
+                }
+            }
+            Ok(())
+        });
+        self.targets = Some(targets);
+
+        if let Some(err) = row_error {
+            return Err(err);
+        }
+        result?;
+        Ok(self.conn.load_data_info())
+    }
+
+    /// Queries names and types of target columns.
+    fn describe_targets(&mut self) -> Result<Vec<Target>> {
+        let columns = if self.columns.is_empty() {
+            "*".into()
+        } else {
+            self.columns
+                .iter()
+                .map(|column| quote_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let describe = self.conn.describe(format!(
+            "SELECT {} FROM {}",
+            columns,
+            quote_table(&self.table)
+        ))?;
+
+        Ok(describe
+            .columns()
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let bit = column.column_type() == ColumnType::MYSQL_TYPE_BIT;
+                Target {
+                    name: self
+                        .columns
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| column.name_str().into_owned()),
+                    hex: bit || (column.is_binary() && is_binary_type(column.column_type())),
+                    bit,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Returns `true` for types, that contain arbitrary bytes if the column is binary.
+fn is_binary_type(column_type: ColumnType) -> bool {
+    use ColumnType::*;
+    matches!(
+        column_type,
+        MYSQL_TYPE_STRING
+            | MYSQL_TYPE_VAR_STRING
+            | MYSQL_TYPE_VARCHAR
+            | MYSQL_TYPE_TINY_BLOB
+            | MYSQL_TYPE_MEDIUM_BLOB
+            | MYSQL_TYPE_LONG_BLOB
+            | MYSQL_TYPE_BLOB
+            | MYSQL_TYPE_GEOMETRY
+    )
+}
+
+/// Returns the `LOAD DATA LOCAL INFILE` statement for the given targets.
+fn statement(table: &str, targets: &[Target], no_backslash_escape: bool) -> String {
+    let mut columns = Vec::with_capacity(targets.len());
+    let mut assignments = Vec::new();
+    for (i, target) in targets.iter().enumerate() {
+        if target.hex {
+            columns.push(format!("@__hex{}", i));
+            assignments.push(format!(
+                "{} = UNHEX(@__hex{})",
+                quote_identifier(&target.name),
+                i
+            ));
+        } else {
+            columns.push(quote_identifier(&target.name));
+        }
+    }
+
+    let mut stmt = LoadDataFormat::new()
+        .with_character_set(Some("utf8mb4"))
+        .statement("bulk_loader", table, no_backslash_escape);
+    stmt.push_str(&format!(" ({})", columns.join(", ")));
+    if !assignments.is_empty() {
+        stmt.push_str(&format!(" SET {}", assignments.join(", ")));
+    }
+    stmt
+}
+
+/// Returns values of the row in the order of targets.
+fn row_values(params: Params, targets: &[Target]) -> Result<Vec<Value>> {
+    match params {
+        Params::Empty if targets.is_empty() => Ok(Vec::new()),
+        Params::Empty => Err(DriverError::MismatchedStmtParams(targets.len() as u16, 0).into()),
+        Params::Positional(values) if values.len() == targets.len() => Ok(values),
+        Params::Positional(values) => {
+            Err(DriverError::MismatchedStmtParams(targets.len() as u16, values.len()).into())
+        }
+        Params::Named(mut values) => targets
+            .iter()
+            .map(|target| {
+                values.remove(target.name.as_bytes()).ok_or_else(|| {
+                    Error::DriverError(DriverError::MissingNamedParameter(target.name.clone()))
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Writes a single row in the `LOAD DATA` format.
+fn write_row<W: Write>(out: &mut W, values: &[Value], targets: &[Target]) -> io::Result<()> {
+    for (i, (value, target)) in values.iter().zip(targets).enumerate() {
+        if i > 0 {
+            out.write_all(b"\t")?;
+        }
+        write_value(out, value, target)?;
+    }
+    out.write_all(b"\n")
+}
+
+/// Writes a single value in the `LOAD DATA` format.
+fn write_value<W: Write>(out: &mut W, value: &Value, target: &Target) -> io::Result<()> {
+    let text: Cow<'_, [u8]> = match *value {
+        NULL => return out.write_all(b"\\N"),
+        Bytes(ref bytes) => Cow::Borrowed(bytes),
+        // the text form of a number would be stored as is, i.e. as a string of digits
+        Int(x) if target.bit => Cow::Owned(x.to_be_bytes().to_vec()),
+        UInt(x) if target.bit => Cow::Owned(x.to_be_bytes().to_vec()),
+        Int(x) => Cow::Owned(x.to_string().into_bytes()),
+        UInt(x) => Cow::Owned(x.to_string().into_bytes()),
+        Float(x) => Cow::Owned(x.to_string().into_bytes()),
+        Double(x) => Cow::Owned(x.to_string().into_bytes()),
+        Date(y, m, d, h, i, s, 0) => Cow::Owned(
+            format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", y, m, d, h, i, s).into_bytes(),
+        ),
+        Date(y, m, d, h, i, s, u) => Cow::Owned(
+            format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+                y, m, d, h, i, s, u
+            )
+            .into_bytes(),
+        ),
+        Time(neg, d, h, i, s, u) => {
+            let sign = if neg { "-" } else { "" };
+            let hours = d * 24 + u32::from(h);
+            let time = if u == 0 {
+                format!("{}{:02}:{:02}:{:02}", sign, hours, i, s)
+            } else {
+                format!("{}{:02}:{:02}:{:02}.{:06}", sign, hours, i, s, u)
+            };
+            Cow::Owned(time.into_bytes())
+        }
+    };
+
+    if target.hex {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+        for chunk in text.chunks(4096) {
+            let encoded = chunk
+                .iter()
+                .flat_map(|byte| [DIGITS[(byte >> 4) as usize], DIGITS[(byte & 0xf) as usize]])
+                .collect::<Vec<_>>();
+            out.write_all(&encoded)?;
+        }
+        return Ok(());
+    }
+
+    let mut start = 0;
+    for (i, byte) in text.iter().enumerate() {
+        let escaped: &[u8] = match byte {
+            b'\\' => b"\\\\",
+            b'\t' => b"\\t",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\0' => b"\\0",
+            _ => continue,
+        };
+        out.write_all(&text[start..i])?;
+        out.write_all(escaped)?;
+        start = i + 1;
+    }
+    out.write_all(&text[start..])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{row_values, statement, write_row, Target};
+    use crate::{params, Params, Value};
+
+    fn targets() -> Vec<Target> {
+        vec![
+            Target {
+                name: "id".into(),
+                hex: false,
+                bit: false,
+            },
+            Target {
+                name: "text".into(),
+                hex: false,
+                bit: false,
+            },
+            Target {
+                name: "data".into(),
+                hex: true,
+                bit: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn should_generate_bulk_load_statement() {
+        assert_eq!(
+            statement("db.tbl", &targets(), false),
+            "LOAD DATA LOCAL INFILE 'bulk_loader' INTO TABLE `db`.`tbl` CHARACTER SET 'utf8mb4' \
+             FIELDS TERMINATED BY '\t' ESCAPED BY '\\\\' LINES TERMINATED BY '\\n' \
+             (`id`, `text`, @__hex2) SET `data` = UNHEX(@__hex2)"
+        );
+    }
+
+    #[test]
+    fn should_serialize_rows() {
+        let mut out = Vec::new();
+        let rows: Vec<Params> = vec![
+            (1, "a\tb\nc\\d\0e\rf", vec![0_u8, 0xff, b'\t']).into(),
+            (Value::NULL, Value::NULL, Value::NULL).into(),
+            (
+                -1.5_f64,
+                Value::Date(2020, 1, 2, 3, 4, 5, 0),
+                Value::Time(true, 1, 2, 3, 4, 5),
+            )
+                .into(),
+            (u64::MAX, Value::Date(2020, 1, 2, 3, 4, 5, 6), "").into(),
+        ];
+        for row in rows {
+            let values = row_values(row, &targets()).unwrap();
+            write_row(&mut out, &values, &targets()).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1\ta\\tb\\nc\\\\d\\0e\\rf\t00ff09\n\
+             \\N\t\\N\t\\N\n\
+             -1.5\t2020-01-02 03:04:05\t2d32363a30333a30342e303030303035\n\
+             18446744073709551615\t2020-01-02 03:04:05.000006\t\n"
+        );
+    }
+
+    #[test]
+    fn should_serialize_bit_values() {
+        let targets = vec![Target {
+            name: "flags".into(),
+            hex: true,
+            bit: true,
+        }];
+        let mut out = Vec::new();
+        for value in [
+            Value::Int(5),
+            Value::UInt(0x0102),
+            Value::from(vec![0x05_u8]),
+        ] {
+            write_row(&mut out, &[value], &targets).unwrap();
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "0000000000000005\n0000000000000102\n05\n"
+        );
+    }
+
+    #[test]
+    fn should_match_row_values_to_targets() {
+        let values = row_values(
+            params! { "data" => b"x", "text" => "y", "id" => 1 },
+            &targets(),
+        )
+        .unwrap();
+        assert_eq!(
+            values,
+            vec![Value::Int(1), Value::from("y"), Value::from(b"x")]
+        );
+        assert!(row_values(params! { "id" => 1 }, &targets()).is_err());
+        assert!(row_values((1, 2).into(), &targets()).is_err());
+        assert!(row_values(Params::Empty, &targets()).is_err());
+    }
+}
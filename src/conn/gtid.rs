@@ -0,0 +1,266 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    packets::{binlog_request::BinlogRequest, GnoInterval, Sid},
+    proto::MySerialize,
+};
+
+use std::{collections::BTreeMap, fmt, io, str::FromStr};
+
+/// Length of a source identifier (server UUID).
+const UUID_LEN: usize = 16;
+
+/// Set of MySql global transaction identifiers (i.e. the value of `gtid_executed`).
+///
+/// Every GTID consists of a source identifier (server UUID) and a transaction number (`gno`).
+/// Textual representation is the same as the one used by the server
+/// (i.e. `3E11FA47-71CA-11E1-9E33-C80AA9429562:1-5:11,…`).
+///
+/// ```
+/// # use mysql::*;
+/// let mut set: GtidSet = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5".parse().unwrap();
+/// let sid = *set.sids().next().unwrap();
+/// set.add(sid, 6);
+/// set.add(sid, 8);
+/// assert!(set.contains(sid, 3));
+/// assert!(!set.contains(sid, 7));
+/// assert_eq!(set.to_string(), "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-6:8");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct GtidSet {
+    /// Sorted, non-overlapping half-open intervals of transaction numbers for every source.
+    intervals: BTreeMap<[u8; UUID_LEN], Vec<(u64, u64)>>,
+}
+
+impl GtidSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Returns source identifiers contained in the set.
+    pub fn sids(&self) -> impl Iterator<Item = &[u8; UUID_LEN]> {
+        self.intervals.keys()
+    }
+
+    /// Returns `true` if the set contains the given transaction.
+    pub fn contains(&self, sid: [u8; UUID_LEN], gno: u64) -> bool {
+        self.intervals
+            .get(&sid)
+            .map(|intervals| {
+                intervals
+                    .iter()
+                    .any(|&(start, end)| start <= gno && gno < end)
+            })
+            .unwrap_or(false)
+    }
+
+    /// Adds the given transaction to the set.
+    pub fn add(&mut self, sid: [u8; UUID_LEN], gno: u64) {
+        self.add_interval(sid, gno, gno.saturating_add(1));
+    }
+
+    /// Adds transactions from `start` (inclusive) to `end` (exclusive) to the set.
+    ///
+    /// Empty intervals are ignored.
+    pub fn add_interval(&mut self, sid: [u8; UUID_LEN], start: u64, end: u64) {
+        if start >= end {
+            return;
+        }
+        let intervals = self.intervals.entry(sid).or_default();
+        intervals.push((start, end));
+        intervals.sort_unstable();
+
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+        for &(start, end) in intervals.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        *intervals = merged;
+    }
+
+    /// Adds every transaction of the other set to this set.
+    pub fn union(&mut self, other: &GtidSet) {
+        for (sid, intervals) in &other.intervals {
+            for &(start, end) in intervals {
+                self.add_interval(*sid, start, end);
+            }
+        }
+    }
+
+    /// Converts the set into `Sid`s used by `COM_BINLOG_DUMP_GTID`.
+    pub fn to_sids(&self) -> Vec<Sid<'static>> {
+        self.intervals
+            .iter()
+            .map(|(sid, intervals)| {
+                Sid::new(*sid).with_intervals(
+                    intervals
+                        .iter()
+                        .map(|&(start, end)| GnoInterval::new(start, end))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Creates a set from `Sid`s used by `COM_BINLOG_DUMP_GTID`.
+    pub fn from_sids(sids: &[Sid<'_>]) -> Self {
+        let mut set = Self::new();
+        for sid in sids {
+            for interval in sid.intervals() {
+                // `GnoInterval` does not expose its bounds, but it's serialized as two `u64`.
+                let mut buf = Vec::with_capacity(16);
+                interval.serialize(&mut buf);
+                let start = u64::from_le_bytes(buf[..8].try_into().unwrap());
+                let end = u64::from_le_bytes(buf[8..16].try_into().unwrap());
+                set.add_interval(sid.uuid(), start, end);
+            }
+        }
+        set
+    }
+
+    /// Returns a request, that will use `COM_BINLOG_DUMP_GTID` to stream every transaction,
+    /// that is not contained in this set.
+    pub fn binlog_request(&self, server_id: u32) -> BinlogRequest<'static> {
+        BinlogRequest::new(server_id)
+            .with_use_gtid(true)
+            .with_sids(self.to_sids())
+    }
+}
+
+impl fmt::Display for GtidSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (sid, intervals)) in self.intervals.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            for (j, byte) in sid.iter().enumerate() {
+                if matches!(j, 4 | 6 | 8 | 10) {
+                    f.write_str("-")?;
+                }
+                write!(f, "{:02x}", byte)?;
+            }
+            for &(start, end) in intervals {
+                if end - start == 1 {
+                    write!(f, ":{}", start)?;
+                } else {
+                    write!(f, ":{}-{}", start, end - 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GtidSet {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid GTID set: {}", s),
+            )
+        };
+
+        let mut set = Self::new();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let mut parts = item.split(':');
+            let sid = parse_uuid(parts.next().unwrap_or_default()).ok_or_else(invalid)?;
+            for interval in parts {
+                let (start, end) = match interval.split_once('-') {
+                    Some((start, end)) => (start, end),
+                    None => (interval, interval),
+                };
+                let start: u64 = start.trim().parse().map_err(|_| invalid())?;
+                let end: u64 = end.trim().parse().map_err(|_| invalid())?;
+                if start == 0 || end < start {
+                    return Err(invalid());
+                }
+                set.add_interval(sid, start, end.saturating_add(1));
+            }
+        }
+        Ok(set)
+    }
+}
+
+/// Parses a UUID in the `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` form (hyphens are optional).
+fn parse_uuid(s: &str) -> Option<[u8; UUID_LEN]> {
+    let digits = s.trim().bytes().filter(|b| *b != b'-').collect::<Vec<_>>();
+    if digits.len() != UUID_LEN * 2 {
+        return None;
+    }
+    let mut uuid = [0_u8; UUID_LEN];
+    for (byte, pair) in uuid.iter_mut().zip(digits.chunks(2)) {
+        let pair = std::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(uuid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::GtidSet;
+
+    const UUID_A: &str = "3e11fa47-71ca-11e1-9e33-c80aa9429562";
+    const UUID_B: &str = "57a2fb1c-1b3e-11ef-8a9d-0242ac110002";
+
+    #[test]
+    fn should_parse_and_format_gtid_set() {
+        let input = format!("{}:1-5:11:7-9,\n{}:1", UUID_B.to_uppercase(), UUID_A);
+        let set: GtidSet = input.parse().unwrap();
+        assert_eq!(
+            set.to_string(),
+            format!("{}:1,{}:1-5:7-9:11", UUID_A, UUID_B)
+        );
+        assert_eq!(set.to_string().parse::<GtidSet>().unwrap(), set);
+
+        assert!("".parse::<GtidSet>().unwrap().is_empty());
+        assert!("foo:1".parse::<GtidSet>().is_err());
+        assert!(format!("{}:0", UUID_A).parse::<GtidSet>().is_err());
+        assert!(format!("{}:5-1", UUID_A).parse::<GtidSet>().is_err());
+    }
+
+    #[test]
+    fn should_merge_intervals() {
+        let mut set: GtidSet = format!("{}:1-3:10", UUID_A).parse().unwrap();
+        let sid = *set.sids().next().unwrap();
+        set.add(sid, 4);
+        set.add(sid, 9);
+        assert_eq!(set.to_string(), format!("{}:1-4:9-10", UUID_A));
+        set.add_interval(sid, 5, 9);
+        assert_eq!(set.to_string(), format!("{}:1-10", UUID_A));
+        assert!(set.contains(sid, 10));
+        assert!(!set.contains(sid, 11));
+
+        let other: GtidSet = format!("{}:2", UUID_B).parse().unwrap();
+        set.union(&other);
+        assert_eq!(set.to_string(), format!("{}:1-10,{}:2", UUID_A, UUID_B));
+    }
+
+    #[test]
+    fn should_convert_to_and_from_sids() {
+        let set: GtidSet = format!("{}:1-3:10,{}:2", UUID_A, UUID_B).parse().unwrap();
+        let sids = set.to_sids();
+        assert_eq!(sids.len(), 2);
+        assert_eq!(GtidSet::from_sids(&sids), set);
+
+        let request = set.binlog_request(42);
+        assert!(request.use_gtid());
+        assert_eq!(request.server_id(), 42);
+        assert_eq!(request.sids(), &sids[..]);
+    }
+}
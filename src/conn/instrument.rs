@@ -0,0 +1,486 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! `tracing` instrumentation (see the `tracing` crate feature).
+//!
+//! Spans are named `mysql.*` and carry OpenTelemetry semantic convention fields
+//! (`db.system`, `db.statement`, `net.peer.name`, …). Errors are recorded into the `error`
+//! field and `otel.status_code` is set to `ERROR`. If the feature is disabled,
+//! then every function of this module is a no-op.
+
+use std::{borrow::Cow, fmt, sync::Arc};
+
+#[cfg(feature = "tracing")]
+pub(crate) use self::imp::*;
+#[cfg(not(feature = "tracing"))]
+pub(crate) use self::noop::*;
+
+/// Defines how a statement is recorded into the `db.statement` field of `tracing` spans
+/// (see [`crate::OptsBuilder::statement_sanitizer`]).
+///
+/// ```
+/// # use mysql::*;
+/// let sanitizer = StatementSanitizer::ReplaceLiterals;
+/// assert_eq!(
+///     sanitizer.sanitize("SELECT * FROM users WHERE name = 'foo' AND id > 42").as_deref(),
+///     Some("SELECT * FROM users WHERE name = ? AND id > ?"),
+/// );
+/// assert_eq!(StatementSanitizer::Omit.sanitize("DO 1"), None);
+/// ```
+#[derive(Clone, Default)]
+pub enum StatementSanitizer {
+    /// Statements are recorded as is.
+    Verbatim,
+    /// String and numeric literals are replaced with `?` (the default).
+    ///
+    /// Comments are kept as is, except for executable comments (`/*! ... */`)
+    /// and optimizer hints (`/*+ ... */`), where literals are replaced as well.
+    #[default]
+    ReplaceLiterals,
+    /// Statements are not recorded.
+    Omit,
+    /// Statements are recorded as returned by the given function.
+    Custom(Arc<dyn Fn(&str) -> String + Send + Sync>),
+}
+
+impl StatementSanitizer {
+    /// Returns the statement as it should be recorded (`None` if it shouldn't).
+    pub fn sanitize<'a>(&self, statement: &'a str) -> Option<Cow<'a, str>> {
+        match self {
+            Self::Verbatim => Some(Cow::Borrowed(statement)),
+            Self::ReplaceLiterals => Some(Cow::Owned(replace_literals(statement))),
+            Self::Omit => None,
+            Self::Custom(f) => Some(Cow::Owned(f(statement))),
+        }
+    }
+}
+
+impl PartialEq for StatementSanitizer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Verbatim, Self::Verbatim)
+            | (Self::ReplaceLiterals, Self::ReplaceLiterals)
+            | (Self::Omit, Self::Omit) => true,
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for StatementSanitizer {}
+
+impl fmt::Debug for StatementSanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Verbatim => f.write_str("Verbatim"),
+            Self::ReplaceLiterals => f.write_str("ReplaceLiterals"),
+            Self::Omit => f.write_str("Omit"),
+            Self::Custom(_) => f.write_str("Custom(...)"),
+        }
+    }
+}
+
+/// Replaces quoted strings and numbers with `?` (identifiers and comments are kept intact).
+fn replace_literals(statement: &str) -> String {
+    let mut output = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+    // whether the previous char could be a part of an identifier
+    let mut in_word = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                while let Some(x) = chars.next() {
+                    if x == '\\' {
+                        chars.next();
+                    } else if x == c {
+                        // doubled quote is an escaped quote
+                        if chars.peek() == Some(&c) {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                output.push('?');
+                in_word = false;
+            }
+            '`' => {
+                output.push(c);
+                for x in chars.by_ref() {
+                    output.push(x);
+                    if x == '`' {
+                        break;
+                    }
+                }
+                in_word = false;
+            }
+            // `#` and `-- ` comments run to the end of the line
+            '#' => {
+                output.push(c);
+                copy_line(&mut chars, &mut output);
+                in_word = false;
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                output.push(c);
+                output.push(chars.next().unwrap_or(c));
+                if chars
+                    .peek()
+                    .is_none_or(|x| x.is_whitespace() || x.is_control())
+                {
+                    copy_line(&mut chars, &mut output);
+                }
+                in_word = false;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                output.push(c);
+                output.push(chars.next().unwrap_or(c));
+                in_word = false;
+                // executable comments and optimizer hints are executed by the server,
+                // so literals are replaced within them (except for the version number)
+                if let Some(x) = chars.next_if(|x| *x == '!' || *x == '+') {
+                    output.push(x);
+                    if x == '!' {
+                        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                            output.push(digit);
+                        }
+                    }
+                    continue;
+                }
+                let mut prev = None;
+                for x in chars.by_ref() {
+                    output.push(x);
+                    if prev == Some('*') && x == '/' {
+                        break;
+                    }
+                    prev = Some(x);
+                }
+                in_word = false;
+            }
+            '0'..='9' if !in_word => {
+                while chars
+                    .next_if(|x| x.is_ascii_alphanumeric() || *x == '.')
+                    .is_some()
+                {}
+                output.push('?');
+            }
+            c => {
+                in_word = c.is_alphanumeric() || c == '_' || c == '$' || c == '@';
+                output.push(c);
+            }
+        }
+    }
+    output
+}
+
+/// Copies chars up to and including the end of the line.
+fn copy_line(chars: &mut impl Iterator<Item = char>, output: &mut String) {
+    for x in chars {
+        output.push(x);
+        if x == '\n' {
+            break;
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod imp {
+    use mysql_common::packets::OkPacket;
+    use tracing::{field, info_span};
+
+    use std::time::Duration;
+
+    use crate::{Conn, Opts, Result};
+
+    pub(crate) type Span = tracing::Span;
+
+    /// Returns a sanitized statement (see [`super::StatementSanitizer`]).
+    fn statement(opts: &Opts, statement: &[u8]) -> Option<String> {
+        let statement = String::from_utf8_lossy(statement);
+        opts.get_statement_sanitizer()
+            .sanitize(&statement)
+            .map(|x| x.into_owned())
+    }
+
+    /// Span of the connection setup (server handshake and authentication).
+    pub(crate) fn connect_span(opts: &Opts) -> Span {
+        info_span!(
+            "mysql.connect",
+            db.system = "mysql",
+            db.name = opts.get_db_name(),
+            db.user = opts.get_user(),
+            net.peer.name = %opts.get_ip_or_hostname(),
+            net.peer.port = opts.get_tcp_port(),
+            db.mysql.connection_id = field::Empty,
+            db.mysql.server_version = field::Empty,
+            db.mysql.tls = field::Empty,
+            db.mysql.auth_plugin = field::Empty,
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Span of a TLS upgrade.
+    pub(crate) fn tls_span() -> Span {
+        info_span!(
+            "mysql.tls",
+            error = field::Empty,
+            otel.status_code = field::Empty
+        )
+    }
+
+    /// Span of the authentication using the given plugin.
+    pub(crate) fn auth_span(auth_plugin: &[u8]) -> Span {
+        info_span!(
+            "mysql.auth",
+            db.mysql.auth_plugin = %String::from_utf8_lossy(auth_plugin),
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Records connection details into the current span.
+    pub(crate) fn record_connected(conn: &Conn, tls: bool, auth_plugin: &[u8]) {
+        let (major, minor, patch) = conn.server_version();
+        let span = Span::current();
+        span.record("db.mysql.connection_id", conn.connection_id());
+        span.record(
+            "db.mysql.server_version",
+            field::display(format_args!("{}.{}.{}", major, minor, patch)),
+        );
+        span.record("db.mysql.tls", tls);
+        span.record(
+            "db.mysql.auth_plugin",
+            field::display(String::from_utf8_lossy(auth_plugin)),
+        );
+    }
+
+    /// Span of a text query.
+    pub(crate) fn query_span(conn: &Conn, query: &[u8]) -> Span {
+        let opts = &conn.0.opts;
+        info_span!(
+            "mysql.query",
+            db.system = "mysql",
+            db.statement = statement(opts, query),
+            net.peer.name = %opts.get_ip_or_hostname(),
+            db.mysql.connection_id = conn.connection_id(),
+            db.rows_affected = field::Empty,
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Span of a statement preparation.
+    pub(crate) fn prepare_span(conn: &Conn, query: &[u8]) -> Span {
+        let opts = &conn.0.opts;
+        info_span!(
+            "mysql.prepare",
+            db.system = "mysql",
+            db.statement = statement(opts, query),
+            net.peer.name = %opts.get_ip_or_hostname(),
+            db.mysql.connection_id = conn.connection_id(),
+            db.mysql.cached = field::Empty,
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Records whether the prepared statement was taken from the statement cache.
+    pub(crate) fn record_cached(cached: bool) {
+        Span::current().record("db.mysql.cached", cached);
+    }
+
+    /// Span of a statement execution.
+    pub(crate) fn execute_span(conn: &Conn, query: Option<&[u8]>) -> Span {
+        let opts = &conn.0.opts;
+        info_span!(
+            "mysql.execute",
+            db.system = "mysql",
+            db.statement = query.and_then(|query| statement(opts, query)),
+            net.peer.name = %opts.get_ip_or_hostname(),
+            db.mysql.connection_id = conn.connection_id(),
+            db.rows_affected = field::Empty,
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Records the number of affected rows into the current span.
+    pub(crate) fn record_ok(ok: &OkPacket<'_>) {
+        Span::current().record("db.rows_affected", ok.affected_rows());
+    }
+
+    /// Span of a transaction operation (i.e. `START TRANSACTION`, `COMMIT`, `ROLLBACK`).
+    pub(crate) fn transaction_span(conn: &Conn, operation: &'static str) -> Span {
+        info_span!(
+            "mysql.transaction",
+            db.system = "mysql",
+            db.operation = operation,
+            net.peer.name = %conn.0.opts.get_ip_or_hostname(),
+            db.mysql.connection_id = conn.connection_id(),
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Span of [`crate::Pool::get_conn`] and friends.
+    pub(crate) fn get_conn_span() -> Span {
+        info_span!(
+            "mysql.pool.get_conn",
+            db.system = "mysql",
+            net.peer.name = field::Empty,
+            db.mysql.connection_id = field::Empty,
+            db.mysql.pool.wait_time_us = field::Empty,
+            db.mysql.pool.created = field::Empty,
+            error = field::Empty,
+            otel.status_code = field::Empty,
+        )
+    }
+
+    /// Records the time spent waiting for a connection and whether it was created.
+    pub(crate) fn record_get_conn(conn: &Conn, wait_time: Duration, created: bool) {
+        let span = Span::current();
+        span.record(
+            "net.peer.name",
+            field::display(conn.0.opts.get_ip_or_hostname()),
+        );
+        span.record("db.mysql.connection_id", conn.connection_id());
+        span.record(
+            "db.mysql.pool.wait_time_us",
+            u64::try_from(wait_time.as_micros()).unwrap_or(u64::MAX),
+        );
+        span.record("db.mysql.pool.created", created);
+    }
+
+    /// Runs `f` within the given span and records an error, if any.
+    pub(crate) fn in_span<T>(span: Span, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let result = span.in_scope(f);
+        if let Err(ref err) = result {
+            span.record("error", field::display(err));
+            span.record("otel.status_code", "ERROR");
+        }
+        result
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod noop {
+    use mysql_common::packets::OkPacket;
+
+    use std::time::Duration;
+
+    use crate::{Conn, Opts, Result};
+
+    /// Placeholder for `tracing::Span`.
+    pub(crate) struct Span;
+
+    pub(crate) fn connect_span(_: &Opts) -> Span {
+        Span
+    }
+
+    pub(crate) fn tls_span() -> Span {
+        Span
+    }
+
+    pub(crate) fn auth_span(_: &[u8]) -> Span {
+        Span
+    }
+
+    pub(crate) fn record_connected(_: &Conn, _: bool, _: &[u8]) {}
+
+    pub(crate) fn query_span(_: &Conn, _: &[u8]) -> Span {
+        Span
+    }
+
+    pub(crate) fn prepare_span(_: &Conn, _: &[u8]) -> Span {
+        Span
+    }
+
+    pub(crate) fn record_cached(_: bool) {}
+
+    pub(crate) fn execute_span(_: &Conn, _: Option<&[u8]>) -> Span {
+        Span
+    }
+
+    pub(crate) fn record_ok(_: &OkPacket<'_>) {}
+
+    pub(crate) fn transaction_span(_: &Conn, _: &'static str) -> Span {
+        Span
+    }
+
+    pub(crate) fn get_conn_span() -> Span {
+        Span
+    }
+
+    pub(crate) fn record_get_conn(_: &Conn, _: Duration, _: bool) {}
+
+    pub(crate) fn in_span<T>(_: Span, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        f()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::StatementSanitizer;
+
+    #[test]
+    fn should_replace_literals() {
+        let sanitizer = StatementSanitizer::ReplaceLiterals;
+        let cases = [
+            ("SELECT 1", "SELECT ?"),
+            (
+                "SELECT `col1`, t2.col FROM t2 WHERE x IN ('a', \"b\", 1.5e3, 0x1F)",
+                "SELECT `col1`, t2.col FROM t2 WHERE x IN (?, ?, ?, ?)",
+            ),
+            (
+                r"INSERT INTO t VALUES ('it''s', 'a\'b', -7)",
+                "INSERT INTO t VALUES (?, ?, -?)",
+            ),
+            (
+                "SELECT * FROM `t1` WHERE `2x` = :foo",
+                "SELECT * FROM `t1` WHERE `2x` = :foo",
+            ),
+            ("SELECT @x2, $ab1", "SELECT @x2, $ab1"),
+            (
+                "SELECT 1 -- it's 2\nFROM t # x = 'y'\nWHERE /* a = \"b\" */ c = 3",
+                "SELECT ? -- it's 2\nFROM t # x = 'y'\nWHERE /* a = \"b\" */ c = ?",
+            ),
+            ("SELECT 5--1, 4/2", "SELECT ?--?, ?/?"),
+            (
+                "/*!80000 SET x = 'secret' */ SELECT /*+ MAX_EXECUTION_TIME(1000) */ 1",
+                "/*!80000 SET x = ? */ SELECT /*+ MAX_EXECUTION_TIME(?) */ ?",
+            ),
+            ("SELECT /*!'a'*/ 2", "SELECT /*!?*/ ?"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(sanitizer.sanitize(input).as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn should_sanitize_statements() {
+        assert_eq!(
+            StatementSanitizer::Verbatim.sanitize("DO 1").as_deref(),
+            Some("DO 1")
+        );
+        assert_eq!(StatementSanitizer::Omit.sanitize("DO 1"), None);
+
+        let custom = StatementSanitizer::Custom(Arc::new(|query| query.to_lowercase()));
+        assert_eq!(custom.sanitize("DO 1").as_deref(), Some("do 1"));
+        assert_eq!(custom.clone(), custom);
+        assert_ne!(
+            custom,
+            StatementSanitizer::Custom(Arc::new(|query| query.to_owned()))
+        );
+        assert_eq!(
+            StatementSanitizer::default(),
+            StatementSanitizer::ReplaceLiterals
+        );
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{borrow::Cow, fmt, sync::Arc, time::Duration};
+
+use crate::{Result, Value};
+
+/// Kind of an intercepted statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// Text query (`COM_QUERY`).
+    Query,
+    /// Statement preparation (`COM_STMT_PREPARE`).
+    Prepare,
+    /// Prepared statement execution (`COM_STMT_EXECUTE`).
+    Execute,
+}
+
+/// Context of an intercepted statement (see [`Interceptor`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryContext {
+    kind: QueryKind,
+    connection_id: u32,
+    attributes: Vec<(String, Value)>,
+}
+
+impl QueryContext {
+    pub(crate) fn new(kind: QueryKind, connection_id: u32) -> Self {
+        Self {
+            kind,
+            connection_id,
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Returns the kind of the statement.
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    /// Returns the identifier of the connection, that runs the statement.
+    pub fn connection_id(&self) -> u32 {
+        self.connection_id
+    }
+
+    /// Adds a query attribute (see [`crate::Conn::with_attributes`]).
+    ///
+    /// Attributes are ignored for [`QueryKind::Prepare`].
+    pub fn add_attribute<K: Into<String>, V: Into<Value>>(&mut self, name: K, value: V) {
+        self.attributes.push((name.into(), value.into()));
+    }
+
+    /// Returns query attributes added by interceptors.
+    pub fn attributes(&self) -> &[(String, Value)] {
+        &self.attributes
+    }
+}
+
+/// Outcome of an intercepted statement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct QueryOutcome {
+    /// Number of affected rows (always `0` if the statement has produced a result set).
+    pub affected_rows: u64,
+    /// `true` if the statement has produced a result set.
+    pub has_result_set: bool,
+}
+
+/// Inspects and rewrites statements (see [`crate::OptsBuilder::interceptor`]).
+///
+/// Interceptors are called for every text query, statement preparation and statement
+/// execution performed by a connection, no matter if it's used directly, via
+/// [`crate::PooledConn`] or via [`crate::Transaction`] (this includes queries issued
+/// by the driver itself, such as `START TRANSACTION`).
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// use mysql::*;
+/// use mysql::prelude::*;
+/// use std::{borrow::Cow, io, time::Duration};
+///
+/// struct ReadOnly;
+///
+/// impl Interceptor for ReadOnly {
+///     fn before_query(&self, ctx: &mut QueryContext, query: &mut Cow<'_, str>) -> Result<()> {
+///         if ctx.kind() != QueryKind::Execute && query.trim_start().starts_with("INSERT") {
+///             return Err(DriverError::QueryRejected("maintenance window".into()).into());
+///         }
+///         // sqlcommenter-style tagging
+///         query.to_mut().push_str(" /*tenant='foo'*/");
+///         Ok(())
+///     }
+///
+///     fn after_query(&self, _: &QueryContext, _: &Result<QueryOutcome>, elapsed: Duration) {
+///         if elapsed > Duration::from_secs(1) {
+///             eprintln!("slow query: {:?}", elapsed);
+///         }
+///     }
+/// }
+///
+/// let opts = OptsBuilder::from_opts(get_opts()).interceptor(ReadOnly);
+/// let mut conn = Conn::new(opts)?;
+/// conn.query_drop("CREATE TEMPORARY TABLE tmp.interceptor (id INT)")?;
+/// assert!(conn.query_drop("INSERT INTO tmp.interceptor VALUES (1)").is_err());
+/// # });
+/// ```
+pub trait Interceptor: Send + Sync {
+    /// Called before the statement is sent to the server.
+    ///
+    /// The query could be rewritten (rewriting is ignored for [`QueryKind::Execute`],
+    /// because the statement is already prepared). An error rejects the statement.
+    fn before_query(&self, ctx: &mut QueryContext, query: &mut Cow<'_, str>) -> Result<()> {
+        let _ = (ctx, query);
+        Ok(())
+    }
+
+    /// Called once the server has responded to the statement (i.e. before rows of a result set
+    /// are read) or the statement was rejected by an interceptor.
+    fn after_query(&self, ctx: &QueryContext, result: &Result<QueryOutcome>, elapsed: Duration) {
+        let _ = (ctx, result, elapsed);
+    }
+}
+
+/// Chain of interceptors registered on [`crate::OptsBuilder`].
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors(pub(crate) Vec<Arc<dyn Interceptor>>);
+
+impl PartialEq for Interceptors {
+    fn eq(&self, other: &Interceptors) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+impl Eq for Interceptors {}
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Interceptors({})", self.0.len())
+    }
+}
@@ -7,11 +7,14 @@
 // modified, or distributed except according to those terms.
 
 use std::{
-    fmt, io,
-    sync::{Arc, Mutex},
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
 };
 
-use crate::Conn;
+use crate::{conn::quote_identifier, Conn, Value};
 
 pub(crate) type LocalInfileInner =
     Arc<Mutex<dyn for<'a> FnMut(&'a [u8], &'a mut LocalInfile<'_>) -> io::Result<()> + Send>>;
@@ -74,6 +77,33 @@ impl LocalInfileHandler {
     {
         LocalInfileHandler(Arc::new(Mutex::new(f)))
     }
+
+    /// Creates a handler, that reads requested files from the local filesystem,
+    /// but only if a file is located within one of the given directories.
+    ///
+    /// Note that the file name is chosen by the server, so a malicious server is able
+    /// to request any file accessible to the client. This handler will reject
+    /// (with `io::ErrorKind::PermissionDenied`) any file, that, after resolving symlinks and
+    /// `..` components, is not located within the allowed directories. Relative file
+    /// names are resolved against the current directory.
+    pub fn allowed_dirs<I, P>(dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        let dirs = dirs.into_iter().map(Into::into).collect::<Vec<_>>();
+        LocalInfileHandler::new(move |file_name, writer| {
+            let path = check_path(&dirs, file_name)?;
+            io::copy(&mut fs::File::open(path)?, writer)?;
+            Ok(())
+        })
+    }
+}
+
+impl From<LocalInfileSources> for LocalInfileHandler {
+    fn from(sources: LocalInfileSources) -> Self {
+        sources.handler()
+    }
 }
 
 impl PartialEq for LocalInfileHandler {
@@ -90,6 +120,367 @@ impl fmt::Debug for LocalInfileHandler {
     }
 }
 
+type Sources = HashMap<Vec<u8>, Box<dyn Read + Send>>;
+
+/// Registry of named sources for `LOAD DATA LOCAL INFILE` requests.
+///
+/// Requested file name is used as a source name, so nothing is ever read from
+/// the local filesystem. Every source is removed from the registry once requested,
+/// because a reader could only be streamed once.
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// use mysql::*;
+/// use mysql::prelude::*;
+///
+/// let pool = Pool::new(get_opts())?;
+/// let mut conn = pool.get_conn()?;
+///
+/// let sources = LocalInfileSources::new();
+/// sources.register("numbers", &b"1\n2\n3\n"[..]);
+/// conn.set_local_infile_handler(Some(sources.handler()));
+///
+/// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")?;
+/// match conn.query_drop("LOAD DATA LOCAL INFILE 'numbers' INTO TABLE mysql.tbl") {
+///     Ok(_) => (),
+///     Err(Error::MySqlError(ref e)) if e.code == 1148 || e.code == 3948 => {
+///         // functionality is not supported by the server
+///         return Ok(());
+///     }
+///     err => {
+///         err?;
+///     }
+/// }
+///
+/// let result: Vec<u32> = conn.query("SELECT a FROM mysql.tbl ORDER BY a")?;
+/// assert_eq!(result, vec![1, 2, 3]);
+/// assert!(sources.is_empty());
+/// # });
+/// ```
+#[derive(Clone, Default)]
+pub struct LocalInfileSources(Arc<Mutex<Sources>>);
+
+impl LocalInfileSources {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a source under the given name (replaces the previous source, if any).
+    pub fn register<N, R>(&self, name: N, source: R)
+    where
+        N: Into<Vec<u8>>,
+        R: Read + Send + 'static,
+    {
+        self.lock().insert(name.into(), Box::new(source));
+    }
+
+    /// Removes a source. Returns `true` if the source was registered.
+    pub fn remove<N: AsRef<[u8]>>(&self, name: N) -> bool {
+        self.lock().remove(name.as_ref()).is_some()
+    }
+
+    /// Returns `true` if there are no registered sources.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Returns a handler, that streams registered sources.
+    ///
+    /// The handler will fail with `io::ErrorKind::NotFound` if the requested source
+    /// is not registered.
+    pub fn handler(&self) -> LocalInfileHandler {
+        let sources = self.clone();
+        LocalInfileHandler::new(move |file_name, writer| {
+            let source = sources.lock().remove(file_name);
+            match source {
+                Some(mut source) => io::copy(&mut source, writer).map(drop),
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "LOCAL INFILE source `{}` is not registered",
+                        String::from_utf8_lossy(file_name)
+                    ),
+                )),
+            }
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Sources> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for LocalInfileSources {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.lock().keys().map(|name| String::from_utf8_lossy(name)))
+            .finish()
+    }
+}
+
+/// Format of the data for [`Conn::load_data`].
+///
+/// Defaults are the same as for the `LOAD DATA` statement, i.e. tab-separated fields,
+/// newline-terminated lines, no enclosing character and `\` as an escape character.
+///
+/// ```
+/// # use mysql::*;
+/// let format = LoadDataFormat::new()
+///     .with_columns(["id", "name"])
+///     .with_fields_terminated_by(",")
+///     .with_enclosed_by(Some(b'"'))
+///     .with_ignore_lines(1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadDataFormat {
+    columns: Vec<String>,
+    fields_terminated_by: Vec<u8>,
+    enclosed_by: Option<u8>,
+    optionally_enclosed: bool,
+    escaped_by: Option<u8>,
+    lines_terminated_by: Vec<u8>,
+    ignore_lines: u64,
+    character_set: Option<String>,
+}
+
+impl LoadDataFormat {
+    /// Calls `Self::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines the list of columns (defaults to empty list, i.e. all columns of the table).
+    pub fn with_columns<I, T>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.columns = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Defines the field terminator (defaults to `\t`).
+    pub fn with_fields_terminated_by<T: Into<Vec<u8>>>(mut self, terminator: T) -> Self {
+        self.fields_terminated_by = terminator.into();
+        self
+    }
+
+    /// Defines the character used to enclose fields (defaults to `None`).
+    pub fn with_enclosed_by(mut self, enclosed_by: Option<u8>) -> Self {
+        self.enclosed_by = enclosed_by;
+        self
+    }
+
+    /// Only string fields are enclosed if `true` (defaults to `false`).
+    pub fn with_optionally_enclosed(mut self, optionally_enclosed: bool) -> Self {
+        self.optionally_enclosed = optionally_enclosed;
+        self
+    }
+
+    /// Defines the escape character (defaults to `Some(b'\\')`, `None` disables escaping).
+    pub fn with_escaped_by(mut self, escaped_by: Option<u8>) -> Self {
+        self.escaped_by = escaped_by;
+        self
+    }
+
+    /// Defines the line terminator (defaults to `\n`).
+    pub fn with_lines_terminated_by<T: Into<Vec<u8>>>(mut self, terminator: T) -> Self {
+        self.lines_terminated_by = terminator.into();
+        self
+    }
+
+    /// Defines the number of lines to skip at the start of the data (defaults to `0`).
+    pub fn with_ignore_lines(mut self, ignore_lines: u64) -> Self {
+        self.ignore_lines = ignore_lines;
+        self
+    }
+
+    /// Defines the character set of the data (defaults to `None`, i.e. the server will use
+    /// the value of the `character_set_database` system variable).
+    pub fn with_character_set<T: Into<String>>(mut self, character_set: Option<T>) -> Self {
+        self.character_set = character_set.map(Into::into);
+        self
+    }
+
+    /// Returns the list of columns.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Returns the field terminator.
+    pub fn fields_terminated_by(&self) -> &[u8] {
+        &self.fields_terminated_by
+    }
+
+    /// Returns the character used to enclose fields.
+    pub fn enclosed_by(&self) -> Option<u8> {
+        self.enclosed_by
+    }
+
+    /// Returns `true` if only string fields are enclosed.
+    pub fn optionally_enclosed(&self) -> bool {
+        self.optionally_enclosed
+    }
+
+    /// Returns the escape character.
+    pub fn escaped_by(&self) -> Option<u8> {
+        self.escaped_by
+    }
+
+    /// Returns the line terminator.
+    pub fn lines_terminated_by(&self) -> &[u8] {
+        &self.lines_terminated_by
+    }
+
+    /// Returns the number of lines to skip.
+    pub fn ignore_lines(&self) -> u64 {
+        self.ignore_lines
+    }
+
+    /// Returns the character set of the data.
+    pub fn character_set(&self) -> Option<&str> {
+        self.character_set.as_deref()
+    }
+
+    /// Returns the `LOAD DATA LOCAL INFILE` statement.
+    ///
+    /// `table` may be qualified with a database name (i.e. `db.table`).
+    pub(crate) fn statement(
+        &self,
+        file_name: &str,
+        table: &str,
+        no_backslash_escape: bool,
+    ) -> String {
+        let literal = |bytes: &[u8]| Value::Bytes(bytes.to_vec()).as_sql(no_backslash_escape);
+        let mut stmt = format!(
+            "LOAD DATA LOCAL INFILE {} INTO TABLE {}",
+            literal(file_name.as_bytes()),
+            quote_table(table),
+        );
+        if let Some(ref character_set) = self.character_set {
+            stmt.push_str(" CHARACTER SET ");
+            stmt.push_str(&literal(character_set.as_bytes()));
+        }
+        stmt.push_str(" FIELDS TERMINATED BY ");
+        stmt.push_str(&literal(&self.fields_terminated_by));
+        if let Some(enclosed_by) = self.enclosed_by {
+            if self.optionally_enclosed {
+                stmt.push_str(" OPTIONALLY");
+            }
+            stmt.push_str(" ENCLOSED BY ");
+            stmt.push_str(&literal(&[enclosed_by]));
+        }
+        stmt.push_str(" ESCAPED BY ");
+        stmt.push_str(&literal(self.escaped_by.as_slice()));
+        stmt.push_str(" LINES TERMINATED BY ");
+        stmt.push_str(&literal(&self.lines_terminated_by));
+        if self.ignore_lines > 0 {
+            stmt.push_str(&format!(" IGNORE {} LINES", self.ignore_lines));
+        }
+        if !self.columns.is_empty() {
+            let columns = self
+                .columns
+                .iter()
+                .map(|column| quote_identifier(column))
+                .collect::<Vec<_>>();
+            stmt.push_str(&format!(" ({})", columns.join(", ")));
+        }
+        stmt
+    }
+}
+
+impl Default for LoadDataFormat {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+            fields_terminated_by: b"\t".to_vec(),
+            enclosed_by: None,
+            optionally_enclosed: false,
+            escaped_by: Some(b'\\'),
+            lines_terminated_by: b"\n".to_vec(),
+            ignore_lines: 0,
+            character_set: None,
+        }
+    }
+}
+
+/// Information about the completed `LOAD DATA` statement, parsed from the info string
+/// reported by the server (i.e. `Records: 3  Deleted: 0  Skipped: 0  Warnings: 0`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LoadDataInfo {
+    /// Number of records read from the data.
+    pub records: u64,
+    /// Number of replaced records.
+    pub deleted: u64,
+    /// Number of skipped records (i.e. duplicates).
+    pub skipped: u64,
+    /// Number of warnings.
+    pub warnings: u64,
+}
+
+impl LoadDataInfo {
+    /// Parses the info string reported by the server.
+    ///
+    /// Returns `None` if the string does not contain every field.
+    pub fn parse(info: &str) -> Option<Self> {
+        let (mut records, mut deleted, mut skipped, mut warnings) = (None, None, None, None);
+        let mut words = info.split_whitespace();
+        while let (Some(key), Some(value)) = (words.next(), words.next()) {
+            let value = value.parse().ok()?;
+            match key {
+                "Records:" => records = Some(value),
+                "Deleted:" => deleted = Some(value),
+                "Skipped:" => skipped = Some(value),
+                "Warnings:" => warnings = Some(value),
+                _ => return None,
+            }
+        }
+        Some(Self {
+            records: records?,
+            deleted: deleted?,
+            skipped: skipped?,
+            warnings: warnings?,
+        })
+    }
+}
+
+/// Quotes a table name, that may be qualified with a database name (i.e. `db.table`).
+pub(crate) fn quote_table(table: &str) -> String {
+    table
+        .splitn(2, '.')
+        .map(quote_identifier)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Returns canonical path of the requested file, if it's located within allowed directories.
+fn check_path(allowed_dirs: &[PathBuf], file_name: &[u8]) -> io::Result<PathBuf> {
+    let denied = || {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "LOCAL INFILE request for `{}` is not allowed",
+                String::from_utf8_lossy(file_name)
+            ),
+        )
+    };
+
+    let file_name = std::str::from_utf8(file_name).map_err(|_| denied())?;
+    let path = Path::new(file_name).canonicalize().map_err(|_| denied())?;
+    let allowed = allowed_dirs.iter().any(|dir| {
+        dir.canonicalize()
+            .map(|dir| path.starts_with(dir))
+            .unwrap_or(false)
+    });
+
+    if allowed && path.is_file() {
+        Ok(path)
+    } else {
+        Err(denied())
+    }
+}
+
 /// Local in-file stream.
 /// The callback will be passed a reference to this stream, which it
 /// should use to write the contents of the requested file.
@@ -127,3 +518,85 @@ impl<'a> io::Write for LocalInfile<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io, path::Path};
+
+    use super::{check_path, LoadDataFormat, LoadDataInfo};
+
+    #[test]
+    fn should_parse_load_data_info() {
+        assert_eq!(
+            LoadDataInfo::parse("Records: 3  Deleted: 1  Skipped: 2  Warnings: 4"),
+            Some(LoadDataInfo {
+                records: 3,
+                deleted: 1,
+                skipped: 2,
+                warnings: 4,
+            })
+        );
+        assert_eq!(LoadDataInfo::parse(""), None);
+        assert_eq!(LoadDataInfo::parse("Records: 3  Deleted: 1"), None);
+        assert_eq!(
+            LoadDataInfo::parse("Rows matched: 1  Changed: 1  Warnings: 0"),
+            None
+        );
+    }
+
+    #[test]
+    fn should_generate_load_data_statement() {
+        assert_eq!(
+            LoadDataFormat::new().statement("file", "tbl", false),
+            "LOAD DATA LOCAL INFILE 'file' INTO TABLE `tbl` FIELDS TERMINATED BY '\t' \
+             ESCAPED BY '\\\\' LINES TERMINATED BY '\\n'"
+        );
+        assert_eq!(
+            LoadDataFormat::new()
+                .with_columns(["id", "na`me"])
+                .with_fields_terminated_by(",")
+                .with_enclosed_by(Some(b'\''))
+                .with_optionally_enclosed(true)
+                .with_escaped_by(None)
+                .with_lines_terminated_by("\r\n")
+                .with_ignore_lines(1)
+                .with_character_set(Some("utf8mb4"))
+                .statement("fi'le", "db.tbl", true),
+            "LOAD DATA LOCAL INFILE 'fi''le' INTO TABLE `db`.`tbl` CHARACTER SET 'utf8mb4' \
+             FIELDS TERMINATED BY ',' \
+             OPTIONALLY ENCLOSED BY '''' ESCAPED BY '' LINES TERMINATED BY '\r\n' \
+             IGNORE 1 LINES (`id`, `na``me`)"
+        );
+    }
+
+    #[test]
+    fn should_only_allow_files_within_allowed_dirs() {
+        let root = std::env::temp_dir().join(format!("mysql-local-infile-{}", std::process::id()));
+        let allowed = root.join("allowed");
+        fs::create_dir_all(allowed.join("nested")).unwrap();
+        fs::write(allowed.join("nested").join("data.csv"), b"1\n").unwrap();
+        fs::write(root.join("secret"), b"secret").unwrap();
+
+        let dirs = vec![allowed.clone()];
+        let file_name = |path: &Path| path.to_str().unwrap().as_bytes().to_vec();
+
+        let data = allowed.join("nested").join("data.csv");
+        assert_eq!(
+            check_path(&dirs, &file_name(&data)).unwrap(),
+            data.canonicalize().unwrap()
+        );
+
+        for path in [
+            root.join("secret"),
+            allowed.join("..").join("secret"),
+            allowed.join("missing"),
+            allowed.join("nested"),
+        ] {
+            let err = check_path(&dirs, &file_name(&path)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        }
+        assert!(check_path(&[], &file_name(&data)).is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
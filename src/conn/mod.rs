@@ -15,10 +15,11 @@ use mysql_common::{
     io::{ParseBuf, ReadMysqlExt},
     named_params::ParsedNamedParams,
     packets::{
-        AuthPlugin, AuthSwitchRequest, Column, ComChangeUser, ComChangeUserMoreData, ComStmtClose,
-        ComStmtExecuteRequestBuilder, ComStmtSendLongData, CommonOkPacket, ErrPacket,
-        HandshakePacket, HandshakeResponse, OkPacket, OkPacketDeserializer, OkPacketKind,
-        OldAuthSwitchRequest, OldEofPacket, ResultSetTerminator, SessionStateInfo,
+        session_state_change::SessionStateChange, AuthPlugin, AuthSwitchRequest, Column,
+        ComChangeUser, ComChangeUserMoreData, ComStmtClose, ComStmtExecuteRequestBuilder,
+        ComStmtSendLongData, CommonOkPacket, ErrPacket, HandshakePacket, HandshakeResponse,
+        OkPacket, OkPacketDeserializer, OkPacketKind, OldAuthSwitchRequest, OldEofPacket,
+        ResultSetTerminator, SessionStateInfo,
     },
     proto::{codec::Compression, sync_framed::MySyncFramed, MySerialize},
 };
@@ -29,7 +30,7 @@ use mysql_common::{
 };
 
 use std::{
-    borrow::{Borrow, Cow},
+    borrow::Cow,
     cmp,
     collections::HashMap,
     convert::TryFrom,
@@ -38,6 +39,7 @@ use std::{
     ops::{Deref, DerefMut},
     process,
     sync::Arc,
+    time::Instant,
 };
 
 #[cfg(unix)]
@@ -46,25 +48,33 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use crate::{
     buffer_pool::{get_buffer, Buffer},
     conn::{
-        local_infile::LocalInfile,
+        gtid::GtidSet,
+        interceptor::{QueryContext, QueryKind, QueryOutcome},
+        local_infile::{LoadDataFormat, LoadDataInfo, LocalInfile},
         pool::{Pool, PooledConn},
+        query_attributes::QueryAttributes,
         query_result::{Binary, Or, Text},
-        stmt::{InnerStmt, Statement},
-        stmt_cache::StmtCache,
+        session_state::{SessionStateTracker, ENABLE_TRACKERS, TRACK_SCHEMA},
+        stats::{ConnStats, PendingQuery, SlowQuery},
+        stmt::{Describe, InnerStmt, Statement, StmtCacheStats},
+        stmt_cache::{ColumnDefs, SharedStmtCache, StmtCache, StmtMetadata},
         transaction::{AccessMode, TxOpts},
+        warning::{Warning, WarningLevel},
+        xa::{XaTransaction, Xid},
     },
     consts::{CapabilityFlags, Command, StatusFlags, MAX_PAYLOAD_LEN},
-    from_value, from_value_opt,
-    io::Stream,
+    from_row_opt, from_value, from_value_opt,
+    io::{Metered, Stream},
     prelude::*,
     ChangeUserOpts,
     DriverError::{
         CleartextPluginDisabled, MismatchedStmtParams, NamedParamsForPositionalQuery,
         OldMysqlPasswordDisabled, Protocol41NotSet, ReadOnlyTransNotSupported, SetupError,
-        UnexpectedPacket, UnknownAuthPlugin, UnsupportedProtocol,
+        UnexpectedPacket, UnknownAuthPlugin, UnsupportedProtocol, Warnings,
     },
     Error::{self, DriverError, MySqlError},
-    LocalInfileHandler, Opts, OptsBuilder, Params, QueryResult, Result, Transaction,
+    FromRowError, LocalInfileHandler, Opts, OptsBuilder, Params, QueryResult, Result, Row,
+    ServerError, Transaction,
     Value::{self, Bytes, NULL},
 };
 
@@ -72,19 +82,41 @@ use crate::DriverError::TlsNotSupported;
 use crate::SslOpts;
 
 #[cfg(feature = "binlog")]
-use self::binlog_stream::BinlogStream;
+use self::binlog_stream::{BinlogStream, BinlogStreamOpts};
 
+#[cfg(feature = "binlog")]
+pub mod binlog_file;
 #[cfg(feature = "binlog")]
 pub mod binlog_stream;
+pub mod bulk_loader;
+pub mod gtid;
+pub mod instrument;
+pub mod interceptor;
 pub mod local_infile;
 pub mod opts;
 pub mod pool;
 pub mod query;
+mod query_attributes;
+pub mod query_check;
 pub mod query_result;
 pub mod queryable;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
+pub mod row_ref;
+mod session_state;
+pub mod stats;
 pub mod stmt;
 mod stmt_cache;
 pub mod transaction;
+pub mod warning;
+pub mod xa;
+
+/// MariaDB extended capability – server won't send unchanged result set metadata
+/// upon prepared statement execution.
+const MARIADB_CLIENT_CACHE_METADATA: u32 = 1 << 4;
+
+/// Columns of a result set or an OK packet of a statement without one.
+type ResultSetMeta = Or<Arc<[Column]>, OkPacket<'static>>;
 
 /// Mutable connection.
 #[derive(Debug)]
@@ -161,8 +193,14 @@ impl DerefMut for ConnMut<'_, '_, '_> {
 #[derive(Debug)]
 struct ConnInner {
     opts: Opts,
-    stream: Option<MySyncFramed<Stream>>,
+    stream: Option<MySyncFramed<Metered<Stream>>>,
     stmt_cache: StmtCache,
+    /// Statement metadata cache shared between connections of a pool.
+    shared_stmt_cache: Option<Arc<SharedStmtCache>>,
+    /// Current default schema.
+    schema: Option<String>,
+    /// `true` if schema changes are reported by the server (`session_track_schema`).
+    schema_tracked: bool,
 
     // TODO: clean this up
     server_version: Option<(u16, u16, u16)>,
@@ -171,6 +209,12 @@ struct ConnInner {
     /// Last Ok packet, if any.
     ok_packet: Option<OkPacket<'static>>,
     capability_flags: CapabilityFlags,
+    /// Negotiated MariaDB extended capabilities.
+    mariadb_capability_flags: u32,
+    /// `true` if `resultset_metadata` session variable is set to `NONE`.
+    resultset_metadata_none: bool,
+    /// Attributes of the next query or statement execution.
+    query_attributes: QueryAttributes,
     connection_id: u32,
     status_flags: StatusFlags,
     character_set: u8,
@@ -178,6 +222,20 @@ struct ConnInner {
     connected: bool,
     has_results: bool,
     local_infile_handler: Option<LocalInfileHandler>,
+    /// Session state tracker, if session state restoration is enabled.
+    session_state: Option<SessionStateTracker>,
+    /// Statistics of this connection (bytes on the wire are counted by the stream).
+    stats: ConnStats,
+    /// `true` if a packet was written, but the response is not read yet.
+    awaiting_response: bool,
+    /// Query timed by the slow query log, if any.
+    pending_query: Option<PendingQuery>,
+    /// Warnings fetched for the last statement (see [`OptsBuilder::fetch_warnings`]).
+    fetched_warnings: Vec<Warning>,
+    /// `true` if `SHOW WARNINGS` is in progress.
+    fetching_warnings: bool,
+    /// `true` if the connection is unusable (see [`Conn::is_broken`]).
+    broken: bool,
 
     auth_plugin: AuthPlugin<'static>,
     nonce: Vec<u8>,
@@ -190,8 +248,14 @@ impl ConnInner {
     fn empty(opts: Opts) -> Self {
         ConnInner {
             stmt_cache: StmtCache::new(opts.get_stmt_cache_size()),
+            shared_stmt_cache: None,
+            schema: opts.get_db_name().map(String::from),
+            schema_tracked: false,
             stream: None,
             capability_flags: CapabilityFlags::empty(),
+            mariadb_capability_flags: 0,
+            resultset_metadata_none: false,
+            query_attributes: QueryAttributes::new(),
             status_flags: StatusFlags::empty(),
             connection_id: 0u32,
             character_set: 0u8,
@@ -202,6 +266,13 @@ impl ConnInner {
             server_version: None,
             mariadb_server_version: None,
             local_infile_handler: None,
+            session_state: None,
+            stats: ConnStats::default(),
+            awaiting_response: false,
+            pending_query: None,
+            fetched_warnings: Vec::new(),
+            fetching_warnings: false,
+            broken: false,
             auth_plugin: AuthPlugin::MysqlNativePassword,
             nonce: Vec::new(),
             reset_upon_return: opts.get_pool_opts().reset_connection(),
@@ -234,6 +305,67 @@ impl Conn {
         self.0.connection_id
     }
 
+    /// Returns `true` if the connection is unusable and should be dropped.
+    ///
+    /// A connection breaks on an I/O error (including read and write timeouts),
+    /// on a protocol violation or if the server has closed it (see [`Error::is_connection_broken`]),
+    /// because the state of the request-response exchange is unknown afterwards.
+    /// A broken [`PooledConn`] is never returned to its pool.
+    pub fn is_broken(&self) -> bool {
+        self.0.broken
+    }
+
+    /// Marks the connection as broken, if the error leaves it unusable
+    /// (see [`Conn::is_broken`]).
+    fn check_broken<T>(&mut self, result: Result<T>) -> Result<T> {
+        if matches!(result, Err(ref err) if err.is_connection_broken()) {
+            self.0.broken = true;
+        }
+        result
+    }
+
+    /// Returns counters of this connection's statement cache.
+    ///
+    /// Every [`Queryable::prep`] call (explicit or implicit) is either a hit or a miss,
+    /// evictions are caused by [`crate::OptsBuilder::stmt_cache_size`].
+    /// See [`Pool::stmt_cache_stats`] for the pool-wide metadata cache.
+    pub fn stmt_cache_stats(&self) -> StmtCacheStats {
+        self.0.stmt_cache.stats()
+    }
+
+    /// Returns statistics of this connection.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let before = conn.stats();
+    /// conn.query_drop("SELECT 1 UNION ALL SELECT 2")?;
+    /// let stats = conn.stats();
+    /// assert_eq!(stats.commands_sent - before.commands_sent, 1);
+    /// assert_eq!(stats.rows_received - before.rows_received, 2);
+    /// # });
+    /// ```
+    pub fn stats(&self) -> ConnStats {
+        let (bytes_read, bytes_written) = self
+            .0
+            .stream
+            .as_ref()
+            .map(|stream| stream.get_ref().counters())
+            .unwrap_or_default();
+        ConnStats {
+            bytes_read,
+            bytes_written,
+            ..self.0.stats
+        }
+    }
+
+    /// Makes this connection use the given pool-wide statement metadata cache.
+    pub(crate) fn set_shared_stmt_cache(&mut self, cache: Option<Arc<SharedStmtCache>>) {
+        self.0.shared_stmt_cache = cache;
+    }
+
     /// Returns number of rows affected by the last query.
     pub fn affected_rows(&self) -> u64 {
         self.0
@@ -255,6 +387,8 @@ impl Conn {
     }
 
     /// Returns number of warnings, reported by the server.
+    ///
+    /// See [`Conn::fetch_warnings`] to get the warnings themselves.
     pub fn warnings(&self) -> u16 {
         self.0
             .ok_packet
@@ -263,6 +397,46 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Returns warnings of the last statement fetched automatically
+    /// (see [`OptsBuilder::fetch_warnings`]).
+    pub fn fetched_warnings(&self) -> &[Warning] {
+        &self.0.fetched_warnings
+    }
+
+    /// Executes `SHOW WARNINGS` and returns warnings of the last statement.
+    ///
+    /// Unlike a plain `SHOW WARNINGS` query this keeps the result of the last statement,
+    /// so that [`Conn::warnings`], [`Conn::affected_rows`] and others are left intact.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(2))")?;
+    /// conn.query_drop("INSERT IGNORE INTO mysql.tbl VALUES ('foo')")?;
+    /// let warnings = conn.fetch_warnings()?;
+    /// assert_eq!(warnings.len(), usize::from(conn.warnings()));
+    /// assert_eq!(warnings[0].code, 1265);
+    /// # });
+    /// ```
+    pub fn fetch_warnings(&mut self) -> Result<Vec<Warning>> {
+        let ok_packet = self.0.ok_packet.clone();
+        let status_flags = self.0.status_flags;
+        self.0.fetching_warnings = true;
+        let result = self.query_map("SHOW WARNINGS", |(level, code, message): (String, _, _)| {
+            Warning {
+                level: WarningLevel::parse(&level),
+                code,
+                message,
+            }
+        });
+        self.0.fetching_warnings = false;
+        self.0.ok_packet = ok_packet;
+        self.0.status_flags = status_flags;
+        result
+    }
+
     /// [Info], reported by the server.
     ///
     /// Will be empty if not defined.
@@ -289,6 +463,31 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Returns `true` if there is an active transaction on this connection.
+    ///
+    /// The value is taken from the server status flags of the last OK packet,
+    /// so it also covers transactions started with plain SQL (e.g. `BEGIN`).
+    pub fn in_transaction(&self) -> bool {
+        self.0
+            .status_flags
+            .contains(StatusFlags::SERVER_STATUS_IN_TRANS)
+    }
+
+    /// Returns `true` if there is an active read-only transaction on this connection.
+    pub fn in_read_only_transaction(&self) -> bool {
+        self.0
+            .status_flags
+            .contains(StatusFlags::SERVER_STATUS_IN_TRANS_READONLY)
+    }
+
+    /// Returns `true` if autocommit mode is enabled on this connection,
+    /// as reported by the server status flags of the last OK packet.
+    pub fn autocommit(&self) -> bool {
+        self.0
+            .status_flags
+            .contains(StatusFlags::SERVER_STATUS_AUTOCOMMIT)
+    }
+
     pub fn session_state_changes(&self) -> io::Result<Vec<SessionStateInfo<'_>>> {
         self.0
             .ok_packet
@@ -298,20 +497,20 @@ impl Conn {
             .map(Option::unwrap_or_default)
     }
 
-    fn stream_ref(&self) -> &MySyncFramed<Stream> {
+    fn stream_ref(&self) -> &MySyncFramed<Metered<Stream>> {
         self.0.stream.as_ref().expect("incomplete connection")
     }
 
-    fn stream_mut(&mut self) -> &mut MySyncFramed<Stream> {
+    fn stream_mut(&mut self) -> &mut MySyncFramed<Metered<Stream>> {
         self.0.stream.as_mut().expect("incomplete connection")
     }
 
     fn is_insecure(&self) -> bool {
-        self.stream_ref().get_ref().is_insecure()
+        self.stream_ref().get_ref().get_ref().is_insecure()
     }
 
     fn is_socket(&self) -> bool {
-        self.stream_ref().get_ref().is_socket()
+        self.stream_ref().get_ref().get_ref().is_socket()
     }
 
     /// Check the connection can be improved.
@@ -362,23 +561,89 @@ impl Conn {
                 conn
             }
         };
-        for cmd in conn.0.opts.get_init() {
-            conn.query_drop(cmd)?;
-        }
+        conn.run_init()?;
         Ok(conn)
     }
 
+    /// Executes init commands and sets up session state tracking (if enabled).
+    fn run_init(&mut self) -> Result<()> {
+        self.0.session_state = None;
+        self.0.schema_tracked = false;
+        if self.has_capability(CapabilityFlags::CLIENT_SESSION_TRACK) {
+            if self.0.opts.get_pool_opts().restore_session_state() {
+                let schema = self.0.opts.get_db_name().map(String::from);
+                self.0.session_state = Some(SessionStateTracker::new(schema));
+                self.query_drop(ENABLE_TRACKERS)?;
+                self.0.schema_tracked = true;
+            } else if self.0.opts.get_pool_opts().shared_stmt_cache_size() > 0 {
+                // the shared statement cache is keyed by the default schema
+                self.query_drop(TRACK_SCHEMA)?;
+                self.0.schema_tracked = true;
+            }
+        }
+        for cmd in self.0.opts.get_init() {
+            self.query_drop(cmd)?;
+        }
+        if self.0.session_state.is_some() {
+            // changed variables are restored to these values
+            let variables: Vec<(String, Option<Vec<u8>>)> = self.query("SHOW SESSION VARIABLES")?;
+            if let Some(tracker) = self.0.session_state.as_mut() {
+                tracker.set_initial_variables(
+                    variables
+                        .into_iter()
+                        .filter_map(|(name, value)| Some((name, value?))),
+                );
+                tracker.start();
+            }
+        }
+        Ok(())
+    }
+
+    /// Restores the session state changed since the last restore
+    /// (see [`crate::PoolOpts::with_restore_session_state`]).
+    fn restore_session_state(&mut self) -> Result<()> {
+        let no_backslash_escape = self
+            .0
+            .status_flags
+            .contains(StatusFlags::SERVER_STATUS_NO_BACKSLASH_ESCAPES);
+        let restore = match self.0.session_state.as_mut() {
+            Some(tracker) if !tracker.is_dirty() => return Ok(()),
+            Some(tracker) => tracker.take_restore(no_backslash_escape),
+            None => None,
+        };
+        let restore = match restore {
+            Some(restore) => restore,
+            None => return self.reset(),
+        };
+
+        if let Some(tracker) = self.0.session_state.as_mut() {
+            tracker.pause();
+        }
+        if let Some(ref query) = restore.variables {
+            self.query_drop(query)?;
+        }
+        if let Some(ref schema) = restore.schema {
+            self.select_db(schema)?;
+        }
+        if let Some(tracker) = self.0.session_state.as_mut() {
+            tracker.start();
+        }
+        Ok(())
+    }
+
     fn exec_com_reset_connection(&mut self) -> Result<()> {
         self.write_command(Command::COM_RESET_CONNECTION, &[])?;
         let packet = self.read_packet()?;
         self.handle_ok::<CommonOkPacket>(&packet)?;
         self.0.last_command = 0;
+        self.0.resultset_metadata_none = false;
         self.0.stmt_cache.clear();
         Ok(())
     }
 
     fn exec_com_change_user(&mut self, opts: ChangeUserOpts) -> Result<()> {
         opts.update_opts(&mut self.0.opts);
+        self.0.schema = self.0.opts.get_db_name().map(String::from);
         let com_change_user = ComChangeUser::new()
             .with_user(self.0.opts.get_user().map(|x| x.as_bytes()))
             .with_database(self.0.opts.get_db_name().map(|x| x.as_bytes()))
@@ -400,6 +665,7 @@ impl Conn {
             .into_owned();
         self.write_command_raw(&com_change_user)?;
         self.0.last_command = 0;
+        self.0.resultset_metadata_none = false;
         self.0.stmt_cache.clear();
         self.continue_auth(false)
     }
@@ -433,11 +699,7 @@ impl Conn {
             Err(e) => return Err(e),
         }
 
-        for cmd in self.0.opts.get_init() {
-            self.query_drop(cmd)?;
-        }
-
-        Ok(())
+        self.run_init()
     }
 
     /// Executes [`COM_CHANGE_USER`][1].
@@ -457,13 +719,18 @@ impl Conn {
     ///
     /// [1]: https://dev.mysql.com/doc/c-api/5.7/en/mysql-change-user.html
     pub fn change_user(&mut self, opts: ChangeUserOpts) -> Result<()> {
-        self.exec_com_change_user(opts)
+        self.exec_com_change_user(opts)?;
+        // session trackers are turned off by the server, so the state can't be restored anymore
+        self.0.session_state = None;
+        self.0.schema_tracked = false;
+        Ok(())
     }
 
     fn switch_to_ssl(&mut self, ssl_opts: SslOpts) -> Result<()> {
         let stream = self.0.stream.take().expect("incomplete conn");
         let (in_buf, out_buf, codec, stream) = stream.destruct();
-        let stream = stream.make_secure(self.0.opts.get_host(), ssl_opts)?;
+        let host = self.0.opts.get_host();
+        let stream = stream.try_map(|stream| stream.make_secure(host, ssl_opts))?;
         let stream = MySyncFramed::construct(in_buf, out_buf, codec, stream);
         self.0.stream = Some(stream);
         Ok(())
@@ -509,16 +776,25 @@ impl Conn {
                 bind_address,
             )?
         };
-        self.0.stream = Some(MySyncFramed::new(stream));
+        self.0.stream = Some(MySyncFramed::new(Metered::new(stream)));
         Ok(())
     }
 
     fn raw_read_packet(&mut self, buffer: &mut Vec<u8>) -> Result<()> {
-        if !self.stream_mut().next_packet(buffer)? {
-            Err(Error::server_disconnected())
-        } else {
-            Ok(())
+        let start = Instant::now();
+        let result = self.stream_mut().next_packet(buffer);
+        self.0.stats.io_time += start.elapsed();
+        let result = match result {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(Error::server_disconnected()),
+            Err(err) => Err(err.into()),
+        };
+        self.check_broken(result)?;
+        self.0.stats.payload_bytes_read += buffer.len() as u64;
+        if mem::take(&mut self.0.awaiting_response) {
+            self.0.stats.round_trips += 1;
         }
+        Ok(())
     }
 
     fn read_packet(&mut self) -> Result<Buffer> {
@@ -529,7 +805,7 @@ impl Conn {
                     match ParseBuf(&buffer).parse(self.0.capability_flags)? {
                         ErrPacket::Error(server_error) => {
                             self.handle_err();
-                            return Err(MySqlError(From::from(server_error)));
+                            return self.check_broken(Err(MySqlError(From::from(server_error))));
                         }
                         ErrPacket::Progress(_progress_report) => {
                             // TODO: Report progress
@@ -557,8 +833,12 @@ impl Conn {
     }
 
     fn write_packet<T: Buf>(&mut self, data: &mut T) -> Result<()> {
-        self.stream_mut().send(data)?;
-        Ok(())
+        self.0.stats.payload_bytes_written += data.remaining() as u64;
+        self.0.awaiting_response = true;
+        let start = Instant::now();
+        let result = self.stream_mut().send(data);
+        self.0.stats.io_time += start.elapsed();
+        self.check_broken(result.map_err(Into::into))
     }
 
     fn handle_handshake(&mut self, hp: &HandshakePacket<'_>) {
@@ -578,14 +858,34 @@ impl Conn {
             .parse::<OkPacketDeserializer<T>>(self.0.capability_flags)?
             .into_inner();
         self.0.status_flags = ok.status_flags();
+        if self.0.session_state.is_some() || self.0.schema_tracked {
+            let is_text_query = self.0.last_command == Command::COM_QUERY as u8;
+            let changes = ok
+                .session_state_info()?
+                .iter()
+                .map(|info| info.decode().map(|change| change.into_owned()))
+                .collect::<io::Result<Vec<_>>>()?;
+            for change in &changes {
+                if let SessionStateChange::Schema(schema) = change {
+                    self.0.schema = Some(schema.as_str().into_owned());
+                }
+            }
+            if let Some(tracker) = self.0.session_state.as_mut() {
+                tracker.handle(changes, is_text_query);
+            }
+        }
         self.0.ok_packet = Some(ok.clone().into_owned());
         Ok(ok)
     }
 
     fn handle_err(&mut self) {
-        self.0.status_flags = StatusFlags::empty();
+        // an error doesn't end the transaction, so transaction status is kept
+        self.0.status_flags &= StatusFlags::SERVER_STATUS_IN_TRANS
+            | StatusFlags::SERVER_STATUS_AUTOCOMMIT
+            | StatusFlags::SERVER_STATUS_IN_TRANS_READONLY;
         self.0.has_results = false;
         self.0.ok_packet = None;
+        self.0.pending_query = None;
     }
 
     fn more_results_exists(&self) -> bool {
@@ -665,14 +965,18 @@ impl Conn {
         }
 
         self.handle_handshake(&handshake);
+        self.0.mariadb_capability_flags =
+            mariadb_server_capabilities(&payload, &handshake) & self.get_mariadb_client_flags();
 
         if self.is_insecure() {
             if let Some(ssl_opts) = self.0.opts.get_ssl_opts().cloned() {
                 if !self.has_capability(CapabilityFlags::CLIENT_SSL) {
                     return Err(DriverError(TlsNotSupported));
                 } else {
-                    self.do_ssl_request()?;
-                    self.switch_to_ssl(ssl_opts)?;
+                    instrument::in_span(instrument::tls_span(), || {
+                        self.do_ssl_request()?;
+                        self.switch_to_ssl(ssl_opts)
+                    })?;
                 }
             }
         }
@@ -695,8 +999,10 @@ impl Conn {
             _ => AuthPlugin::MysqlNativePassword,
         };
 
-        self.write_handshake_response()?;
-        self.continue_auth(false)?;
+        instrument::in_span(instrument::auth_span(self.0.auth_plugin.as_bytes()), || {
+            self.write_handshake_response()?;
+            self.continue_auth(false)
+        })?;
 
         if self.has_capability(CapabilityFlags::CLIENT_COMPRESS) {
             self.switch_to_compressed();
@@ -728,6 +1034,11 @@ impl Conn {
         if self.0.opts.get_connect_attrs().is_some() {
             client_flags.insert(CapabilityFlags::CLIENT_CONNECT_ATTRS);
         }
+        if self.0.opts.get_pool_opts().restore_session_state()
+            || self.0.opts.get_pool_opts().shared_stmt_cache_size() > 0
+        {
+            client_flags.insert(CapabilityFlags::CLIENT_SESSION_TRACK);
+        }
         if let Some(db_name) = self.0.opts.get_db_name() {
             if !db_name.is_empty() {
                 client_flags.insert(CapabilityFlags::CLIENT_CONNECT_WITH_DB);
@@ -736,9 +1047,28 @@ impl Conn {
         if self.is_insecure() && self.0.opts.get_ssl_opts().is_some() {
             client_flags.insert(CapabilityFlags::CLIENT_SSL);
         }
+        if self.0.opts.get_optional_resultset_metadata() {
+            client_flags.insert(CapabilityFlags::CLIENT_OPTIONAL_RESULTSET_METADATA);
+        }
+        client_flags.insert(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES);
         client_flags | self.0.opts.get_additional_capabilities()
     }
 
+    /// Returns MariaDB extended capabilities requested by the client.
+    fn get_mariadb_client_flags(&self) -> u32 {
+        if self.0.opts.get_optional_resultset_metadata() {
+            MARIADB_CLIENT_CACHE_METADATA
+        } else {
+            0
+        }
+    }
+
+    /// Writes MariaDB extended capabilities into the last four bytes of the filler
+    /// of a serialized `SslRequest` or `HandshakeResponse`.
+    fn write_mariadb_client_flags(&self, buf: &mut [u8]) {
+        buf[28..32].copy_from_slice(&self.0.mariadb_capability_flags.to_le_bytes());
+    }
+
     fn connect_attrs(&self) -> Option<HashMap<String, String>> {
         if let Some(attrs) = self.0.opts.get_connect_attrs() {
             let program_name = match attrs.get("program_name") {
@@ -781,7 +1111,10 @@ impl Conn {
             DEFAULT_MAX_ALLOWED_PACKET as u32,
             charset as u8,
         );
-        self.write_struct(&ssl_request)
+        let mut buf = get_buffer();
+        ssl_request.serialize(buf.as_mut());
+        self.write_mariadb_client_flags(buf.as_mut());
+        self.write_packet(&mut &*buf)
     }
 
     fn write_handshake_response(&mut self) -> Result<()> {
@@ -807,6 +1140,7 @@ impl Conn {
 
         let mut buf = get_buffer();
         handshake_response.serialize(buf.as_mut());
+        self.write_mariadb_client_flags(buf.as_mut());
         self.write_packet(&mut &*buf)
     }
 
@@ -911,20 +1245,68 @@ impl Conn {
     fn write_command_raw<T: MySerialize>(&mut self, cmd: &T) -> Result<()> {
         let mut buf = get_buffer();
         cmd.serialize(buf.as_mut());
+        self.write_command_buf(&buf)
+    }
+
+    /// Writes a serialized command.
+    fn write_command_buf(&mut self, buf: &[u8]) -> Result<()> {
         self.reset_seq_id();
-        debug_assert!(buf.len() > 0);
+        debug_assert!(!buf.is_empty());
         self.0.last_command = buf[0];
+        self.0.stats.commands_sent += 1;
         self.write_packet(&mut &*buf)
     }
 
+    /// Writes `COM_QUERY` with the given attributes
+    /// (attributes are ignored if `CLIENT_QUERY_ATTRIBUTES` is not negotiated).
+    fn write_com_query(&mut self, query: &[u8], attributes: &[(String, Value)]) -> Result<()> {
+        if !self.has_capability(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES) {
+            return self.write_command(Command::COM_QUERY, query);
+        }
+        let mut buf = get_buffer();
+        buf.as_mut().put_u8(Command::COM_QUERY as u8);
+        query_attributes::write_query_prefix(buf.as_mut(), attributes);
+        buf.as_mut().extend_from_slice(query);
+        self.write_command_buf(&buf)
+    }
+
+    /// Sets attributes of the next query or statement execution.
+    ///
+    /// Attributes are name-value pairs sent along with the query, that are accessible
+    /// on the server side via `mysql_query_attribute_string(name)` (requires the
+    /// `query_attributes` component). Those are applied to the very next `COM_QUERY` or
+    /// `COM_STMT_EXECUTE` issued by this connection and then discarded. Attributes
+    /// are silently ignored if the server doesn't support them (MySql prior to 8.0.23).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+    /// conn.with_attributes(&[("traceparent", traceparent)])
+    ///     .query_drop("DO 1")?;
+    /// conn.with_attributes(&[("traceparent", traceparent)])
+    ///     .exec_drop("DO ?", (1,))?;
+    /// # });
+    /// ```
+    pub fn with_attributes<K, V>(&mut self, attributes: &[(K, V)]) -> &mut Self
+    where
+        K: AsRef<str>,
+        V: Into<Value> + Clone,
+    {
+        self.0.query_attributes = attributes
+            .iter()
+            .map(|(name, value)| (name.as_ref().to_owned(), value.clone().into()))
+            .collect();
+        self
+    }
+
     fn write_command(&mut self, cmd: Command, data: &[u8]) -> Result<()> {
         let mut buf = get_buffer();
         buf.as_mut().put_u8(cmd as u8);
         buf.as_mut().extend_from_slice(data);
-
-        self.reset_seq_id();
-        self.0.last_command = buf[0];
-        self.write_packet(&mut &*buf)
+        self.write_command_buf(&buf)
     }
 
     fn send_long_data(&mut self, stmt_id: u32, params: &[Value]) -> Result<()> {
@@ -946,19 +1328,80 @@ impl Conn {
         Ok(())
     }
 
+    /// Executes the statement.
+    ///
+    /// If the server reports that the statement is invalid (`ER_NEED_REPREPARE`,
+    /// `ER_UNKNOWN_STMT_HANDLER`), then it is re-prepared and executed once again.
+    /// It's safe because the error is the first response to `COM_STMT_EXECUTE`.
     fn _execute(
         &mut self,
         stmt: &Statement,
         params: Params,
-    ) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
-        let exec_request = match &params {
+    ) -> Result<Or<Arc<[Column]>, OkPacket<'static>>> {
+        let params = match params {
+            Params::Named(_) => match stmt.named_params.as_ref() {
+                Some(named_params) => params.into_positional(named_params)?,
+                None => return Err(DriverError(NamedParamsForPositionalQuery)),
+            },
+            params => params,
+        };
+
+        let query = stmt
+            .inner
+            .query()
+            .map(|query| String::from_utf8_lossy(query))
+            .unwrap_or_default();
+        self.intercept(
+            QueryKind::Execute,
+            &query,
+            |this, query| {
+                this.start_statement(query, || params.clone());
+                let span = instrument::execute_span(this, stmt.inner.query().map(|x| &x[..]));
+                instrument::in_span(span, || match this.execute_request(&stmt.inner, &params) {
+                    Err(MySqlError(ref err))
+                        if err.code == ServerError::ER_NEED_REPREPARE as u16
+                            || err.code == ServerError::ER_UNKNOWN_STMT_HANDLER as u16 =>
+                    {
+                        let inner = this.reprepare(&stmt.inner)?;
+                        this.execute_request(&inner, &params)
+                    }
+                    result => result,
+                })
+            },
+            query_outcome,
+        )
+    }
+
+    /// Evicts the invalidated statement and prepares its query once again.
+    fn reprepare(&mut self, stmt: &Arc<InnerStmt>) -> Result<Arc<InnerStmt>> {
+        let key = self.0.stmt_cache.query(stmt.id());
+        // the statement might still exist on the server (`ER_NEED_REPREPARE`)
+        self.close(Statement::new(stmt.clone(), None))?;
+        match stmt.query().cloned() {
+            Some(query) => {
+                // metadata of other connections is stale as well
+                if let Some(cache) = self.0.shared_stmt_cache.as_ref() {
+                    cache.remove(&query);
+                }
+                let key = key.unwrap_or_else(|| query.clone());
+                self._prepare(&key, &query, None)
+            }
+            None => Err(DriverError(UnexpectedPacket)),
+        }
+    }
+
+    fn execute_request(
+        &mut self,
+        stmt: &InnerStmt,
+        params: &Params,
+    ) -> Result<Or<Arc<[Column]>, OkPacket<'static>>> {
+        let cached_columns = self.omit_result_metadata(stmt)?;
+        let params: &[Value] = match params {
             Params::Empty => {
                 if stmt.num_params() != 0 {
                     return Err(DriverError(MismatchedStmtParams(stmt.num_params(), 0)));
                 }
-
-                let (body, _) = ComStmtExecuteRequestBuilder::new(stmt.id()).build(&[]);
-                body
+                &[]
             }
             Params::Positional(params) => {
                 if stmt.num_params() != params.len() as u16 {
@@ -967,29 +1410,97 @@ impl Conn {
                         params.len(),
                     )));
                 }
+                params
+            }
+            Params::Named(_) => return Err(DriverError(NamedParamsForPositionalQuery)),
+        };
 
-                let (body, as_long_data) =
-                    ComStmtExecuteRequestBuilder::new(stmt.id()).build(params);
+        let attributes = mem::take(&mut self.0.query_attributes);
+        let with_attributes = self.has_capability(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES);
+        let values = query_attributes::execute_values(
+            params,
+            if with_attributes { &attributes } else { &[] },
+        );
 
-                if as_long_data {
-                    self.send_long_data(stmt.id(), params)?;
-                }
+        let (body, as_long_data) = ComStmtExecuteRequestBuilder::new(stmt.id()).build(&values);
+        if as_long_data {
+            self.send_long_data(stmt.id(), &values)?;
+        }
 
-                body
-            }
-            Params::Named(_) => {
-                if let Some(named_params) = stmt.named_params.as_ref() {
-                    return self._execute(stmt, params.into_positional(named_params)?);
-                } else {
-                    return Err(DriverError(NamedParamsForPositionalQuery));
-                }
+        let mut buf = get_buffer();
+        body.serialize(buf.as_mut());
+        if with_attributes {
+            query_attributes::rewrite_execute(buf.as_mut(), params.len(), &attributes);
+        }
+        self.write_command_buf(&buf)?;
+        let result = self.handle_result_set(cached_columns.clone())?;
+        if let (Some(cached), Or::A(columns)) = (cached_columns, &result) {
+            if !Arc::ptr_eq(&cached, columns) {
+                // metadata has changed and was sent again (`MARIADB_CLIENT_CACHE_METADATA`),
+                // so it should be used for following executions
+                self.0.stmt_cache.set_columns(stmt.id(), columns.clone());
             }
+        }
+        Ok(result)
+    }
+
+    /// Returns columns of the given statement, if the server is allowed to omit
+    /// result set metadata upon its execution (see [`OptsBuilder::optional_resultset_metadata`]).
+    fn omit_result_metadata(&mut self, stmt: &InnerStmt) -> Result<Option<Arc<[Column]>>> {
+        if !self.0.opts.get_optional_resultset_metadata() {
+            return Ok(None);
+        }
+        // the cached statement holds the latest metadata sent by the server
+        let columns = match self.0.stmt_cache.by_id(stmt.id()) {
+            Some(cached) => cached.shared_columns(),
+            None => stmt.shared_columns(),
+        };
+        self.set_resultset_metadata_none(columns.is_some())?;
+        Ok(columns)
+    }
+
+    /// Switches `resultset_metadata` session variable, if `CLIENT_OPTIONAL_RESULTSET_METADATA`
+    /// is negotiated and the variable has a different value.
+    fn set_resultset_metadata_none(&mut self, none: bool) -> Result<()> {
+        if !self.has_capability(CapabilityFlags::CLIENT_OPTIONAL_RESULTSET_METADATA)
+            || self.0.resultset_metadata_none == none
+        {
+            return Ok(());
+        }
+        let query = if none {
+            "SET SESSION resultset_metadata = NONE"
+        } else {
+            "SET SESSION resultset_metadata = FULL"
         };
-        self.write_command_raw(&exec_request)?;
-        self.handle_result_set()
+        // this is an implementation detail, so it should not be restored
+        if let Some(tracker) = self.0.session_state.as_mut() {
+            tracker.pause();
+        }
+        let result = self.write_com_query(query.as_bytes(), &[]).and_then(|_| {
+            let pld = self.read_packet()?;
+            self.handle_ok::<CommonOkPacket>(&pld).map(drop)
+        });
+        if let Some(tracker) = self.0.session_state.as_mut() {
+            tracker.start();
+        }
+        result?;
+        self.0.resultset_metadata_none = none;
+        Ok(())
+    }
+
+    /// Returns `true` if the column count packet is followed by the `metadata_follows` flag.
+    fn has_metadata_follows_flag(&self) -> bool {
+        self.has_capability(CapabilityFlags::CLIENT_OPTIONAL_RESULTSET_METADATA)
+            || (self.0.mariadb_capability_flags & MARIADB_CLIENT_CACHE_METADATA != 0
+                && self.0.last_command == Command::COM_STMT_EXECUTE as u8)
     }
 
     fn _start_transaction(&mut self, tx_opts: TxOpts) -> Result<()> {
+        let span = instrument::transaction_span(self, "START TRANSACTION");
+        instrument::in_span(span, || self.start_transaction_inner(tx_opts))
+    }
+
+    fn start_transaction_inner(&mut self, tx_opts: TxOpts) -> Result<()> {
         if let Some(i_level) = tx_opts.isolation_level() {
             self.query_drop(format!("SET TRANSACTION ISOLATION LEVEL {}", i_level))?;
         }
@@ -1017,34 +1528,114 @@ impl Conn {
     }
 
     fn send_local_infile(&mut self, file_name: &[u8]) -> Result<OkPacket<'static>> {
-        {
-            let buffer_size = cmp::min(
-                MAX_PAYLOAD_LEN - 4,
-                self.stream_ref().codec().max_allowed_packet - 4,
-            );
-            let chunk = vec![0u8; buffer_size].into_boxed_slice();
-            let maybe_handler = self
-                .0
-                .local_infile_handler
-                .clone()
-                .or_else(|| self.0.opts.get_local_infile_handler().cloned());
-            let mut local_infile = LocalInfile::new(io::Cursor::new(chunk), self);
-            if let Some(handler) = maybe_handler {
+        let maybe_handler = self
+            .0
+            .local_infile_handler
+            .clone()
+            .or_else(|| self.0.opts.get_local_infile_handler().cloned());
+        match maybe_handler {
+            Some(handler) => {
                 // Unwrap won't panic because we have exclusive access to `self` and this
                 // method is not re-entrant, because `LocalInfile` does not expose the
                 // connection.
                 let handler_fn = &mut *handler.0.lock()?;
-                handler_fn(file_name, &mut local_infile)?;
+                self.stream_local_infile(file_name, |file_name, local_infile| {
+                    handler_fn(file_name, local_infile)
+                })
             }
-            local_infile.flush()?;
+            None => self.stream_local_infile(file_name, |_, _| Ok(())),
         }
+    }
+
+    /// Streams the requested file using the given function.
+    fn stream_local_infile<F>(&mut self, file_name: &[u8], f: F) -> Result<OkPacket<'static>>
+    where
+        F: FnOnce(&[u8], &mut LocalInfile<'_>) -> io::Result<()>,
+    {
+        let result = {
+            let buffer_size = cmp::min(
+                MAX_PAYLOAD_LEN - 4,
+                self.stream_ref().codec().max_allowed_packet - 4,
+            );
+            let chunk = vec![0u8; buffer_size].into_boxed_slice();
+            let mut local_infile = LocalInfile::new(io::Cursor::new(chunk), self);
+            f(file_name, &mut local_infile).and_then(|_| local_infile.flush())
+        };
+        // The file is terminated even if the handler has failed,
+        // so that the connection remains usable.
         self.write_packet(&mut &[][..])?;
-        let payload = self.read_packet()?;
+        let payload = self.read_packet();
+        result?;
+        let payload = payload?;
         let ok = self.handle_ok::<CommonOkPacket>(&payload)?;
         Ok(ok.into_owned())
     }
 
-    fn handle_result_set(&mut self) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
+    /// Executes `LOAD DATA LOCAL INFILE` query and streams the requested file
+    /// using the given function instead of the local infile handler.
+    pub(crate) fn query_local_infile<F>(&mut self, query: &str, f: F) -> Result<OkPacket<'static>>
+    where
+        F: FnOnce(&[u8], &mut LocalInfile<'_>) -> io::Result<()>,
+    {
+        self.intercept(
+            QueryKind::Query,
+            query,
+            |this, query| {
+                let span = instrument::query_span(this, query.as_bytes());
+                instrument::in_span(span, || {
+                    this.set_resultset_metadata_none(false)?;
+                    let attributes = mem::take(&mut this.0.query_attributes);
+                    this.start_statement(query, || Params::Empty);
+                    this.write_com_query(query.as_bytes(), &attributes)?;
+                    let result = this.handle_result_set_inner(None);
+                    let pld = match this.check_broken(result)? {
+                        Or::A(Or::B(ok)) => return Ok(ok),
+                        Or::A(meta) => {
+                            // not a `LOAD DATA` query, so the result set is dropped
+                            // to keep the connection in sync
+                            drop(QueryResult::<Text>::new(ConnMut::Mut(this), meta));
+                            return Err(DriverError(UnexpectedPacket));
+                        }
+                        Or::B(pld) => pld,
+                    };
+                    let ok = this.stream_local_infile(&pld[1..], f)?;
+                    instrument::record_ok(&ok);
+                    this.finish_statement()?;
+                    Ok(ok)
+                })
+            },
+            |ok| QueryOutcome {
+                affected_rows: ok.affected_rows(),
+                has_result_set: false,
+            },
+        )
+    }
+
+    /// Handles the response to a query or statement execution.
+    ///
+    /// `cached_columns` are used if the server has omitted result set metadata.
+    fn handle_result_set(
+        &mut self,
+        cached_columns: Option<Arc<[Column]>>,
+    ) -> Result<Or<Arc<[Column]>, OkPacket<'static>>> {
+        let result = self.handle_result_set_inner(cached_columns);
+        let pld = match self.check_broken(result)? {
+            Or::A(meta) => return Ok(meta),
+            Or::B(pld) => pld,
+        };
+        // errors of the local infile handler leave the connection usable,
+        // so only transport errors mark it as broken here
+        let ok = self.send_local_infile(&pld[1..])?;
+        instrument::record_ok(&ok);
+        self.finish_statement()?;
+        Ok(Or::B(ok))
+    }
+
+    /// Returns the `LOCAL INFILE` request packet as `Or::B`, if the server has sent one.
+    fn handle_result_set_inner(
+        &mut self,
+        cached_columns: Option<Arc<[Column]>>,
+    ) -> Result<Or<ResultSetMeta, Buffer>> {
         if self.more_results_exists() {
             self.sync_seq_id();
         }
@@ -1052,33 +1643,151 @@ impl Conn {
         let pld = self.read_packet()?;
         match pld[0] {
             0x00 => {
-                let ok = self.handle_ok::<CommonOkPacket>(&pld)?;
-                Ok(Or::B(ok.into_owned()))
+                let ok = self.handle_ok::<CommonOkPacket>(&pld)?.into_owned();
+                instrument::record_ok(&ok);
+                self.finish_statement()?;
+                Ok(Or::A(Or::B(ok)))
             }
-            0xfb => match self.send_local_infile(&pld[1..]) {
-                Ok(ok) => Ok(Or::B(ok)),
-                Err(err) => Err(err),
-            },
+            0xfb => Ok(Or::B(pld)),
             _ => {
                 let mut reader = &pld[..];
                 let column_count = reader.read_lenenc_int()?;
-                let mut columns: Vec<Column> = Vec::with_capacity(column_count as usize);
-                for _ in 0..column_count {
-                    let pld = self.read_packet()?;
-                    let column = ParseBuf(&pld).parse(())?;
-                    columns.push(column);
-                }
+                let metadata_follows =
+                    !self.has_metadata_follows_flag() || reader.first() != Some(&0);
+                let columns: Arc<[Column]> = if metadata_follows {
+                    let mut columns: Vec<Column> = Vec::with_capacity(column_count as usize);
+                    for _ in 0..column_count {
+                        let pld = self.read_packet()?;
+                        let column = ParseBuf(&pld).parse(())?;
+                        columns.push(column);
+                    }
+                    columns.into()
+                } else {
+                    match cached_columns {
+                        Some(columns) if columns.len() as u64 == column_count => columns,
+                        _ => return Err(DriverError(UnexpectedPacket)),
+                    }
+                };
                 // skip eof packet
                 self.drop_packet()?;
                 self.0.has_results = column_count > 0;
-                Ok(Or::A(columns))
+                Ok(Or::A(Or::A(columns)))
+            }
+        }
+    }
+
+    fn _query(&mut self, query: &str) -> Result<Or<Arc<[Column]>, OkPacket<'static>>> {
+        self.intercept(
+            QueryKind::Query,
+            query,
+            |this, query| {
+                let span = instrument::query_span(this, query.as_bytes());
+                instrument::in_span(span, || {
+                    this.set_resultset_metadata_none(false)?;
+                    let attributes = mem::take(&mut this.0.query_attributes);
+                    this.start_statement(query, || Params::Empty);
+                    this.write_com_query(query.as_bytes(), &attributes)?;
+                    this.handle_result_set(None)
+                })
+            },
+            query_outcome,
+        )
+    }
+
+    /// Called before a statement is sent.
+    ///
+    /// Clears fetched warnings and starts timing the query, if the slow query log is enabled
+    /// (see [`OptsBuilder::slow_query_threshold`]).
+    fn start_statement(&mut self, query: &str, params: impl FnOnce() -> Params) {
+        if self.0.fetching_warnings {
+            return;
+        }
+        self.0.fetched_warnings.clear();
+        self.0.pending_query = self.0.opts.slow_query_log().map(|_| PendingQuery {
+            query: query.to_owned(),
+            params: params(),
+            start: Instant::now(),
+        });
+    }
+
+    /// Called once the whole response to a statement is read.
+    ///
+    /// Reports the statement to the slow query log (see [`OptsBuilder::slow_query_threshold`])
+    /// and fetches its warnings (see [`OptsBuilder::fetch_warnings`]).
+    fn finish_statement(&mut self) -> Result<()> {
+        if self.more_results_exists() || self.0.fetching_warnings {
+            return Ok(());
+        }
+        if let Some(pending) = self.0.pending_query.take() {
+            let elapsed = pending.start.elapsed();
+            if let Some(log) = self.0.opts.slow_query_log() {
+                if elapsed >= log.threshold {
+                    (log.handler)(&SlowQuery {
+                        query: &pending.query,
+                        params: &pending.params,
+                        elapsed,
+                        warnings: self.warnings(),
+                        connection_id: self.connection_id(),
+                    });
+                }
+            }
+        }
+        if self.warnings() > 0 && self.0.opts.get_fetch_warnings() {
+            self.0.fetched_warnings = self.fetch_warnings()?;
+            let promote = self
+                .0
+                .fetched_warnings
+                .iter()
+                .any(|warning| warning.level > WarningLevel::Note);
+            if promote && self.0.opts.get_warnings_as_errors() {
+                return Err(DriverError(Warnings(self.0.fetched_warnings.clone())));
             }
         }
+        Ok(())
     }
 
-    fn _query(&mut self, query: &str) -> Result<Or<Vec<Column>, OkPacket<'static>>> {
-        self.write_command(Command::COM_QUERY, query.as_bytes())?;
-        self.handle_result_set()
+    /// Passes the statement through interceptors (see [`OptsBuilder::interceptor`])
+    /// and calls `f` with the (possibly rewritten) query.
+    fn intercept<T>(
+        &mut self,
+        kind: QueryKind,
+        query: &str,
+        f: impl FnOnce(&mut Self, &str) -> Result<T>,
+        outcome: impl FnOnce(&T) -> QueryOutcome,
+    ) -> Result<T> {
+        if self.0.opts.get_interceptors().is_empty() {
+            return f(self, query);
+        }
+
+        let interceptors = self.0.opts.get_interceptors().to_vec();
+        let mut ctx = QueryContext::new(kind, self.connection_id());
+        let mut query = Cow::Borrowed(query);
+        let mut elapsed = Default::default();
+        let mut value = None;
+        let result = interceptors
+            .iter()
+            .try_for_each(|interceptor| interceptor.before_query(&mut ctx, &mut query))
+            .and_then(|_| {
+                if kind != QueryKind::Prepare {
+                    let attributes = ctx.attributes().iter().cloned();
+                    self.0.query_attributes.extend(attributes);
+                }
+                let start = Instant::now();
+                let result = f(self, &query);
+                elapsed = start.elapsed();
+                result
+            })
+            .map(|x| {
+                let outcome = outcome(&x);
+                value = Some(x);
+                outcome
+            });
+
+        for interceptor in interceptors.iter().rev() {
+            interceptor.after_query(&ctx, &result, elapsed);
+        }
+
+        result.map(|_| value.expect("value is set on success"))
     }
 
     /// Executes [`COM_PING`](https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_ping.html)
@@ -1102,42 +1811,184 @@ impl Conn {
         Ok(Transaction::new(self.into()))
     }
 
+    /// Starts new XA transaction branch with the given XID (`XA START`).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// # let mut other_conn = Conn::new(get_opts())?;
+    /// let xid = Xid::new("doctest").with_bqual("shard_1");
+    ///
+    /// let mut xa = conn.start_xa_transaction(xid)?;
+    /// xa.query_drop("DO 1")?;
+    /// let xid = xa.prepare()?;
+    ///
+    /// // prepared transaction could be committed on another connection
+    /// assert!(other_conn.xa_recover()?.contains(&xid));
+    /// other_conn.xa_commit(&xid)?;
+    /// # });
+    /// ```
+    pub fn start_xa_transaction(&mut self, xid: Xid) -> Result<XaTransaction<'_>> {
+        self.query_drop(format!("XA START {}", xid.to_sql()))?;
+        Ok(XaTransaction::new(self.into(), xid))
+    }
+
+    /// Commits prepared XA transaction (`XA COMMIT`).
+    pub fn xa_commit(&mut self, xid: &Xid) -> Result<()> {
+        self.query_drop(format!("XA COMMIT {}", xid.to_sql()))
+    }
+
+    /// Rolls back prepared XA transaction (`XA ROLLBACK`).
+    pub fn xa_rollback(&mut self, xid: &Xid) -> Result<()> {
+        self.query_drop(format!("XA ROLLBACK {}", xid.to_sql()))
+    }
+
+    /// Returns XIDs of prepared XA transactions (`XA RECOVER`).
+    pub fn xa_recover(&mut self) -> Result<Vec<Xid>> {
+        self.query_map("XA RECOVER", |row: Row| {
+            // `formatID` is signed, so it is read as `i64` and checked by `Xid::from_recover_row`
+            from_row_opt::<(i64, usize, usize, Vec<u8>)>(row.clone())
+                .ok()
+                .and_then(|(format_id, gtrid_length, bqual_length, data)| {
+                    Xid::from_recover_row(format_id, gtrid_length, bqual_length, data)
+                })
+                .ok_or_else(|| Error::from(FromRowError(row)))
+        })?
+        .into_iter()
+        .collect()
+    }
+
     fn _true_prepare(&mut self, query: &[u8]) -> Result<InnerStmt> {
+        self._true_prepare_with_metadata(query, None)
+    }
+
+    /// Prepares the query reusing column definitions cached in `metadata`
+    /// if raw packets sent by the server are the same (otherwise `metadata` gets updated).
+    fn _true_prepare_with_metadata(
+        &mut self,
+        query: &[u8],
+        metadata: Option<&mut StmtMetadata>,
+    ) -> Result<InnerStmt> {
+        let result = self.true_prepare_inner(query, metadata);
+        self.check_broken(result)
+    }
+
+    fn true_prepare_inner(
+        &mut self,
+        query: &[u8],
+        mut metadata: Option<&mut StmtMetadata>,
+    ) -> Result<InnerStmt> {
+        // metadata is required to prepare a statement
+        self.set_resultset_metadata_none(false)?;
         self.write_command(Command::COM_STMT_PREPARE, query)?;
+        self.0.stats.prepares += 1;
         let pld = self.read_packet()?;
         let mut stmt = ParseBuf(&pld).parse::<InnerStmt>(self.connection_id())?;
+        let mut params = None;
         if stmt.num_params() > 0 {
-            let mut params: Vec<Column> = Vec::with_capacity(stmt.num_params() as usize);
-            for _ in 0..stmt.num_params() {
-                let pld = self.read_packet()?;
-                params.push(ParseBuf(&pld).parse(())?);
-            }
-            stmt = stmt.with_params(Some(params));
-            self.drop_packet()?;
+            let cached = metadata.as_ref().and_then(|x| x.params.as_ref());
+            let defs = self.read_column_defs(stmt.num_params(), cached)?;
+            stmt = stmt.with_params(Some(defs.columns.clone()));
+            params = Some(defs);
         }
+        let mut columns = None;
         if stmt.num_columns() > 0 {
-            let mut columns: Vec<Column> = Vec::with_capacity(stmt.num_columns() as usize);
-            for _ in 0..stmt.num_columns() {
-                let pld = self.read_packet()?;
-                columns.push(ParseBuf(&pld).parse(())?);
-            }
-            stmt = stmt.with_columns(Some(columns));
-            self.drop_packet()?;
+            let cached = metadata.as_ref().and_then(|x| x.columns.as_ref());
+            let defs = self.read_column_defs(stmt.num_columns(), cached)?;
+            stmt = stmt.with_columns(Some(defs.columns.clone()));
+            columns = Some(defs);
+        }
+        if let Some(metadata) = metadata.as_mut() {
+            metadata.params = params;
+            metadata.columns = columns;
         }
         Ok(stmt)
     }
 
-    fn _prepare(&mut self, query: &[u8]) -> Result<Arc<InnerStmt>> {
-        if let Some(entry) = self.0.stmt_cache.by_query(query) {
-            return Ok(entry.stmt.clone());
+    /// Reads `num` column definitions followed by an EOF packet.
+    ///
+    /// Definitions are only parsed if raw packets differ from the `cached` ones.
+    fn read_column_defs(&mut self, num: u16, cached: Option<&ColumnDefs>) -> Result<ColumnDefs> {
+        let mut packets = Vec::with_capacity(num as usize);
+        for _ in 0..num {
+            packets.push(self.read_packet()?.as_ref().to_vec());
         }
+        self.drop_packet()?;
+        match cached {
+            Some(cached) if *cached.packets == packets[..] => Ok(cached.clone()),
+            _ => {
+                let columns = packets
+                    .iter()
+                    .map(|pld| ParseBuf(pld).parse(()))
+                    .collect::<io::Result<Vec<Column>>>()?;
+                Ok(ColumnDefs {
+                    packets: packets.into(),
+                    columns: columns.into(),
+                })
+            }
+        }
+    }
 
-        let inner_st = Arc::new(self._true_prepare(query)?);
+    /// Returns parameter and result set metadata of the given query without executing it.
+    ///
+    /// The query is prepared on the server and the statement gets closed right away,
+    /// so the statement cache is left intact. Named parameters are supported.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// let describe = conn.describe("SELECT :foo + 1 AS bar")?;
+    /// assert_eq!(describe.num_params(), 1);
+    /// assert_eq!(describe.named_params(), Some(&[b"foo".to_vec()][..]));
+    /// assert_eq!(describe.columns()[0].name_str(), "bar");
+    /// # });
+    /// ```
+    pub fn describe<T: AsRef<str>>(&mut self, query: T) -> Result<Describe> {
+        let parsed = ParsedNamedParams::parse(query.as_ref().as_bytes())?;
+        let named_params = collect_named_params(&parsed);
+        let inner = self._true_prepare(parsed.query())?;
+        self.write_command_raw(&ComStmtClose::new(inner.id()))?;
+        Ok(Describe::new(inner, named_params))
+    }
+
+    /// Prepares the `query` unless there is a statement cached by the given `key`.
+    fn _prepare(
+        &mut self,
+        key: &[u8],
+        query: &[u8],
+        metadata: Option<&mut StmtMetadata>,
+    ) -> Result<Arc<InnerStmt>> {
+        let span = instrument::prepare_span(self, query);
+        instrument::in_span(span, || self.prepare_inner(key, query, metadata))
+    }
+
+    fn prepare_inner(
+        &mut self,
+        key: &[u8],
+        query: &[u8],
+        metadata: Option<&mut StmtMetadata>,
+    ) -> Result<Arc<InnerStmt>> {
+        if let Some(entry) = self.0.stmt_cache.by_query(key) {
+            self.0.stats.prepare_cache_hits += 1;
+            instrument::record_cached(true);
+            return Ok(entry.stmt.clone());
+        }
+        instrument::record_cached(false);
+
+        let query = Arc::new(query.to_vec());
+        let inner_st = Arc::new(
+            self._true_prepare_with_metadata(&query, metadata)?
+                .with_query(query.clone()),
+        );
 
         if let Some(old_stmt) = self
             .0
             .stmt_cache
-            .put(Arc::new(query.into()), inner_st.clone())
+            .put(Arc::new(key.to_vec()), inner_st.clone())
         {
             self.close(Statement::new(old_stmt, None))?;
         }
@@ -1149,23 +2000,28 @@ impl Conn {
         if self.0.connected {
             return Ok(());
         }
-        self.do_handshake()
-            .and_then(|_| match self.0.opts.get_max_allowed_packet() {
-                Some(x) => Ok(x),
-                None => Ok(from_value_opt::<usize>(
-                    self.get_system_var("max_allowed_packet")?.unwrap_or(NULL),
-                )
-                .unwrap_or(0)),
-            })
-            .and_then(|max_allowed_packet| {
-                if max_allowed_packet == 0 {
-                    Err(DriverError(SetupError))
-                } else {
-                    self.stream_mut().codec_mut().max_allowed_packet = max_allowed_packet;
-                    self.0.connected = true;
-                    Ok(())
-                }
-            })
+        let span = instrument::connect_span(&self.0.opts);
+        instrument::in_span(span, || self.connect_inner())
+    }
+
+    fn connect_inner(&mut self) -> Result<()> {
+        self.do_handshake()?;
+        let tls = !self.is_insecure() && !self.is_socket();
+        instrument::record_connected(self, tls, self.0.auth_plugin.as_bytes());
+        let max_allowed_packet = match self.0.opts.get_max_allowed_packet() {
+            Some(x) => x,
+            None => {
+                from_value_opt::<usize>(self.get_system_var("max_allowed_packet")?.unwrap_or(NULL))
+                    .unwrap_or(0)
+            }
+        };
+        if max_allowed_packet == 0 {
+            Err(DriverError(SetupError))
+        } else {
+            self.stream_mut().codec_mut().max_allowed_packet = max_allowed_packet;
+            self.0.connected = true;
+            Ok(())
+        }
     }
 
     fn get_system_var(&mut self, name: &str) -> Result<Option<Value>> {
@@ -1173,6 +2029,11 @@ impl Conn {
     }
 
     fn next_row_packet(&mut self) -> Result<Option<Buffer>> {
+        let result = self.next_row_packet_inner();
+        self.check_broken(result)
+    }
+
+    fn next_row_packet_inner(&mut self) -> Result<Option<Buffer>> {
         if !self.0.has_results {
             return Ok(None);
         }
@@ -1183,14 +2044,17 @@ impl Conn {
             if pld[0] == 0xfe && pld.len() < MAX_PAYLOAD_LEN {
                 self.0.has_results = false;
                 self.handle_ok::<ResultSetTerminator>(&pld)?;
+                self.finish_statement()?;
                 return Ok(None);
             }
         } else if pld[0] == 0xfe && pld.len() < 8 {
             self.0.has_results = false;
             self.handle_ok::<OldEofPacket>(&pld)?;
+            self.finish_statement()?;
             return Ok(None);
         }
 
+        self.0.stats.rows_received += 1;
         Ok(Some(pld))
     }
 
@@ -1208,6 +2072,59 @@ impl Conn {
         self.0.local_infile_handler = handler;
     }
 
+    /// Loads data from the given reader into the table using `LOAD DATA LOCAL INFILE`.
+    ///
+    /// The statement is generated according to the given format (the `table` may be qualified
+    /// with a database name, i.e. `db.table`). The reader is streamed regardless of the
+    /// connection's local infile handler, which is left intact.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT, name TEXT)")?;
+    ///
+    /// let data = "id,name\n1,foo\n2,\"bar, baz\"\n";
+    /// let format = LoadDataFormat::new()
+    ///     .with_columns(["id", "name"])
+    ///     .with_fields_terminated_by(",")
+    ///     .with_enclosed_by(Some(b'"'))
+    ///     .with_ignore_lines(1);
+    ///
+    /// match conn.load_data("mysql.tbl", data.as_bytes(), &format) {
+    ///     Ok(info) => assert_eq!(info.records, 2),
+    ///     Err(Error::MySqlError(ref e)) if e.code == 1148 || e.code == 3948 => {
+    ///         // functionality is not supported by the server
+    ///         return Ok(());
+    ///     }
+    ///     Err(err) => return Err(err.into()),
+    /// }
+    ///
+    /// let rows: Vec<(u32, String)> = conn.query("SELECT id, name FROM mysql.tbl")?;
+    /// assert_eq!(rows, vec![(1, "foo".into()), (2, "bar, baz".into())]);
+    /// # });
+    /// ```
+    pub fn load_data<R: io::Read>(
+        &mut self,
+        table: &str,
+        mut reader: R,
+        format: &LoadDataFormat,
+    ) -> Result<LoadDataInfo> {
+        let stmt = format.statement("load_data", table, self.no_backslash_escape());
+        self.query_local_infile(&stmt, |_, writer| io::copy(&mut reader, writer).map(drop))?;
+        Ok(self.load_data_info())
+    }
+
+    /// Returns information about the last `LOAD DATA` statement.
+    pub(crate) fn load_data_info(&self) -> LoadDataInfo {
+        LoadDataInfo::parse(&self.info_str()).unwrap_or_else(|| LoadDataInfo {
+            records: self.affected_rows(),
+            warnings: self.warnings().into(),
+            ..Default::default()
+        })
+    }
+
     pub fn no_backslash_escape(&self) -> bool {
         self.0
             .status_flags
@@ -1228,7 +2145,17 @@ impl Conn {
     }
 
     #[cfg(feature = "binlog")]
-    fn request_binlog(&mut self, request: BinlogRequest<'_>) -> Result<()> {
+    fn request_binlog(
+        &mut self,
+        request: &BinlogRequest<'_>,
+        stream_opts: &BinlogStreamOpts,
+    ) -> Result<()> {
+        if let Some(period) = stream_opts.heartbeat_period() {
+            self.query_drop(format!(
+                "SET @master_heartbeat_period = {}",
+                period.as_nanos()
+            ))?;
+        }
         self.register_as_slave(request.server_id())?;
         self.write_command_raw(&request.as_cmd())?;
         Ok(())
@@ -1241,15 +2168,44 @@ impl Conn {
     /// of the first known binlog.
     #[cfg(feature = "binlog")]
     #[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
-    pub fn get_binlog_stream(mut self, request: BinlogRequest<'_>) -> Result<BinlogStream> {
-        self.request_binlog(request)?;
-        Ok(BinlogStream::new(self))
+    pub fn get_binlog_stream(self, request: BinlogRequest<'_>) -> Result<BinlogStream> {
+        self.get_binlog_stream_with_opts(request, BinlogStreamOpts::default())
+    }
+
+    /// Turns this connection into a binlog stream using the given options
+    /// (see [`Conn::get_binlog_stream`]).
+    #[cfg(feature = "binlog")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
+    pub fn get_binlog_stream_with_opts(
+        mut self,
+        request: BinlogRequest<'_>,
+        stream_opts: BinlogStreamOpts,
+    ) -> Result<BinlogStream> {
+        self.request_binlog(&request, &stream_opts)?;
+        Ok(BinlogStream::new(self, &request, stream_opts))
+    }
+
+    /// Returns the set of GTIDs of transactions executed on the server
+    /// (value of the `gtid_executed` system variable, not supported by MariaDB).
+    ///
+    /// It's useful to request a binlog stream using `COM_BINLOG_DUMP_GTID`
+    /// (see [`GtidSet::binlog_request`]).
+    pub fn gtid_executed(&mut self) -> Result<GtidSet> {
+        let gtid_executed: Option<String> = self.query_first("SELECT @@GLOBAL.gtid_executed")?;
+        Ok(gtid_executed.unwrap_or_default().parse()?)
     }
 
     fn cleanup_for_pool(&mut self) -> Result<()> {
         self.set_local_infile_handler(None);
         if self.0.reset_upon_return {
             self.reset()?;
+        } else {
+            if self.in_transaction() {
+                self.query_drop("ROLLBACK")?;
+            }
+            if self.0.opts.get_pool_opts().restore_session_state() {
+                self.restore_session_state()?;
+            }
         }
 
         self.0.reset_upon_return = self.0.opts.get_pool_opts().reset_connection();
@@ -1261,7 +2217,7 @@ impl Conn {
 #[cfg(unix)]
 impl AsRawFd for Conn {
     fn as_raw_fd(&self) -> RawFd {
-        self.stream_ref().get_ref().as_raw_fd()
+        self.stream_ref().get_ref().get_ref().as_raw_fd()
     }
 }
 
@@ -1272,17 +2228,12 @@ impl Queryable for Conn {
     }
 
     fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
-        let query = query.as_ref();
-        let parsed = ParsedNamedParams::parse(query.as_bytes())?;
-        let named_params: Vec<Vec<u8>> =
-            parsed.params().iter().map(|param| param.to_vec()).collect();
-        let named_params = if named_params.is_empty() {
-            None
-        } else {
-            Some(named_params)
-        };
-        self._prepare(parsed.borrow().query())
-            .map(|inner| Statement::new(inner, named_params))
+        self.intercept(
+            QueryKind::Prepare,
+            query.as_ref(),
+            |this, rewritten| this.prep_inner(query.as_ref(), rewritten),
+            |_| QueryOutcome::default(),
+        )
     }
 
     fn close(&mut self, stmt: Statement) -> Result<()> {
@@ -1302,8 +2253,113 @@ impl Queryable for Conn {
     }
 }
 
+impl Conn {
+    /// Prepares the query using statement caches.
+    ///
+    /// Statements are cached by the `original` query, i.e. before interceptors rewrote it.
+    fn prep_inner(&mut self, original: &str, query: &str) -> Result<Statement> {
+        let key = original.as_bytes();
+        let query = query.as_bytes();
+
+        // metadata depends on the default schema, so it has to be tracked
+        let shared_stmt_cache = self
+            .0
+            .shared_stmt_cache
+            .clone()
+            .filter(|_| self.0.schema_tracked);
+        let cache = match shared_stmt_cache {
+            Some(cache) if !self.0.stmt_cache.contains_query(key) => cache,
+            _ => {
+                let parsed = ParsedNamedParams::parse(query)?;
+                let named_params = collect_named_params(&parsed);
+                let inner = self._prepare(key, parsed.query(), None)?;
+                return Ok(Statement::new(inner, named_params));
+            }
+        };
+
+        let schema = self.0.schema.clone();
+        let cached = cache.get(schema.as_deref(), query);
+        let mut metadata = match cached.as_deref() {
+            Some(metadata) => metadata.clone(),
+            None => {
+                let parsed = ParsedNamedParams::parse(query)?;
+                StmtMetadata {
+                    query: parsed.query().to_vec(),
+                    named_params: collect_named_params(&parsed),
+                    params: None,
+                    columns: None,
+                }
+            }
+        };
+
+        let prepared_query = metadata.query.clone();
+        let inner = self._prepare(key, &prepared_query, Some(&mut metadata))?;
+        let named_params = metadata.named_params.clone();
+        if cached.as_deref() != Some(&metadata) {
+            cache.put(schema.as_deref(), query.to_vec(), Arc::new(metadata));
+        }
+        Ok(Statement::new(inner, named_params))
+    }
+}
+
+/// Quotes an identifier using backticks.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Returns the outcome of a query or statement execution for interceptors.
+fn query_outcome(meta: &Or<Arc<[Column]>, OkPacket<'static>>) -> QueryOutcome {
+    match meta {
+        Or::A(_) => QueryOutcome {
+            affected_rows: 0,
+            has_result_set: true,
+        },
+        Or::B(ok) => QueryOutcome {
+            affected_rows: ok.affected_rows(),
+            has_result_set: false,
+        },
+    }
+}
+
+/// Returns MariaDB extended capabilities announced in the initial handshake packet.
+///
+/// Those are stored in the last four bytes of the reserved area, and only if the server
+/// doesn't set `CLIENT_LONG_PASSWORD` (which MariaDB uses to tell itself from MySql).
+fn mariadb_server_capabilities(payload: &[u8], handshake: &HandshakePacket<'_>) -> u32 {
+    if handshake
+        .capabilities()
+        .contains(CapabilityFlags::CLIENT_LONG_PASSWORD)
+    {
+        return 0;
+    }
+    // protocol version, server version and its zero terminator
+    let offset = match payload.iter().skip(1).position(|x| *x == 0) {
+        Some(pos) => pos + 2,
+        None => return 0,
+    };
+    match payload.get(offset + 27..offset + 31) {
+        Some(bytes) => u32::from_le_bytes(bytes.try_into().unwrap()),
+        None => 0,
+    }
+}
+
+/// Returns names of named parameters, if any.
+fn collect_named_params(parsed: &ParsedNamedParams<'_>) -> Option<Vec<Vec<u8>>> {
+    let named_params: Vec<Vec<u8>> = parsed.params().iter().map(|param| param.to_vec()).collect();
+    if named_params.is_empty() {
+        None
+    } else {
+        Some(named_params)
+    }
+}
+
 impl Drop for Conn {
     fn drop(&mut self) {
+        if self.0.broken {
+            // there is no point in talking to the server
+            return;
+        }
+
         let stmt_cache = mem::replace(&mut self.0.stmt_cache, StmtCache::new(0));
 
         for (_, entry) in stmt_cache.into_iter() {
@@ -1339,11 +2395,16 @@ mod test {
             from_row, from_value, params,
             prelude::*,
             test_misc::get_opts,
-            Conn,
-            DriverError::{MissingNamedParameter, NamedParamsForPositionalQuery},
-            Error::DriverError,
-            LocalInfileHandler, Opts, OptsBuilder, Pool, TxOpts,
+            BulkLoader, Conn,
+            DriverError::{
+                MismatchedStmtParams, MissingNamedParameter, NamedParamsForPositionalQuery,
+                UnexpectedPacket,
+            },
+            Error::{self, DriverError},
+            LoadDataFormat, LocalInfileHandler, LocalInfileSources, Opts, OptsBuilder, Pool,
+            TxOpts,
             Value::{self, Bytes, Date, Float, Int, NULL},
+            Xid,
         };
 
         fn get_system_variable<T>(conn: &mut Conn, name: &str) -> T
@@ -1778,6 +2839,113 @@ mod test {
                 Some(4_usize),
             );
         }
+
+        #[test]
+        fn should_handle_savepoints() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")
+                .unwrap();
+
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            tx.query_drop("INSERT INTO mysql.tbl(a) VALUES(1)").unwrap();
+            {
+                let mut outer = tx.nested().unwrap();
+                outer
+                    .query_drop("INSERT INTO mysql.tbl(a) VALUES(2)")
+                    .unwrap();
+                {
+                    let mut inner = outer.nested().unwrap();
+                    assert_eq!(inner.name(), "__mysql_nested_2");
+                    inner
+                        .query_drop("INSERT INTO mysql.tbl(a) VALUES(3)")
+                        .unwrap();
+                    // implicit rollback to savepoint
+                }
+                outer.release().unwrap();
+            }
+            let mut savepoint = tx.savepoint("foo`bar").unwrap();
+            savepoint
+                .exec_drop("INSERT INTO mysql.tbl(a) VALUES(?)", (4,))
+                .unwrap();
+            savepoint.rollback().unwrap();
+            tx.commit().unwrap();
+
+            let values: Vec<u8> = conn.query("SELECT a FROM mysql.tbl ORDER BY a").unwrap();
+            assert_eq!(values, vec![1, 2]);
+        }
+
+        #[test]
+        fn should_handle_xa_transactions() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let mut other_conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("DROP TABLE IF EXISTS mysql.xa_tbl")
+                .unwrap();
+            conn.query_drop("CREATE TABLE mysql.xa_tbl(a INT)").unwrap();
+
+            // two-phase commit finished on another connection
+            let xid = Xid::new(b"\x00xa_test".to_vec())
+                .with_bqual("branch")
+                .with_format_id(42);
+            let mut xa = conn.start_xa_transaction(xid.clone()).unwrap();
+            xa.exec_drop("INSERT INTO mysql.xa_tbl(a) VALUES(?)", (1,))
+                .unwrap();
+            assert_eq!(xa.prepare().unwrap(), xid);
+            assert!(other_conn.xa_recover().unwrap().contains(&xid));
+            other_conn.xa_commit(&xid).unwrap();
+            assert!(!other_conn.xa_recover().unwrap().contains(&xid));
+
+            // prepared and rolled back
+            let xid = Xid::new("xa_test_rollback");
+            let mut xa = conn.start_xa_transaction(xid.clone()).unwrap();
+            xa.query_drop("INSERT INTO mysql.xa_tbl(a) VALUES(2)")
+                .unwrap();
+            let xid = xa.prepare().unwrap();
+            conn.xa_rollback(&xid).unwrap();
+
+            // one-phase commit
+            let mut xa = conn
+                .start_xa_transaction(Xid::new("xa_test_commit"))
+                .unwrap();
+            xa.query_drop("INSERT INTO mysql.xa_tbl(a) VALUES(3)")
+                .unwrap();
+            xa.commit().unwrap();
+
+            // implicit rollback
+            {
+                let mut xa = conn.start_xa_transaction(Xid::new("xa_test_drop")).unwrap();
+                xa.query_drop("INSERT INTO mysql.xa_tbl(a) VALUES(4)")
+                    .unwrap();
+            }
+
+            let values: Vec<u8> = conn.query("SELECT a FROM mysql.xa_tbl ORDER BY a").unwrap();
+            assert_eq!(values, vec![1, 3]);
+            conn.query_drop("DROP TABLE mysql.xa_tbl").unwrap();
+        }
+
+        #[test]
+        fn should_track_transaction_status() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            assert!(!conn.in_transaction());
+            assert!(conn.autocommit());
+
+            let mut tx = conn.start_transaction(TxOpts::default()).unwrap();
+            assert!(tx.conn.in_transaction());
+            tx.query_drop("SELECT * FROM unknown_table").unwrap_err();
+            assert!(tx.conn.in_transaction());
+            tx.rollback().unwrap();
+            assert!(!conn.in_transaction());
+
+            conn.query_drop("BEGIN").unwrap();
+            assert!(conn.in_transaction());
+            conn.query_drop("COMMIT").unwrap();
+            assert!(!conn.in_transaction());
+
+            conn.query_drop("SET autocommit = 0").unwrap();
+            assert!(!conn.autocommit());
+            conn.query_drop("SET autocommit = 1").unwrap();
+            assert!(conn.autocommit());
+        }
+
         #[test]
         fn should_handle_LOCAL_INFILE_with_custom_handler() {
             let mut conn = Conn::new(get_opts()).unwrap();
@@ -1809,6 +2977,146 @@ mod test {
             assert_eq!(count, 1536);
         }
 
+        #[test]
+        fn should_load_data_from_readers() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT, name TEXT)")
+                .unwrap();
+
+            let format = LoadDataFormat::new()
+                .with_columns(["name", "id"])
+                .with_fields_terminated_by(",")
+                .with_enclosed_by(Some(b'"'));
+            match conn.load_data("mysql.tbl", &b"foo,1\n\"b,ar\",2\n"[..], &format) {
+                Ok(info) => assert_eq!(info.records, 2),
+                Err(ref err) if format!("{}", err).find("not allowed").is_some() => {
+                    return;
+                }
+                Err(err) => panic!("ERROR {}", err),
+            }
+
+            // named sources and unknown sources
+            let sources = LocalInfileSources::new();
+            sources.register("three", &b"3\tbaz\n"[..]);
+            conn.set_local_infile_handler(Some(sources.handler()));
+            conn.query_drop("LOAD DATA LOCAL INFILE 'three' INTO TABLE mysql.tbl")
+                .unwrap();
+            assert!(sources.is_empty());
+            let err = conn
+                .query_drop("LOAD DATA LOCAL INFILE 'three' INTO TABLE mysql.tbl")
+                .unwrap_err();
+            assert!(matches!(err, Error::IoError(_)), "{}", err);
+
+            // files outside of allowed directories are rejected
+            conn.set_local_infile_handler(Some(LocalInfileHandler::allowed_dirs([
+                std::env::temp_dir().join("mysql-no-such-dir"),
+            ])));
+            let err = conn
+                .query_drop("LOAD DATA LOCAL INFILE '/etc/passwd' INTO TABLE mysql.tbl")
+                .unwrap_err();
+            assert!(matches!(err, Error::IoError(_)), "{}", err);
+
+            // connection is still usable
+            assert!(!conn.is_broken());
+            let rows: Vec<(u32, String)> = conn
+                .query("SELECT id, name FROM mysql.tbl ORDER BY id")
+                .unwrap();
+            assert_eq!(
+                rows,
+                vec![(1, "foo".into()), (2, "b,ar".into()), (3, "baz".into())]
+            );
+        }
+
+        #[test]
+        fn should_bulk_load_rows() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop(
+                "CREATE TEMPORARY TABLE mysql.tbl(id INT, name TEXT, data BLOB, ts DATETIME(6))",
+            )
+            .unwrap();
+
+            let mut loader =
+                BulkLoader::new(&mut conn, "mysql.tbl").columns(["id", "name", "data"]);
+            let rows = (0..100_u8).map(|i| (i, format!("a\tb\\{}\n", i), vec![0_u8, b'\n', i]));
+            match loader.load_rows(rows) {
+                Ok(info) => assert_eq!(info.records, 100),
+                Err(ref err) if format!("{}", err).find("not allowed").is_some() => {
+                    return;
+                }
+                Err(err) => panic!("ERROR {}", err),
+            }
+            let info = loader
+                .load_rows(vec![
+                    params! { "data" => Value::NULL, "name" => "named", "id" => 100 },
+                ])
+                .unwrap();
+            assert_eq!(info.records, 1);
+
+            // invalid row stops the load, but the connection is still usable
+            let err = loader.load_rows(vec![vec![Value::from(101)]]).unwrap_err();
+            assert!(
+                matches!(err, Error::DriverError(MismatchedStmtParams(3, 1))),
+                "{}",
+                err
+            );
+            drop(loader);
+
+            let rows: Vec<(u8, String, Option<Vec<u8>>)> = conn
+                .query("SELECT id, name, data FROM mysql.tbl ORDER BY id")
+                .unwrap();
+            assert_eq!(rows.len(), 101);
+            assert_eq!(
+                rows[42],
+                (42, "a\tb\\42\n".into(), Some(vec![0, b'\n', 42]))
+            );
+            assert_eq!(rows[100], (100, "named".into(), None));
+
+            BulkLoader::new(&mut conn, "mysql.tbl")
+                .load_rows(vec![(
+                    200,
+                    Value::NULL,
+                    Value::NULL,
+                    Value::Date(2020, 1, 2, 3, 4, 5, 6),
+                )])
+                .unwrap();
+            let ts: Option<Value> = conn
+                .query_first("SELECT ts FROM mysql.tbl WHERE id = 200")
+                .unwrap();
+            assert_eq!(ts, Some(Value::Date(2020, 1, 2, 3, 4, 5, 6)));
+        }
+
+        #[test]
+        fn should_drain_unexpected_result_set_of_local_infile_query() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            let err = conn
+                .query_local_infile("SELECT 1, 2", |_, _| Ok(()))
+                .unwrap_err();
+            assert!(matches!(err, DriverError(UnexpectedPacket)), "{}", err);
+            assert!(!conn.is_broken());
+            assert_eq!(conn.query_first::<u8, _>("SELECT 3").unwrap(), Some(3));
+        }
+
+        #[test]
+        fn should_bulk_load_integers_into_bit_columns() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(id INT, flags BIT(8))")
+                .unwrap();
+
+            let rows = vec![(1, Value::Int(5)), (2, Value::from(vec![0xa0_u8]))];
+            match BulkLoader::new(&mut conn, "mysql.tbl").load_rows(rows) {
+                Ok(info) => assert_eq!(info.records, 2),
+                Err(ref err) if format!("{}", err).find("not allowed").is_some() => {
+                    return;
+                }
+                Err(err) => panic!("ERROR {}", err),
+            }
+
+            let flags: Vec<u8> = conn
+                .query("SELECT flags + 0 FROM mysql.tbl ORDER BY id")
+                .unwrap();
+            assert_eq!(flags, vec![5, 0xa0]);
+        }
+
         #[test]
         fn should_reset_connection() {
             let mut conn = Conn::new(get_opts()).unwrap();
@@ -2270,6 +3578,363 @@ mod test {
             assert_eq!(status.1, 3);
         }
 
+        #[test]
+        fn should_describe_query_without_caching_statement() {
+            let mut conn = Conn::new(get_opts()).unwrap();
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl (id INT UNSIGNED NOT NULL PRIMARY KEY, name TEXT)")
+                .unwrap();
+
+            let describe = conn
+                .describe("SELECT id, name FROM mysql.tbl WHERE id = :id OR name = :name")
+                .unwrap();
+            assert_eq!(describe.num_params(), 2);
+            assert_eq!(
+                describe.named_params(),
+                Some(&[b"id".to_vec(), b"name".to_vec()][..])
+            );
+            assert_eq!(describe.num_columns(), 2);
+            assert_eq!(describe.columns()[0].name_str(), "id");
+            assert!(!describe.columns()[0].is_nullable());
+            assert!(describe.columns()[0].is_unsigned());
+            assert!(describe.columns()[1].is_nullable());
+            assert_eq!(conn.0.stmt_cache.iter().count(), 0);
+
+            let describe = conn.describe("DO ?").unwrap();
+            assert_eq!(describe.num_params(), 1);
+            assert_eq!(describe.named_params(), None);
+            assert!(describe.columns().is_empty());
+            assert_eq!(conn.0.stmt_cache.iter().count(), 0);
+        }
+
+        #[test]
+        fn should_reprepare_invalidated_statements() -> crate::Result<()> {
+            use mysql_common::packets::ComStmtClose;
+
+            let mut conn = Conn::new(get_opts())?;
+            let stmt = conn.prep("SELECT :foo, 2")?;
+
+            // the statement is gone on the server side (e.g. after a proxy switch)
+            conn.write_command_raw(&ComStmtClose::new(stmt.id()))?;
+
+            let row: Option<(u8, u8)> = conn.exec_first(&stmt, params! { "foo" => 1 })?;
+            assert_eq!(row, Some((1, 2)));
+            let new_stmt = conn.prep("SELECT :foo, 2")?;
+            assert_ne!(new_stmt.id(), stmt.id());
+
+            // the old statement handle keeps working
+            let row: Option<(u8, u8)> = conn.exec_first(&stmt, params! { "foo" => 3 })?;
+            assert_eq!(row, Some((3, 2)));
+            let row: Option<(u8, u8)> =
+                conn.exec_first("SELECT :foo, 2", params! { "foo" => 4 })?;
+            assert_eq!(row, Some((4, 2)));
+            assert_eq!(conn.0.stmt_cache.iter().count(), 1);
+
+            Ok(())
+        }
+
+        #[test]
+        fn should_reuse_columns_of_cached_statements() -> crate::Result<()> {
+            let opts = OptsBuilder::from_opts(get_opts()).optional_resultset_metadata(true);
+            let mut conn = Conn::new(opts)?;
+            let stmt = conn.prep("SELECT ? AS foo, 'bar' AS bar")?;
+
+            for i in 0..3_u8 {
+                let mut result = conn.exec_iter(&stmt, (i,))?;
+                let columns = result.columns();
+                let names = columns
+                    .as_ref()
+                    .iter()
+                    .map(|col| col.name_str().into_owned())
+                    .collect::<Vec<_>>();
+                assert_eq!(names, ["foo", "bar"]);
+                let row: (u8, String) = from_row(result.next().unwrap()?);
+                assert_eq!(row, (i, "bar".into()));
+                drop(result);
+
+                // text queries and statements without a result set are unaffected
+                let value: Option<u8> = conn.query_first("SELECT 42")?;
+                assert_eq!(value, Some(42));
+                conn.exec_drop("DO ?", (i,))?;
+            }
+
+            if conn
+                .has_capability(crate::consts::CapabilityFlags::CLIENT_OPTIONAL_RESULTSET_METADATA)
+            {
+                conn.exec_drop(&stmt, (1,))?;
+                assert!(conn.0.resultset_metadata_none);
+                conn.reset()?;
+                assert!(!conn.0.resultset_metadata_none);
+                let mode: Option<String> = conn.query_first("SELECT @@resultset_metadata")?;
+                assert_eq!(mode.as_deref(), Some("FULL"));
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn should_send_query_attributes() -> crate::Result<()> {
+            let mut conn = Conn::new(get_opts())?;
+            if !conn.has_capability(crate::consts::CapabilityFlags::CLIENT_QUERY_ATTRIBUTES) {
+                // attributes are ignored
+                conn.with_attributes(&[("foo", "bar")]).query_drop("DO 1")?;
+                return Ok(());
+            }
+            // the component might be already installed
+            let _ = conn.query_drop("INSTALL COMPONENT 'file://component_query_attributes'");
+
+            let value: Option<String> = conn
+                .with_attributes(&[("traceparent", "00-01"), ("foo", "bar")])
+                .query_first("SELECT mysql_query_attribute_string('traceparent')")?;
+            assert_eq!(value.as_deref(), Some("00-01"));
+
+            // attributes are only sent with the next query
+            let value: Option<Option<String>> =
+                conn.query_first("SELECT mysql_query_attribute_string('traceparent')")?;
+            assert_eq!(value, Some(None));
+
+            let row: Option<(u8, String)> = conn
+                .with_attributes(&[("foo", Value::from("baz"))])
+                .exec_first("SELECT ?, mysql_query_attribute_string('foo')", (7,))?;
+            assert_eq!(row, Some((7, "baz".into())));
+
+            let value: Option<String> = conn
+                .with_attributes(&[("foo", 42)])
+                .exec_first("SELECT mysql_query_attribute_string('foo')", ())?;
+            assert_eq!(value.as_deref(), Some("42"));
+
+            Ok(())
+        }
+
+        #[test]
+        fn should_collect_stats() -> crate::Result<()> {
+            let mut conn = Conn::new(get_opts())?;
+            let before = conn.stats();
+            assert!(before.bytes_read > 0 && before.bytes_written > 0);
+
+            conn.query_drop("SELECT 1 UNION ALL SELECT 2")?;
+            let stmt = conn.prep("SELECT ?")?;
+            conn.exec_drop(&stmt, (1,))?;
+            conn.prep("SELECT ?")?;
+
+            let stats = conn.stats();
+            assert_eq!(stats.commands_sent - before.commands_sent, 3);
+            assert_eq!(stats.round_trips - before.round_trips, 3);
+            assert_eq!(stats.rows_received - before.rows_received, 3);
+            assert_eq!(stats.prepares - before.prepares, 1);
+            assert_eq!(stats.prepare_cache_hits - before.prepare_cache_hits, 1);
+            assert!(stats.payload_bytes_written > before.payload_bytes_written);
+            assert!(stats.payload_bytes_read > before.payload_bytes_read);
+            assert!(stats.io_time > before.io_time);
+            if !crate::test_misc::test_compression() {
+                // every packet has a 4 bytes header
+                assert!(
+                    stats.bytes_written - before.bytes_written
+                        > stats.payload_bytes_written - before.payload_bytes_written
+                );
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn should_log_slow_queries() -> crate::Result<()> {
+            use std::sync::{Arc, Mutex};
+
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let log_clone = log.clone();
+            let opts = OptsBuilder::from_opts(get_opts()).slow_query_threshold(
+                Duration::from_millis(100),
+                move |query| {
+                    log_clone.lock().unwrap().push((
+                        query.query().to_owned(),
+                        query.params().clone(),
+                        query.warnings(),
+                    ));
+                },
+            );
+            let mut conn = Conn::new(opts)?;
+            log.lock().unwrap().clear();
+
+            conn.query_drop("DO 1")?;
+            conn.query_drop("SELECT SLEEP(0.2), 1 / 0")?;
+            conn.exec_drop("DO SLEEP(?)", (0.2,))?;
+            conn.query_drop("DO 2")?;
+
+            assert_eq!(
+                *log.lock().unwrap(),
+                [
+                    (
+                        "SELECT SLEEP(0.2), 1 / 0".to_owned(),
+                        crate::Params::Empty,
+                        1
+                    ),
+                    (
+                        "DO SLEEP(?)".to_owned(),
+                        crate::Params::Positional(vec![0.2.into()]),
+                        0
+                    ),
+                ]
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn should_fetch_warnings() -> crate::Result<()> {
+            use crate::{Warning, WarningLevel};
+
+            let truncated = Warning {
+                level: WarningLevel::Warning,
+                code: 1265,
+                message: "Data truncated for column 'a' at row 1".into(),
+            };
+
+            let mut conn = Conn::new(get_opts())?;
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(2))")?;
+            conn.query_drop("INSERT IGNORE INTO mysql.tbl VALUES ('foo')")?;
+            assert!(conn.fetched_warnings().is_empty());
+            assert_eq!(conn.fetch_warnings()?, std::slice::from_ref(&truncated));
+            assert_eq!(conn.warnings(), 1);
+            assert_eq!(conn.affected_rows(), 1);
+
+            let mut conn = Conn::new(OptsBuilder::from_opts(get_opts()).fetch_warnings(true))?;
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(2))")?;
+            conn.exec_drop("INSERT IGNORE INTO mysql.tbl VALUES (?)", ("foo",))?;
+            assert_eq!(conn.fetched_warnings(), std::slice::from_ref(&truncated));
+            assert_eq!(conn.affected_rows(), 1);
+            let mut result = conn.query_iter("SELECT a FROM mysql.tbl WHERE a = 1")?;
+            assert!(result.by_ref().all(|row| row.is_ok()));
+            assert!(!result.fetched_warnings().is_empty());
+            drop(result);
+            conn.query_drop("DO 1")?;
+            assert!(conn.fetched_warnings().is_empty());
+
+            let mut conn = Conn::new(OptsBuilder::from_opts(get_opts()).warnings_as_errors(true))?;
+            conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a VARCHAR(2))")?;
+            let err = conn
+                .query_drop("INSERT IGNORE INTO mysql.tbl VALUES ('foo')")
+                .unwrap_err();
+            match err {
+                DriverError(crate::DriverError::Warnings(warnings)) => {
+                    assert_eq!(warnings, [truncated])
+                }
+                err => panic!("unexpected error: {}", err),
+            }
+            // notes are not turned into errors
+            conn.query_drop("DROP TEMPORARY TABLE IF EXISTS mysql.missing")?;
+            assert_eq!(conn.fetched_warnings()[0].level, WarningLevel::Note);
+            conn.ping()?;
+            Ok(())
+        }
+
+        #[test]
+        fn should_intercept_statements() -> crate::Result<()> {
+            use std::{
+                borrow::Cow,
+                sync::{Arc, Mutex},
+            };
+
+            use crate::{Interceptor, QueryContext, QueryKind, QueryOutcome};
+
+            type Log = Arc<Mutex<Vec<(QueryKind, String, bool, Option<QueryOutcome>)>>>;
+
+            struct Recorder(Log);
+
+            impl Interceptor for Recorder {
+                fn before_query(
+                    &self,
+                    ctx: &mut QueryContext,
+                    query: &mut Cow<'_, str>,
+                ) -> crate::Result<()> {
+                    if query.contains("forbidden") {
+                        return Err(crate::DriverError::QueryRejected("forbidden".into()).into());
+                    }
+                    self.0
+                        .lock()
+                        .unwrap()
+                        .push((ctx.kind(), query.to_string(), false, None));
+                    if ctx.kind() != QueryKind::Execute {
+                        query.to_mut().push_str(" /*tagged*/");
+                    }
+                    Ok(())
+                }
+
+                fn after_query(
+                    &self,
+                    ctx: &QueryContext,
+                    result: &crate::Result<QueryOutcome>,
+                    _: Duration,
+                ) {
+                    let outcome = result.as_ref().ok().copied();
+                    self.0
+                        .lock()
+                        .unwrap()
+                        .push((ctx.kind(), String::new(), true, outcome));
+                }
+            }
+
+            let log = Log::default();
+            let opts = OptsBuilder::from_opts(get_opts()).interceptor(Recorder(log.clone()));
+            let mut conn = Conn::new(opts)?;
+            log.lock().unwrap().clear();
+
+            let stmt = conn.prep("SELECT ?")?;
+            let value: Option<u8> = conn.exec_first(&stmt, (1,))?;
+            assert_eq!(value, Some(1));
+            conn.query_drop("DO 1")?;
+            let err = conn.query_drop("SELECT 'forbidden'").unwrap_err();
+            assert!(matches!(
+                err,
+                DriverError(crate::DriverError::QueryRejected(_))
+            ));
+
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            tx.exec_drop("DO ?", (1,))?;
+            tx.commit()?;
+
+            // statements are cached by the query before it was rewritten
+            assert_eq!(conn.prep("SELECT ?")?.id(), stmt.id());
+
+            let log = log.lock().unwrap();
+            let before = log
+                .iter()
+                .filter(|(_, _, after, _)| !after)
+                .map(|(kind, query, _, _)| (*kind, query.as_str()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                before,
+                [
+                    (QueryKind::Prepare, "SELECT ?"),
+                    (QueryKind::Execute, "SELECT ? /*tagged*/"),
+                    (QueryKind::Query, "DO 1"),
+                    (QueryKind::Query, "START TRANSACTION"),
+                    (QueryKind::Prepare, "DO ?"),
+                    (QueryKind::Execute, "DO ? /*tagged*/"),
+                    (QueryKind::Query, "COMMIT"),
+                    (QueryKind::Prepare, "SELECT ?"),
+                ]
+            );
+            let outcomes = log
+                .iter()
+                .filter(|(_, _, after, _)| *after)
+                .map(|(_, _, _, outcome)| outcome.map(|x| x.has_result_set))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                outcomes,
+                [
+                    Some(false),
+                    Some(true),
+                    Some(false),
+                    None,
+                    Some(false),
+                    Some(false),
+                    Some(false),
+                    Some(false),
+                    Some(false),
+                ]
+            );
+
+            Ok(())
+        }
+
         #[test]
         fn should_hold_stmt_cache_size_bounds() {
             let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(3);
@@ -2299,6 +3964,15 @@ mod test {
                 .collect::<Vec<&[u8]>>();
             order.sort();
             assert_eq!(order, &[b"DO 3", b"DO 5", b"DO 6"]);
+
+            assert_eq!(
+                conn.stmt_cache_stats(),
+                crate::StmtCacheStats {
+                    hits: 2,
+                    misses: 6,
+                    evictions: 3,
+                }
+            );
         }
 
         #[test]
@@ -2576,6 +4250,187 @@ mod test {
 
             Ok(())
         }
+
+        #[test]
+        #[cfg(feature = "binlog")]
+        fn should_track_position_and_resume_binlog_stream() -> crate::Result<()> {
+            use mysql_common::binlog::consts::EventType;
+
+            use crate::{BinlogDumpFlags, BinlogStreamOpts};
+
+            let mut conn = Conn::new(get_opts())?;
+            let row: crate::Row = "SHOW BINARY LOGS".first(&mut conn)?.unwrap();
+            let filename: Vec<u8> = row.get(0).unwrap();
+            let pos: u64 = row.get(1).unwrap();
+
+            conn.query_drop("CREATE TABLE IF NOT EXISTS binlog_resume (id INT)")?;
+            for i in 0..3 {
+                conn.exec_drop("INSERT INTO binlog_resume VALUES (?)", (i,))?;
+            }
+            conn.query_drop("DROP TABLE binlog_resume")?;
+
+            let request = BinlogRequest::new(15)
+                .with_filename(filename.clone())
+                .with_pos(pos)
+                .with_flags(BinlogDumpFlags::BINLOG_DUMP_NON_BLOCK);
+            let stream_opts =
+                BinlogStreamOpts::new().with_heartbeat_period(Some(Duration::from_secs(1)));
+
+            // read the whole stream
+            let mut stream = Conn::new(get_opts())?
+                .get_binlog_stream_with_opts(request.clone(), stream_opts.clone())?;
+            let mut xids = 0;
+            for event in &mut stream {
+                if event?.header().event_type() == Ok(EventType::XID_EVENT) {
+                    xids += 1;
+                }
+            }
+            assert!(xids >= 3);
+            assert!(stream.position() > pos || stream.filename() != &filename[..]);
+            let (end_filename, end_position) = (stream.filename().to_vec(), stream.position());
+
+            // stop in the middle of the second transaction and resume
+            let mut stream =
+                Conn::new(get_opts())?.get_binlog_stream_with_opts(request, stream_opts)?;
+            let mut xids = 0;
+            let mut committed = 0;
+            while let Some(event) = stream.next() {
+                match event?.header().event_type() {
+                    Ok(EventType::XID_EVENT) => {
+                        xids += 1;
+                        committed = stream.position();
+                    }
+                    Ok(EventType::WRITE_ROWS_EVENT) if xids == 1 => break,
+                    _ => (),
+                }
+            }
+            assert_eq!(stream.position(), committed);
+
+            stream.resume()?;
+            assert!(!stream.is_terminated());
+            for event in &mut stream {
+                event?;
+            }
+            assert_eq!(stream.filename(), &end_filename[..]);
+            assert_eq!(stream.position(), end_position);
+
+            Ok(())
+        }
+
+        #[test]
+        #[cfg(feature = "binlog")]
+        fn should_write_and_read_binlog_files() -> crate::Result<()> {
+            use mysql_common::binlog::consts::EventType;
+
+            use crate::{BinlogDumpFlags, BinlogFile, BinlogWriter};
+
+            let mut conn = Conn::new(get_opts())?;
+            let row: crate::Row = "SHOW BINARY LOGS".first(&mut conn)?.unwrap();
+            let filename: String = row.get(0).unwrap();
+            conn.query_drop("CREATE TABLE IF NOT EXISTS binlog_archive (id INT)")?;
+            conn.query_drop("INSERT INTO binlog_archive VALUES (1)")?;
+            conn.query_drop("DROP TABLE binlog_archive")?;
+
+            let dir = std::env::temp_dir().join(format!("mysql-binlog-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir)?;
+
+            let request = BinlogRequest::new(17)
+                .with_filename(filename.as_bytes())
+                .with_flags(BinlogDumpFlags::BINLOG_DUMP_NON_BLOCK);
+            let mut stream = Conn::new(get_opts())?.get_binlog_stream(request.clone())?;
+            let mut writer = BinlogWriter::new(&dir);
+            writer.write_stream(&mut stream)?;
+            assert!(writer.current_file().is_some());
+
+            let live = Conn::new(get_opts())?
+                .get_binlog_stream(request)?
+                .take_while(|event| {
+                    // stop at the end of the first file
+                    !matches!(event, Ok(event) if event.header().log_pos() > 0
+                        && event.header().event_type() == Ok(EventType::ROTATE_EVENT))
+                })
+                .map(|event| event.map(|event| event.header()))
+                .collect::<crate::Result<Vec<_>>>()?;
+            let archived = BinlogFile::open(dir.join(&filename))?
+                .map(|event| event.map(|event| event.header()))
+                .collect::<crate::Result<Vec<_>>>()?;
+            let live = live
+                .into_iter()
+                .filter(|header| header.log_pos() > 0)
+                .filter(|header| header.event_type() != Ok(EventType::ROTATE_EVENT))
+                .collect::<Vec<_>>();
+            let archived = archived
+                .into_iter()
+                .filter(|header| header.event_type() != Ok(EventType::ROTATE_EVENT))
+                .collect::<Vec<_>>();
+            assert_eq!(live, archived);
+
+            std::fs::remove_dir_all(dir)?;
+            Ok(())
+        }
+
+        #[test]
+        #[cfg(feature = "binlog")]
+        fn should_read_row_changes() -> crate::Result<()> {
+            use crate::{BinlogDumpFlags, RowChangeKind};
+
+            let mut conn = Conn::new(get_opts())?;
+            let row: crate::Row = "SHOW BINARY LOGS".first(&mut conn)?.unwrap();
+            let filename: Vec<u8> = row.get(0).unwrap();
+            let pos: u64 = row.get(1).unwrap();
+
+            conn.query_drop("CREATE TABLE IF NOT EXISTS binlog_changes (id INT, name TEXT)")?;
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            tx.exec_drop("INSERT INTO binlog_changes VALUES (?, ?)", (1, "foo"))?;
+            tx.exec_drop("INSERT INTO binlog_changes VALUES (?, ?)", (2, "bar"))?;
+            tx.commit()?;
+            conn.query_drop("UPDATE binlog_changes SET name = 'baz' WHERE id = 1")?;
+            conn.query_drop("DELETE FROM binlog_changes WHERE id = 2")?;
+            conn.query_drop("DROP TABLE binlog_changes")?;
+
+            let request = BinlogRequest::new(16)
+                .with_filename(filename)
+                .with_pos(pos)
+                .with_flags(BinlogDumpFlags::BINLOG_DUMP_NON_BLOCK);
+            let transactions = Conn::new(get_opts())?
+                .get_binlog_stream(request)?
+                .row_changes()
+                .collect::<crate::Result<Vec<_>>>()?;
+            let transactions = transactions
+                .into_iter()
+                .map(|tx| {
+                    tx.changes
+                        .into_iter()
+                        .filter(|change| change.table == "binlog_changes")
+                        .collect::<Vec<_>>()
+                })
+                .filter(|changes| !changes.is_empty())
+                .collect::<Vec<_>>();
+
+            assert_eq!(transactions.len(), 3);
+            assert_eq!(transactions[0].len(), 2);
+            assert_eq!(transactions[0][0].kind, RowChangeKind::Insert);
+            let after = transactions[0][1].after.as_ref().unwrap();
+            assert_eq!(after.get::<i32, _>("id"), Some(2));
+            assert_eq!(after.get::<String, _>("name"), Some("bar".into()));
+
+            let update = &transactions[1][0];
+            assert_eq!(update.kind, RowChangeKind::Update);
+            assert_eq!(
+                update.before.as_ref().unwrap().get::<String, _>("name"),
+                Some("foo".into())
+            );
+            assert_eq!(
+                update.after.as_ref().unwrap().get::<String, _>("name"),
+                Some("baz".into())
+            );
+
+            assert_eq!(transactions[2][0].kind, RowChangeKind::Delete);
+            assert!(transactions[2][0].after.is_none());
+
+            Ok(())
+        }
     }
 
     #[cfg(feature = "nightly")]
@@ -10,11 +10,15 @@ use percent_encoding::percent_decode;
 use url::Url;
 
 use std::{
-    borrow::Cow, collections::HashMap, fmt, hash::Hash, net::SocketAddr, path::Path, time::Duration,
+    borrow::Cow, collections::HashMap, fmt, hash::Hash, net::SocketAddr, path::Path, sync::Arc,
+    time::Duration,
 };
 
 use crate::{
-    consts::CapabilityFlags, Compression, LocalInfileHandler, PoolConstraints, PoolOpts, UrlError,
+    conn::{interceptor::Interceptors, stats::SlowQueryLog},
+    consts::CapabilityFlags,
+    Compression, Interceptor, LocalInfileHandler, PoolConstraints, PoolOpts, SlowQuery,
+    StatementSanitizer, UrlError,
 };
 
 /// Default value for client side per-connection statement cache.
@@ -232,6 +236,31 @@ pub(crate) struct InnerOpts {
     /// consider using TLS or encrypted tunnels for server connection.
     enable_cleartext_plugin: bool,
 
+    /// Whether to skip result set metadata for prepared statements (defaults to `false`).
+    ///
+    /// See [`OptsBuilder::optional_resultset_metadata`].
+    optional_resultset_metadata: bool,
+
+    /// Defines how statements are recorded into `tracing` spans
+    /// (defaults to [`StatementSanitizer::ReplaceLiterals`]).
+    statement_sanitizer: StatementSanitizer,
+
+    /// Chain of statement interceptors (see [`OptsBuilder::interceptor`]).
+    interceptors: Interceptors,
+
+    /// Client-side slow query log (see [`OptsBuilder::slow_query_threshold`]).
+    slow_query_log: Option<SlowQueryLog>,
+
+    /// Whether to fetch warnings of every statement that produced any (defaults to `false`).
+    ///
+    /// See [`OptsBuilder::fetch_warnings`].
+    fetch_warnings: bool,
+
+    /// Whether to turn warnings into errors (defaults to `false`).
+    ///
+    /// See [`OptsBuilder::warnings_as_errors`].
+    warnings_as_errors: bool,
+
     /// Client side `max_allowed_packet` value (defaults to `None`).
     ///
     /// By default `Conn` will query this value from the server. One can avoid this step
@@ -276,6 +305,12 @@ impl Default for InnerOpts {
             connect_attrs: Some(HashMap::new()),
             secure_auth: true,
             enable_cleartext_plugin: false,
+            optional_resultset_metadata: false,
+            statement_sanitizer: StatementSanitizer::default(),
+            interceptors: Interceptors::default(),
+            slow_query_log: None,
+            fetch_warnings: false,
+            warnings_as_errors: false,
             #[cfg(test)]
             injected_socket: None,
         }
@@ -541,6 +576,46 @@ impl Opts {
     pub fn get_enable_cleartext_plugin(&self) -> bool {
         self.0.enable_cleartext_plugin
     }
+
+    /// Returns `true` if result set metadata should be skipped for prepared statements
+    /// (see [`OptsBuilder::optional_resultset_metadata`]).
+    pub fn get_optional_resultset_metadata(&self) -> bool {
+        self.0.optional_resultset_metadata
+    }
+
+    /// Defines how statements are recorded into the `db.statement` field of `tracing` spans
+    /// (see [`OptsBuilder::statement_sanitizer`]).
+    pub fn get_statement_sanitizer(&self) -> &StatementSanitizer {
+        &self.0.statement_sanitizer
+    }
+
+    /// Returns statement interceptors in order of registration
+    /// (see [`OptsBuilder::interceptor`]).
+    pub fn get_interceptors(&self) -> &[Arc<dyn Interceptor>] {
+        &self.0.interceptors.0
+    }
+
+    /// Returns the threshold of the client-side slow query log, if any
+    /// (see [`OptsBuilder::slow_query_threshold`]).
+    pub fn get_slow_query_threshold(&self) -> Option<Duration> {
+        self.0.slow_query_log.as_ref().map(|log| log.threshold)
+    }
+
+    pub(crate) fn slow_query_log(&self) -> Option<&SlowQueryLog> {
+        self.0.slow_query_log.as_ref()
+    }
+
+    /// Returns `true` if warnings are fetched automatically
+    /// (see [`OptsBuilder::fetch_warnings`]).
+    pub fn get_fetch_warnings(&self) -> bool {
+        self.0.fetch_warnings || self.0.warnings_as_errors
+    }
+
+    /// Returns `true` if warnings are turned into errors
+    /// (see [`OptsBuilder::warnings_as_errors`]).
+    pub fn get_warnings_as_errors(&self) -> bool {
+        self.0.warnings_as_errors
+    }
 }
 
 /// Provides a way to build [`Opts`](struct.Opts.html).
@@ -661,6 +736,32 @@ impl OptsBuilder {
                         return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
                     }
                 },
+                "optional_resultset_metadata" => match value.parse::<bool>() {
+                    Ok(parsed) => self.opts.0.optional_resultset_metadata = parsed,
+                    Err(_) => {
+                        return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
+                    }
+                },
+                "fetch_warnings" => match value.parse::<bool>() {
+                    Ok(parsed) => self.opts.0.fetch_warnings = parsed,
+                    Err(_) => {
+                        return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
+                    }
+                },
+                "warnings_as_errors" => match value.parse::<bool>() {
+                    Ok(parsed) => self.opts.0.warnings_as_errors = parsed,
+                    Err(_) => {
+                        return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
+                    }
+                },
+                "statement_sanitizer" => match value.as_str() {
+                    "verbatim" => self.opts.0.statement_sanitizer = StatementSanitizer::Verbatim,
+                    "replace_literals" => {
+                        self.opts.0.statement_sanitizer = StatementSanitizer::ReplaceLiterals
+                    }
+                    "omit" => self.opts.0.statement_sanitizer = StatementSanitizer::Omit,
+                    _ => return Err(UrlError::InvalidValue(key.to_string(), value.to_string())),
+                },
                 "secure_auth" => match value.parse::<bool>() {
                     Ok(parsed) => self.opts.0.secure_auth = parsed,
                     Err(_) => {
@@ -744,6 +845,15 @@ impl OptsBuilder {
                         return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
                     }
                 },
+                "restore_session_state" => match value.parse::<bool>() {
+                    Ok(parsed) => {
+                        self.opts.0.pool_opts =
+                            self.opts.0.pool_opts.with_restore_session_state(parsed)
+                    }
+                    Err(_) => {
+                        return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
+                    }
+                },
                 "check_health" => match value.parse::<bool>() {
                     Ok(parsed) => {
                         self.opts.0.pool_opts = self.opts.0.pool_opts.with_check_health(parsed)
@@ -752,6 +862,15 @@ impl OptsBuilder {
                         return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
                     }
                 },
+                "shared_stmt_cache_size" => match value.parse::<usize>() {
+                    Ok(parsed) => {
+                        self.opts.0.pool_opts =
+                            self.opts.0.pool_opts.with_shared_stmt_cache_size(parsed)
+                    }
+                    Err(_) => {
+                        return Err(UrlError::InvalidValue(key.to_string(), value.to_string()))
+                    }
+                },
                 "max_allowed_packet" => match value.parse::<usize>() {
                     Ok(parsed) => self.opts.0.max_allowed_packet = Some(parsed),
                     Err(_) => {
@@ -1114,6 +1233,158 @@ impl OptsBuilder {
         self.opts.0.enable_cleartext_plugin = enable_cleartext_plugin;
         self
     }
+
+    /// Enables skipping of result set metadata for prepared statements (defaults to `false`).
+    ///
+    /// Result set metadata (i.e. column definitions) is sent by the server upon every statement
+    /// execution, which is wasteful for wide and frequently executed queries, because
+    /// the same metadata is already known from `COM_STMT_PREPARE`.
+    ///
+    /// # How it works
+    ///
+    /// *   MariaDB – `MARIADB_CLIENT_CACHE_METADATA` capability is negotiated, so that the server
+    ///     won't send result set metadata for a prepared statement unless it has changed.
+    /// *   MySql 8 – `CLIENT_OPTIONAL_RESULTSET_METADATA` capability is negotiated and the
+    ///     `resultset_metadata` session variable is set to `NONE` before executing a statement
+    ///     with known columns (and back to `FULL` before a text query or a prepare).
+    ///
+    ///     Note that every switch of the variable costs an additional roundtrip, i.e. a workload
+    ///     that alternates statement executions with text queries (or prepares) pays one
+    ///     extra `SET` per switch and may get slower. This option only pays off
+    ///     if cached statements are executed in long runs, without text queries in between.
+    ///
+    /// Metadata of a result set is then taken from the prepared statement.
+    /// Do not change the `resultset_metadata` variable manually if this option is enabled.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `optional_resultset_metadata` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?optional_resultset_metadata=true")?;
+    /// assert!(opts.get_optional_resultset_metadata());
+    /// # Ok(()) }
+    /// ```
+    pub fn optional_resultset_metadata(mut self, optional_resultset_metadata: bool) -> Self {
+        self.opts.0.optional_resultset_metadata = optional_resultset_metadata;
+        self
+    }
+
+    /// Defines how statements are recorded into the `db.statement` field of `tracing` spans
+    /// (defaults to [`StatementSanitizer::ReplaceLiterals`]).
+    ///
+    /// Only matters if the `tracing` feature is enabled. Note that the default sanitizer
+    /// keeps statements of the binary protocol intact, because those carry
+    /// parameter placeholders instead of values.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `statement_sanitizer` URL parameter to set this value
+    /// (either `verbatim`, `replace_literals` or `omit`). E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?statement_sanitizer=omit")?;
+    /// assert_eq!(opts.get_statement_sanitizer(), &StatementSanitizer::Omit);
+    /// # Ok(()) }
+    /// ```
+    pub fn statement_sanitizer(mut self, statement_sanitizer: StatementSanitizer) -> Self {
+        self.opts.0.statement_sanitizer = statement_sanitizer;
+        self
+    }
+
+    /// Registers a statement interceptor (see [`Interceptor`]).
+    ///
+    /// Interceptors form a chain: [`Interceptor::before_query`] is called in order
+    /// of registration and [`Interceptor::after_query`] is called in reverse order.
+    pub fn interceptor<T: Interceptor + 'static>(mut self, interceptor: T) -> Self {
+        self.opts.0.interceptors.0.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Fetches warnings of every statement that produced any (defaults to `false`).
+    ///
+    /// If the OK packet (or the result set terminator) of a statement reports a non-zero
+    /// warnings count, then `SHOW WARNINGS` is executed right after the whole response is read.
+    /// Fetched warnings are available via [`crate::Conn::fetched_warnings`] till the next
+    /// statement. Note that for a multi-result set only warnings of the last statement
+    /// are fetched.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `fetch_warnings` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?fetch_warnings=true")?;
+    /// assert!(opts.get_fetch_warnings());
+    /// # Ok(()) }
+    /// ```
+    pub fn fetch_warnings(mut self, fetch_warnings: bool) -> Self {
+        self.opts.0.fetch_warnings = fetch_warnings;
+        self
+    }
+
+    /// Turns warnings of a statement into [`crate::DriverError::Warnings`] error
+    /// (defaults to `false`).
+    ///
+    /// This is a strict mode of [`OptsBuilder::fetch_warnings`] (thus it implies it).
+    /// Warnings of the `Note` level are never turned into an error. If the statement
+    /// has produced a result set, then the error is returned after the last row.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `warnings_as_errors` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?warnings_as_errors=true")?;
+    /// assert!(opts.get_warnings_as_errors());
+    /// assert!(opts.get_fetch_warnings());
+    /// # Ok(()) }
+    /// ```
+    pub fn warnings_as_errors(mut self, warnings_as_errors: bool) -> Self {
+        self.opts.0.warnings_as_errors = warnings_as_errors;
+        self
+    }
+
+    /// Enables the client-side slow query log (defaults to disabled).
+    ///
+    /// `handler` is called for every query or statement execution, that took at least
+    /// `threshold` to complete. The time is measured from sending the query till reading
+    /// the whole response (i.e. it includes the time spent reading result set rows).
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # use std::time::Duration;
+    /// let opts = OptsBuilder::new().slow_query_threshold(Duration::from_millis(500), |query| {
+    ///     eprintln!(
+    ///         "slow query ({:?}, {} warnings): {}",
+    ///         query.elapsed(),
+    ///         query.warnings(),
+    ///         query.query(),
+    ///     );
+    /// });
+    /// assert_eq!(
+    ///     Opts::from(opts).get_slow_query_threshold(),
+    ///     Some(Duration::from_millis(500)),
+    /// );
+    /// ```
+    pub fn slow_query_threshold<F>(mut self, threshold: Duration, handler: F) -> Self
+    where
+        F: Fn(&SlowQuery<'_>) + Send + Sync + 'static,
+    {
+        self.opts.0.slow_query_log = Some(SlowQueryLog {
+            threshold,
+            handler: Arc::new(handler),
+        });
+        self
+    }
 }
 
 impl From<OptsBuilder> for Opts {
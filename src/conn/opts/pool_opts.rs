@@ -13,6 +13,10 @@ macro_rules! const_assert {
     };
 }
 
+/// Default size of the pool-wide statement metadata cache
+/// (see [`PoolOpts::with_shared_stmt_cache_size`]).
+pub const DEFAULT_SHARED_STMT_CACHE_SIZE: usize = 0;
+
 /// Connection pool options.
 ///
 /// ```
@@ -26,7 +30,9 @@ macro_rules! const_assert {
 pub struct PoolOpts {
     constraints: PoolConstraints,
     reset_connection: bool,
+    restore_session_state: bool,
     check_health: bool,
+    shared_stmt_cache_size: usize,
 }
 
 impl PoolOpts {
@@ -65,6 +71,8 @@ impl PoolOpts {
     ///
     /// So to increase overall performance you can safely opt-out of the default behavior
     /// if you are not willing to change the session state in an unpleasant way.
+    /// Note that an open transaction is rolled back upon return to a pool regardless
+    /// of this setting (see [`crate::Conn::in_transaction`]).
     ///
     /// It is also possible to selectively opt-in/out using [`crate::PooledConn::reset_connection`].
     ///
@@ -90,6 +98,44 @@ impl PoolOpts {
         self.reset_connection
     }
 
+    /// Sets whether to restore the session state upon returning a connection to a pool
+    /// (defaults to `false`). Only makes sense if `reset_connection` is `false`.
+    ///
+    /// This is a middle ground between the two [`PoolOpts::with_reset_connection`] modes:
+    /// session state changes are tracked using the `CLIENT_SESSION_TRACK` capability, so that
+    /// changed system variables (e.g. `time_zone` or `sql_mode`) and the default schema
+    /// are selectively restored, and prepared statements are kept alive. Variables are restored
+    /// to values taken by `SHOW SESSION VARIABLES` right after the connection setup
+    /// (i.e. after `init` commands).
+    ///
+    /// Changes that couldn't be restored selectively (user variables, temporary tables, etc.)
+    /// will lead to a connection reset. Note that such changes are only detected for text queries,
+    /// and that the reset might happen for no reason on servers that report
+    /// statement preparation as a state change.
+    ///
+    /// The connection will be reset as well if the server doesn't support session tracking.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `restore_session_state` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?reset_connection=false&restore_session_state=true")?;
+    /// assert_eq!(opts.get_pool_opts().restore_session_state(), true);
+    /// # Ok(()) }
+    /// ```
+    pub fn with_restore_session_state(mut self, restore_session_state: bool) -> Self {
+        self.restore_session_state = restore_session_state;
+        self
+    }
+
+    /// Returns the `restore_session_state` value (see [`PoolOpts::with_restore_session_state`]).
+    pub fn restore_session_state(&self) -> bool {
+        self.restore_session_state
+    }
+
     /// Sets whether to check connection health upon retrieving it from a pool (defaults to `true`).
     ///
     /// If `true`, then `Conn::ping` will be invoked on a non-fresh pooled connection.
@@ -114,6 +160,43 @@ impl PoolOpts {
     pub fn check_health(&self) -> bool {
         self.check_health
     }
+
+    /// Sets the size of the pool-wide statement metadata cache
+    /// (defaults to [`DEFAULT_SHARED_STMT_CACHE_SIZE`], `0` disables the cache).
+    ///
+    /// Prepared statements are cached per connection (see [`crate::OptsBuilder::stmt_cache_size`]),
+    /// so every connection of a pool has to prepare a hot query on its own. This cache keeps
+    /// parsed metadata of prepared queries (named parameters, parameter and column definitions),
+    /// so that a connection, that haven't prepared a query yet, only sends `COM_STMT_PREPARE`
+    /// and reuses the metadata. Metadata is cached per default schema and is only reused
+    /// if column definitions sent by the server are byte-for-byte equal to the cached ones.
+    /// Metadata of a statement gets evicted once the server asks to re-prepare it.
+    ///
+    /// The cache requires the server to report default schema changes (`CLIENT_SESSION_TRACK`,
+    /// i.e. MySql 5.7 or MariaDb 10.2 and above), otherwise it is not used. Note that
+    /// [`crate::Conn::change_user`] turns the cache off for the connection.
+    ///
+    /// # Connection URL
+    ///
+    /// Use `shared_stmt_cache_size` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?shared_stmt_cache_size=512")?;
+    /// assert_eq!(opts.get_pool_opts().shared_stmt_cache_size(), 512);
+    /// # Ok(()) }
+    /// ```
+    pub fn with_shared_stmt_cache_size(mut self, shared_stmt_cache_size: usize) -> Self {
+        self.shared_stmt_cache_size = shared_stmt_cache_size;
+        self
+    }
+
+    /// Returns the `shared_stmt_cache_size` value
+    /// (see [`PoolOpts::with_shared_stmt_cache_size`]).
+    pub fn shared_stmt_cache_size(&self) -> usize {
+        self.shared_stmt_cache_size
+    }
 }
 
 impl Default for PoolOpts {
@@ -121,7 +204,9 @@ impl Default for PoolOpts {
         Self {
             constraints: PoolConstraints::DEFAULT,
             reset_connection: true,
+            restore_session_state: false,
             check_health: true,
+            shared_stmt_cache_size: DEFAULT_SHARED_STMT_CACHE_SIZE,
         }
     }
 }
@@ -1,26 +1,32 @@
 use std::{
     collections::VecDeque,
+    num::NonZeroUsize,
     sync::{
         atomic::{AtomicUsize, Ordering},
-        Condvar, Mutex,
+        Arc, Condvar, Mutex,
     },
 };
 
-use crate::{Conn, Opts, PoolOpts};
+use crate::{conn::stmt_cache::SharedStmtCache, Conn, Opts, PoolOpts};
 
 #[derive(Debug)]
 pub struct Protected {
     opts: Opts,
     connections: VecDeque<Conn>,
+    shared_stmt_cache: Option<Arc<SharedStmtCache>>,
 }
 
 impl Protected {
-    fn new(opts: Opts) -> crate::Result<Protected> {
+    fn new(
+        opts: Opts,
+        shared_stmt_cache: Option<Arc<SharedStmtCache>>,
+    ) -> crate::Result<Protected> {
         let constraints = opts.get_pool_opts().constraints();
 
         let mut this = Protected {
             connections: VecDeque::with_capacity(constraints.max()),
             opts,
+            shared_stmt_cache,
         };
 
         for _ in 0..constraints.min() {
@@ -32,7 +38,8 @@ impl Protected {
 
     pub fn new_conn(&mut self) -> crate::Result<()> {
         match Conn::new(self.opts.clone()) {
-            Ok(conn) => {
+            Ok(mut conn) => {
+                conn.set_shared_stmt_cache(self.shared_stmt_cache.clone());
                 self.connections.push_back(conn);
                 Ok(())
             }
@@ -64,6 +71,7 @@ pub struct Inner {
     protected: (Mutex<Protected>, Condvar),
     pool_opts: PoolOpts,
     count: AtomicUsize,
+    shared_stmt_cache: Option<Arc<SharedStmtCache>>,
 }
 
 impl Inner {
@@ -99,11 +107,21 @@ impl Inner {
         &self.protected
     }
 
+    pub fn shared_stmt_cache(&self) -> Option<&SharedStmtCache> {
+        self.shared_stmt_cache.as_deref()
+    }
+
     pub fn new(opts: Opts) -> crate::Result<Self> {
+        let shared_stmt_cache = NonZeroUsize::new(opts.get_pool_opts().shared_stmt_cache_size())
+            .map(|cap| Arc::new(SharedStmtCache::new(cap)));
         Ok(Self {
             count: AtomicUsize::new(opts.get_pool_opts().constraints().min()),
             pool_opts: opts.get_pool_opts().clone(),
-            protected: (Mutex::new(Protected::new(opts)?), Condvar::new()),
+            protected: (
+                Mutex::new(Protected::new(opts, shared_stmt_cache.clone())?),
+                Condvar::new(),
+            ),
+            shared_stmt_cache,
         })
     }
 }
@@ -10,17 +10,24 @@ use std::{
     fmt,
     ops::Deref,
     sync::Arc,
+    thread,
     time::{Duration, Instant},
 };
 
 use crate::{
-    conn::query_result::{Binary, Text},
+    conn::{
+        instrument,
+        pool::retry::{Retried, RetryPolicy},
+        query_result::{Binary, OwnedQueryResult, Text},
+    },
     prelude::*,
-    ChangeUserOpts, Conn, DriverError, LocalInfileHandler, Opts, Params, QueryResult, Result,
-    Statement, Transaction, TxOpts,
+    ChangeUserOpts, Conn, Describe, DriverError, LoadDataFormat, LoadDataInfo, LocalInfileHandler,
+    Opts, Params, QueryResult, Result, Statement, StmtCacheStats, Transaction, TxOpts,
+    XaTransaction, Xid,
 };
 
 mod inner;
+pub mod retry;
 
 /// Thread-safe cloneable smart pointer to a connection pool.
 ///
@@ -72,9 +79,30 @@ impl Pool {
         &self,
         stmt: Option<T>,
         timeout: Option<Duration>,
-        mut call_ping: bool,
+        call_ping: bool,
     ) -> Result<PooledConn> {
+        let span = instrument::get_conn_span();
+        instrument::in_span(span, || {
+            let start = Instant::now();
+            let (conn, created) = self.take_conn(stmt, timeout, call_ping)?;
+            instrument::record_get_conn(&conn, start.elapsed(), created);
+            Ok(PooledConn {
+                pool: self.clone(),
+                conn: Some(conn),
+            })
+        })
+    }
+
+    /// Takes an idle connection or creates a new one
+    /// (returns `true` alongside the connection if it was created).
+    fn take_conn<T: AsRef<[u8]>>(
+        &self,
+        stmt: Option<T>,
+        timeout: Option<Duration>,
+        mut call_ping: bool,
+    ) -> Result<(Conn, bool)> {
         let times = timeout.map(|timeout| (Instant::now(), timeout));
+        let mut created = false;
 
         let (protected, condvar) = self.inner.protected();
 
@@ -109,6 +137,7 @@ impl Pool {
                 } else {
                     protected.new_conn()?;
                     self.inner.increase();
+                    created = true;
                     // we do not have to call ping for a fresh connection
                     call_ping = false;
                 }
@@ -118,13 +147,10 @@ impl Pool {
         if call_ping && self.inner.opts().check_health() && conn.ping().is_err() {
             // existing connection seem to be dead, retrying..
             self.inner.decrease();
-            return self._get_conn(stmt, timeout, call_ping);
+            return self.take_conn(stmt, timeout, call_ping);
         }
 
-        Ok(PooledConn {
-            pool: self.clone(),
-            conn: Some(conn),
-        })
+        Ok((conn, created))
     }
 
     /// Creates new pool with the given options (see [`Opts`]).
@@ -138,6 +164,17 @@ impl Pool {
         })
     }
 
+    /// Returns counters of the pool-wide statement metadata cache
+    /// (see [`crate::PoolOpts::with_shared_stmt_cache_size`]).
+    ///
+    /// Use [`Conn::stmt_cache_stats`] to get counters of a connection's statement cache.
+    pub fn stmt_cache_stats(&self) -> StmtCacheStats {
+        self.inner
+            .shared_stmt_cache()
+            .map(|cache| cache.stats())
+            .unwrap_or_default()
+    }
+
     /// Gives you a [`PooledConn`](struct.PooledConn.html).
     pub fn get_conn(&self) -> Result<PooledConn> {
         self._get_conn(None::<String>, None, true)
@@ -165,6 +202,72 @@ impl Pool {
             Err(e) => Err(e),
         }
     }
+
+    /// Runs the given closure within a transaction and commits it,
+    /// retrying on deadlocks and lock wait timeouts (see [`RetryPolicy`]).
+    ///
+    /// Every failed attempt is rolled back and followed by a backoff. The closure may be
+    /// called several times, so it shouldn't have side effects outside of the transaction.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// let pool = Pool::new(get_opts())?;
+    ///
+    /// let retried = pool.transaction_with_retry(TxOpts::default(), RetryPolicy::default(), |tx| {
+    ///     tx.query_first::<u8, _>("SELECT 1")
+    /// })?;
+    ///
+    /// assert_eq!(retried.value, Some(1));
+    /// assert_eq!(retried.attempts, 1);
+    /// # });
+    /// ```
+    pub fn transaction_with_retry<T, F>(
+        &self,
+        tx_opts: TxOpts,
+        policy: RetryPolicy,
+        mut f: F,
+    ) -> Result<Retried<T>>
+    where
+        F: FnMut(&mut Transaction<'static>) -> Result<T>,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let can_retry = attempts < policy.max_attempts();
+
+            let mut tx = self.start_transaction(tx_opts)?;
+            let value = match f(&mut tx) {
+                Ok(value) => value,
+                Err(err) => {
+                    // rollback errors are irrelevant here
+                    let _ = tx.rollback();
+                    if can_retry && policy.is_retryable(&err) {
+                        thread::sleep(policy.backoff(attempts));
+                        continue;
+                    }
+                    return Err(err);
+                }
+            };
+
+            match tx.commit() {
+                Ok(()) => return Ok(Retried { value, attempts }),
+                Err(err) if can_retry && policy.is_retryable(&err) => (),
+                Err(err) if err.is_connectivity_error() => match policy.commit_check() {
+                    Some(check) if can_retry => {
+                        if check(&mut self.get_conn()?)? {
+                            return Ok(Retried { value, attempts });
+                        }
+                    }
+                    _ => return Err(err),
+                },
+                Err(err) => return Err(err),
+            }
+
+            thread::sleep(policy.backoff(attempts));
+        }
+    }
 }
 
 impl fmt::Debug for Pool {
@@ -227,24 +330,26 @@ impl Deref for PooledConn {
 impl Drop for PooledConn {
     fn drop(&mut self) {
         if let Some(mut conn) = self.conn.take() {
+            if conn.is_broken() {
+                // the connection might be desynchronized, so it must not reach the pool
+                self.discard();
+                return;
+            }
             match conn.cleanup_for_pool() {
                 Ok(_) => {
                     let (protected, condvar) = self.pool.inner.protected();
-                    match protected.lock() {
-                        Ok(mut protected) => {
-                            protected.push_back(conn);
-                            drop(protected);
-                            condvar.notify_one();
-                        }
-                        Err(_) => {
-                            // everything is broken
-                            self.pool.inner.decrease();
-                        }
+                    if let Ok(mut protected) = protected.lock() {
+                        protected.push_back(conn);
+                        // the lock is held, so that a waiting thread can't miss the notification
+                        condvar.notify_one();
+                        return;
                     }
+                    // everything is broken, but the slot is still freed
+                    self.discard();
                 }
                 Err(_) => {
                     // the connection is broken
-                    self.pool.inner.decrease();
+                    self.discard();
                 }
             }
         }
@@ -252,12 +357,47 @@ impl Drop for PooledConn {
 }
 
 impl PooledConn {
+    /// Frees the pool slot of a connection, that won't return to the pool,
+    /// and wakes up a thread waiting for a connection.
+    fn discard(&self) {
+        let (protected, condvar) = self.pool.inner.protected();
+        // the lock is held, so that a waiting thread can't miss the notification
+        let _protected = protected.lock();
+        self.pool.inner.decrease();
+        condvar.notify_one();
+    }
+
     /// Redirects to
     /// [`Conn#start_transaction`](struct.Conn.html#method.start_transaction)
     pub fn start_transaction(&mut self, tx_opts: TxOpts) -> Result<Transaction> {
         self.conn.as_mut().unwrap().start_transaction(tx_opts)
     }
 
+    /// Redirects to [`Conn::start_xa_transaction`].
+    pub fn start_xa_transaction(&mut self, xid: Xid) -> Result<XaTransaction<'_>> {
+        self.conn.as_mut().unwrap().start_xa_transaction(xid)
+    }
+
+    /// Redirects to [`Conn::xa_commit`].
+    pub fn xa_commit(&mut self, xid: &Xid) -> Result<()> {
+        self.conn.as_mut().unwrap().xa_commit(xid)
+    }
+
+    /// Redirects to [`Conn::xa_rollback`].
+    pub fn xa_rollback(&mut self, xid: &Xid) -> Result<()> {
+        self.conn.as_mut().unwrap().xa_rollback(xid)
+    }
+
+    /// Redirects to [`Conn::xa_recover`].
+    pub fn xa_recover(&mut self) -> Result<Vec<Xid>> {
+        self.conn.as_mut().unwrap().xa_recover()
+    }
+
+    /// Redirects to [`Conn::describe`].
+    pub fn describe<T: AsRef<str>>(&mut self, query: T) -> Result<Describe> {
+        self.conn.as_mut().unwrap().describe(query)
+    }
+
     /// Turns this connection into a binlog stream (see [`Conn::get_binlog_stream`]).
     #[cfg(feature = "binlog")]
     #[cfg_attr(docsrs, doc(cfg(feature = "binlog")))]
@@ -268,6 +408,32 @@ impl PooledConn {
         self.conn.take().unwrap().get_binlog_stream(request)
     }
 
+    /// Performs text query and returns a result, that owns this connection.
+    ///
+    /// Unlike [`Queryable::query_iter`] the result does not borrow the connection, so it
+    /// could be returned from a function or passed to another module. The connection goes
+    /// back to the pool as soon as the first result set is exhausted or the result is dropped.
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// fn numbers(pool: &Pool) -> Result<OwnedQueryResult> {
+    ///     pool.get_conn()?.query_stream("SELECT 1 UNION ALL SELECT 2")
+    /// }
+    ///
+    /// let pool = Pool::new(get_opts())?;
+    /// let numbers = numbers(&pool)?
+    ///     .map(|row| row.map(from_row::<u8>))
+    ///     .collect::<Result<Vec<_>>>()?;
+    /// assert_eq!(numbers, vec![1, 2]);
+    /// # });
+    /// ```
+    pub fn query_stream<Q: AsRef<str>>(mut self, query: Q) -> Result<OwnedQueryResult<Text>> {
+        let meta = self.as_mut()._query(query.as_ref())?;
+        Ok(OwnedQueryResult::new(QueryResult::new(self.into(), meta)))
+    }
+
     /// Unwraps wrapped [`Conn`](struct.Conn.html).
     pub fn unwrap(mut self) -> Conn {
         self.conn.take().unwrap()
@@ -288,6 +454,16 @@ impl PooledConn {
             .set_local_infile_handler(handler);
     }
 
+    /// Loads data into the table using `LOAD DATA LOCAL INFILE` (see [`Conn::load_data`] docs).
+    pub fn load_data<R: std::io::Read>(
+        &mut self,
+        table: &str,
+        reader: R,
+        format: &LoadDataFormat,
+    ) -> Result<LoadDataInfo> {
+        self.conn.as_mut().unwrap().load_data(table, reader, format)
+    }
+
     /// Invokes `COM_CHANGE_USER` (see [`Conn::change_user`] docs).
     pub fn change_user(&mut self) -> Result<()> {
         self.conn
@@ -347,10 +523,73 @@ mod test {
         use std::{thread, time::Duration};
 
         use crate::{
-            from_value, prelude::*, test_misc::get_opts, DriverError, Error, OptsBuilder, Pool,
-            PoolConstraints, PoolOpts, TxOpts, Value,
+            from_row, from_value, params, prelude::*, test_misc::get_opts, DriverError, Error,
+            MySqlError, OptsBuilder, Pool, PoolConstraints, PoolOpts, RetryPolicy, TxOpts, Value,
         };
 
+        #[test]
+        fn should_share_stmt_metadata_between_connections() {
+            let pool_opts = PoolOpts::new()
+                .with_constraints(PoolConstraints::new(2, 2).unwrap())
+                .with_shared_stmt_cache_size(1);
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+
+            let mut conn1 = pool.get_conn().unwrap();
+            let mut conn2 = pool.get_conn().unwrap();
+            let stmt1 = conn1.prep("SELECT :foo AS bar, :foo AS baz").unwrap();
+            let stmt2 = conn2.prep("SELECT :foo AS bar, :foo AS baz").unwrap();
+            assert_ne!(stmt1.connection_id(), stmt2.connection_id());
+            assert_eq!(stmt1.columns(), stmt2.columns());
+            assert_eq!(stmt1.params(), stmt2.params());
+            assert_eq!(stmt1.named_params, stmt2.named_params);
+
+            let row: (u8, u8) = conn2
+                .exec_first(&stmt2, params! { "foo" => 1 })
+                .unwrap()
+                .unwrap();
+            assert_eq!(row, (1, 1));
+
+            // evicts the first query
+            conn2.prep("SELECT 1").unwrap();
+            conn1.prep("SELECT :foo AS bar, :foo AS baz").unwrap();
+
+            let stats = pool.stmt_cache_stats();
+            assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 2, 1));
+            assert_eq!(conn1.stmt_cache_stats().hits, 1);
+            assert_eq!(conn2.stmt_cache_stats().misses, 2);
+        }
+
+        #[test]
+        fn should_key_shared_stmt_metadata_by_schema() {
+            let pool_opts = PoolOpts::new()
+                .with_constraints(PoolConstraints::new(2, 2).unwrap())
+                .with_shared_stmt_cache_size(16);
+            let pool = Pool::new(get_opts().pool_opts(pool_opts)).unwrap();
+
+            let mut conn1 = pool.get_conn().unwrap();
+            let mut conn2 = pool.get_conn().unwrap();
+            conn1
+                .query_drop(
+                    "DROP DATABASE IF EXISTS shared_a; CREATE DATABASE shared_a;
+                    CREATE TABLE shared_a.t (x INT);
+                    DROP DATABASE IF EXISTS shared_b; CREATE DATABASE shared_b;
+                    CREATE TABLE shared_b.t (y TEXT, z TEXT)",
+                )
+                .unwrap();
+
+            conn1.query_drop("USE shared_a").unwrap();
+            conn2.as_mut().select_db("shared_b").unwrap();
+            let stmt1 = conn1.prep("SELECT * FROM t").unwrap();
+            let stmt2 = conn2.prep("SELECT * FROM t").unwrap();
+            assert_eq!(stmt1.columns()[0].name_str(), "x");
+            assert_eq!(stmt2.columns().len(), 2);
+            assert_eq!(stmt2.columns()[0].name_str(), "y");
+
+            conn1
+                .query_drop("DROP DATABASE shared_a; DROP DATABASE shared_b")
+                .unwrap();
+        }
+
         #[test]
         fn multiple_pools_should_work() {
             let pool = Pool::new(get_opts()).unwrap();
@@ -612,6 +851,199 @@ mod test {
             Ok(())
         }
 
+        #[test]
+        fn should_rollback_open_transaction_upon_return() -> crate::Result<()> {
+            let pool = Pool::new(
+                get_opts().pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<1, 1>())
+                        .with_reset_connection(false),
+                ),
+            )?;
+
+            let mut conn = pool.get_conn()?;
+            conn.query_drop("CREATE TEMPORARY TABLE tmp (a INT)")?;
+            conn.query_drop("BEGIN")?;
+            conn.query_drop("INSERT INTO tmp (a) VALUES (1)")?;
+            assert!(conn.in_transaction());
+            drop(conn);
+
+            let mut conn = pool.get_conn()?;
+            assert!(!conn.in_transaction());
+            assert_eq!(
+                conn.query_first::<u8, _>("SELECT COUNT(*) FROM tmp")?,
+                Some(0)
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn should_restore_session_state_upon_return() -> crate::Result<()> {
+            let pool = Pool::new(
+                get_opts().pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<1, 1>())
+                        .with_reset_connection(false)
+                        .with_restore_session_state(true),
+                ),
+            )?;
+
+            let mut conn = pool.get_conn()?;
+            let time_zone: Option<String> = conn.query_first("SELECT @@time_zone")?;
+            let db_name: Option<String> = conn.query_first("SELECT DATABASE()")?;
+            let stmt = conn.prep("SELECT ?")?;
+            conn.query_drop("SET time_zone = '+03:00', wait_timeout = 100")?;
+            conn.query_drop("USE mysql")?;
+            drop(conn);
+
+            let mut conn = pool.get_conn()?;
+            assert_eq!(conn.query_first("SELECT @@time_zone")?, time_zone);
+            assert_ne!(
+                conn.query_first::<u32, _>("SELECT @@wait_timeout")?,
+                Some(100)
+            );
+            assert_eq!(conn.query_first("SELECT DATABASE()")?, db_name);
+            // statement cache is kept
+            assert_eq!(conn.exec_first::<u8, _, _>(&stmt, (1,))?, Some(1));
+
+            // user variables can't be restored selectively
+            conn.query_drop("SET @foo = 42")?;
+            drop(conn);
+
+            let mut conn = pool.get_conn()?;
+            assert_eq!(
+                conn.query_first::<Option<u8>, _>("SELECT @foo")?,
+                Some(None)
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn should_restore_variables_set_by_init() -> crate::Result<()> {
+            let pool = Pool::new(
+                get_opts().init(vec!["SET NAMES latin1"]).pool_opts(
+                    PoolOpts::default()
+                        .with_constraints(PoolConstraints::new_const::<1, 1>())
+                        .with_reset_connection(false)
+                        .with_restore_session_state(true),
+                ),
+            )?;
+
+            let mut conn = pool.get_conn()?;
+            conn.query_drop("SET NAMES utf8mb4")?;
+            drop(conn);
+
+            let mut conn = pool.get_conn()?;
+            assert_eq!(
+                conn.query_first::<String, _>("SELECT @@character_set_client")?,
+                Some("latin1".into())
+            );
+            Ok(())
+        }
+
+        #[test]
+        fn should_retry_transaction_on_deadlock() -> crate::Result<()> {
+            let pool = Pool::new(get_opts())?;
+            let error = |code| {
+                Error::MySqlError(MySqlError {
+                    state: "40001".into(),
+                    message: "simulated".into(),
+                    code,
+                })
+            };
+            let policy = RetryPolicy::default()
+                .with_max_attempts(3)
+                .with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+            let mut calls = 0;
+            let retried = pool.transaction_with_retry(TxOpts::default(), policy.clone(), |tx| {
+                calls += 1;
+                let value = tx.query_first::<u8, _>("SELECT 42")?;
+                if calls < 3 {
+                    Err(error(1213))
+                } else {
+                    Ok(value)
+                }
+            })?;
+            assert_eq!(retried.value, Some(42));
+            assert_eq!(retried.attempts, 3);
+
+            let mut calls = 0;
+            let result = pool.transaction_with_retry(TxOpts::default(), policy.clone(), |_| {
+                calls += 1;
+                Err::<(), _>(error(1205))
+            });
+            assert!(matches!(result, Err(Error::MySqlError(ref e)) if e.code == 1205));
+            assert_eq!(calls, 3);
+
+            let mut calls = 0;
+            let result = pool.transaction_with_retry(TxOpts::default(), policy, |_| {
+                calls += 1;
+                Err::<(), _>(error(1062))
+            });
+            assert!(result.is_err());
+            assert_eq!(calls, 1);
+            Ok(())
+        }
+
+        #[test]
+        fn should_discard_broken_connections() -> crate::Result<()> {
+            let pool = Pool::new(
+                get_opts()
+                    .read_timeout(Some(Duration::from_millis(500)))
+                    .pool_opts(
+                        PoolOpts::default().with_constraints(PoolConstraints::new_const::<1, 1>()),
+                    ),
+            )?;
+
+            let mut conn = pool.get_conn()?;
+            let connection_id = conn.connection_id();
+            let err = conn.query_drop("DO SLEEP(2)").unwrap_err();
+            assert!(err.is_connection_broken());
+            assert!(conn.is_broken());
+            drop(conn);
+            assert_eq!(pool.inner.count(), 0);
+
+            let mut conn = pool.get_conn()?;
+            assert_ne!(conn.connection_id(), connection_id);
+            assert!(!conn.is_broken());
+            assert_eq!(conn.query_first::<u8, _>("SELECT 1")?, Some(1));
+            drop(conn);
+            assert_eq!(pool.inner.count(), 1);
+            Ok(())
+        }
+
+        #[test]
+        fn should_release_connection_after_query_stream() -> crate::Result<()> {
+            let pool = Pool::new(get_opts().pool_opts(
+                PoolOpts::default().with_constraints(PoolConstraints::new_const::<1, 1>()),
+            ))?;
+
+            fn stream(pool: &Pool) -> crate::Result<crate::OwnedQueryResult> {
+                pool.get_conn()?
+                    .query_stream("SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3")
+            }
+
+            let mut result = stream(&pool)?;
+            assert!(pool.try_get_conn(Duration::from_millis(100)).is_err());
+            let rows = result
+                .by_ref()
+                .map(|row| row.map(from_row::<u8>))
+                .collect::<crate::Result<Vec<_>>>()?;
+            assert_eq!(rows, vec![1, 2, 3]);
+            assert!(result.is_exhausted());
+            drop(pool.try_get_conn(Duration::from_millis(100))?);
+
+            let mut result = stream(&pool)?;
+            assert_eq!(result.columns().as_ref().len(), 1);
+            assert!(result.next().is_some());
+            drop(result);
+            let mut conn = pool.try_get_conn(Duration::from_millis(100))?;
+            assert_eq!(conn.query_first::<u8, _>("SELECT 42")?, Some(42));
+
+            Ok(())
+        }
+
         #[test]
         fn should_start_transaction_on_PooledConn() {
             let pool = Pool::new(get_opts()).unwrap();
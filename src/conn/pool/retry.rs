@@ -0,0 +1,210 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{Error, ErrorKind, PooledConn, Result};
+
+/// Function that checks whether a transaction was committed (see
+/// [`RetryPolicy::with_commit_check`]).
+type CommitCheck = Arc<dyn Fn(&mut PooledConn) -> Result<bool> + Send + Sync>;
+
+/// Retry policy for [`crate::Pool::transaction_with_retry`].
+///
+/// ```
+/// # use mysql::*;
+/// # use std::time::Duration;
+/// let policy = RetryPolicy::default()
+///     .with_max_attempts(5)
+///     .with_backoff(Duration::from_millis(10), Duration::from_millis(500));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    commit_check: Option<CommitCheck>,
+}
+
+impl RetryPolicy {
+    /// Calls `Self::default`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of attempts, including the first one (defaults to `3`).
+    ///
+    /// Values less than `1` are treated as `1`.
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Returns the maximum number of attempts.
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Sets the backoff before the second attempt and the upper bound of the backoff
+    /// (defaults to `10ms` and `1s`).
+    ///
+    /// The backoff doubles with every failed attempt.
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff.max(initial_backoff);
+        self
+    }
+
+    /// Returns the backoff before the second attempt.
+    pub fn initial_backoff(&self) -> Duration {
+        self.initial_backoff
+    }
+
+    /// Returns the upper bound of the backoff.
+    pub fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// Turns on/off randomization of the backoff (defaults to `true`).
+    ///
+    /// If enabled, then the actual backoff is chosen uniformly between half and full value,
+    /// so that concurrently failed transactions won't retry in lockstep.
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns `true` if backoff randomization is enabled.
+    pub fn jitter(&self) -> bool {
+        self.jitter
+    }
+
+    /// Enables retry if the connection is lost during `COMMIT` (disabled by default).
+    ///
+    /// In this case it's unknown whether the transaction was committed or not,
+    /// so the given function is called on a new connection to find it out. It should return
+    /// `true` if the transaction was committed, and `false` if it's safe to run it again.
+    pub fn with_commit_check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&mut PooledConn) -> Result<bool> + Send + Sync + 'static,
+    {
+        self.commit_check = Some(Arc::new(check));
+        self
+    }
+
+    /// Returns `true` if the error is a deadlock or a lock wait timeout (see [`Error::kind`]).
+    pub fn is_retryable(&self, err: &Error) -> bool {
+        matches!(err.kind(), ErrorKind::Deadlock | ErrorKind::LockWaitTimeout)
+    }
+
+    pub(crate) fn commit_check(&self) -> Option<&CommitCheck> {
+        self.commit_check.as_ref()
+    }
+
+    /// Returns the backoff after the given failed attempt (starting from `1`).
+    pub(crate) fn backoff(&self, attempt: usize) -> Duration {
+        let exp = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let backoff = self
+            .initial_backoff
+            .checked_mul(2_u32.saturating_pow(exp))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+        if self.jitter {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_usize(attempt);
+            let half = backoff / 2;
+            half + half.mul_f64((hasher.finish() % 1_000) as f64 / 1_000.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            jitter: true,
+            commit_check: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("jitter", &self.jitter)
+            .field("commit_check", &self.commit_check.is_some())
+            .finish()
+    }
+}
+
+/// Result of [`crate::Pool::transaction_with_retry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retried<T> {
+    /// Value returned by the successful attempt.
+    pub value: T,
+    /// Number of attempts made (`1` if the first attempt succeeded).
+    pub attempts: usize,
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+    use crate::{DriverError, Error, MySqlError};
+
+    fn server_error(code: u16) -> Error {
+        Error::MySqlError(MySqlError {
+            state: "40001".into(),
+            message: String::new(),
+            code,
+        })
+    }
+
+    #[test]
+    fn should_classify_retryable_errors() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable(&server_error(1213)));
+        assert!(policy.is_retryable(&server_error(1205)));
+        assert!(!policy.is_retryable(&server_error(1062)));
+        assert!(!policy.is_retryable(&Error::DriverError(DriverError::ConnectTimeout)));
+    }
+
+    #[test]
+    fn should_compute_backoff() {
+        let policy = RetryPolicy::default()
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(50))
+            .with_jitter(false);
+        assert_eq!(policy.backoff(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff(2), Duration::from_millis(20));
+        assert_eq!(policy.backoff(3), Duration::from_millis(40));
+        assert_eq!(policy.backoff(4), Duration::from_millis(50));
+        assert_eq!(policy.backoff(100), Duration::from_millis(50));
+
+        let policy = policy.with_jitter(true);
+        for attempt in 1..10 {
+            let backoff = policy.backoff(attempt);
+            assert!(backoff <= Duration::from_millis(50));
+            assert!(backoff >= Duration::from_millis(5));
+        }
+    }
+}
@@ -8,7 +8,7 @@
 
 use mysql_common::row::convert::FromRowError;
 
-use std::{convert::TryInto, result::Result as StdResult};
+use std::{convert::TryInto, marker::PhantomData, result::Result as StdResult};
 
 use crate::{
     conn::{queryable::AsStatement, ConnMut},
@@ -339,6 +339,71 @@ where
     }
 }
 
+/// Prepared statement query bound to a row type.
+///
+/// This is what `mysql_macros::query_as!` expands to, but it could also be constructed manually.
+///
+/// Example:
+///
+/// ```rust
+/// # mysql::doctest_wrapper!(__result, {
+/// use mysql::*;
+/// let pool = Pool::new(get_opts())?;
+///
+/// let query = TypedQuery::<(u32, String)>::new("SELECT ?, 'foo'", Params::from((42,)));
+///
+/// assert_eq!(query.first(&pool)?, Some((42, String::from("foo"))));
+/// # });
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedQuery<T> {
+    query: QueryWithParams<&'static str, Params>,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<T: FromRow> TypedQuery<T> {
+    /// Creates new typed query.
+    pub fn new(query: &'static str, params: Params) -> Self {
+        Self {
+            query: QueryWithParams { query, params },
+            _row: PhantomData,
+        }
+    }
+
+    /// Returns the underlying query.
+    pub fn into_inner(self) -> QueryWithParams<&'static str, Params> {
+        self.query
+    }
+
+    /// Executes the query and returns the first row (see [`BinQuery::first`]).
+    pub fn first<'a, 'b, 'c: 'b, C>(self, conn: C) -> Result<Option<T>>
+    where
+        C: TryInto<ConnMut<'a, 'b, 'c>>,
+        Error: From<<C as TryInto<ConnMut<'a, 'b, 'c>>>::Error>,
+    {
+        self.query.first(conn)
+    }
+
+    /// Executes the query and collects all rows (see [`BinQuery::fetch`]).
+    pub fn fetch<'a, 'b, 'c: 'b, C>(self, conn: C) -> Result<Vec<T>>
+    where
+        C: TryInto<ConnMut<'a, 'b, 'c>>,
+        Error: From<<C as TryInto<ConnMut<'a, 'b, 'c>>>::Error>,
+    {
+        self.query.fetch(conn)
+    }
+
+    /// Executes the query and folds all rows (see [`BinQuery::fold`]).
+    pub fn fold<'a, 'b, 'c: 'b, U, F, C>(self, conn: C, init: U, next: F) -> Result<U>
+    where
+        C: TryInto<ConnMut<'a, 'b, 'c>>,
+        Error: From<<C as TryInto<ConnMut<'a, 'b, 'c>>>::Error>,
+        F: FnMut(U, T) -> U,
+    {
+        self.query.fold(conn, init, next)
+    }
+}
+
 /// Helper trait for batch statement execution.
 ///
 /// This trait covers the `Queryable::exec_batch` method.
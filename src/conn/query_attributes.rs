@@ -0,0 +1,171 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Encoding of query attributes (see [`crate::Conn::with_attributes`]).
+//!
+//! If `CLIENT_QUERY_ATTRIBUTES` is negotiated, then both `COM_QUERY` and `COM_STMT_EXECUTE`
+//! carry a parameter count followed by a parameter block, where every parameter type
+//! is followed by the parameter name (statement parameters are anonymous).
+
+use mysql_common::{
+    io::BufMutExt, packets::ComStmtExecuteRequestBuilder, proto::MySerialize, Value,
+};
+
+use std::{borrow::Cow, iter};
+
+/// Length of the `COM_STMT_EXECUTE` header (command, statement id, flags, iteration count).
+const EXECUTE_HEADER_LEN: usize = 10;
+
+/// Offset of the `flags` field of the `COM_STMT_EXECUTE` header.
+const EXECUTE_FLAGS_OFFSET: usize = 5;
+
+/// `COM_STMT_EXECUTE` flag, that tells the server that the parameter count is sent
+/// (required for a statement without parameters).
+const PARAMETER_COUNT_AVAILABLE: u8 = 0x08;
+
+/// Named values sent along with a query or a statement execution.
+pub(crate) type QueryAttributes = Vec<(String, Value)>;
+
+/// Returns statement parameters followed by values of the given attributes.
+pub(crate) fn execute_values<'a>(
+    params: &'a [Value],
+    attributes: &[(String, Value)],
+) -> Cow<'a, [Value]> {
+    if attributes.is_empty() {
+        Cow::Borrowed(params)
+    } else {
+        params
+            .iter()
+            .chain(attributes.iter().map(|(_, value)| value))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Rewrites the serialized `COM_STMT_EXECUTE` (built for [`execute_values`])
+/// as required by `CLIENT_QUERY_ATTRIBUTES`.
+pub(crate) fn rewrite_execute(
+    request: &mut Vec<u8>,
+    num_params: usize,
+    attributes: &[(String, Value)],
+) {
+    let count = num_params + attributes.len();
+    if count == 0 {
+        return;
+    }
+    let block = request.split_off(EXECUTE_HEADER_LEN);
+    if num_params == 0 {
+        request[EXECUTE_FLAGS_OFFSET] |= PARAMETER_COUNT_AVAILABLE;
+    }
+    request.put_lenenc_int(count as u64);
+    write_named_params(request, &block, num_params, attributes);
+}
+
+/// Writes the `COM_QUERY` prefix (everything between the command byte and the query)
+/// as required by `CLIENT_QUERY_ATTRIBUTES`.
+pub(crate) fn write_query_prefix(buf: &mut Vec<u8>, attributes: &[(String, Value)]) {
+    buf.put_lenenc_int(attributes.len() as u64);
+    // parameter set count is always 1
+    buf.put_lenenc_int(1);
+    if attributes.is_empty() {
+        return;
+    }
+    // the parameter block is the same as for a statement execution
+    let values = execute_values(&[], attributes);
+    let (request, _) = ComStmtExecuteRequestBuilder::new(0).build(&values);
+    let mut serialized = Vec::new();
+    request.serialize(&mut serialized);
+    write_named_params(buf, &serialized[EXECUTE_HEADER_LEN..], 0, attributes);
+}
+
+/// Writes the null bitmap, the new params bound flag, types and names of parameters
+/// followed by their values (`block` is the same block serialized without names).
+fn write_named_params(
+    buf: &mut Vec<u8>,
+    block: &[u8],
+    num_params: usize,
+    attributes: &[(String, Value)],
+) {
+    let count = num_params + attributes.len();
+    let (head, rest) = block.split_at(count.div_ceil(8) + 1);
+    let (types, values) = rest.split_at(count * 2);
+    buf.extend_from_slice(head);
+    let names =
+        iter::repeat_n("", num_params).chain(attributes.iter().map(|(name, _)| name.as_str()));
+    for (param_type, name) in types.chunks(2).zip(names) {
+        buf.extend_from_slice(param_type);
+        buf.put_lenenc_str(name.as_bytes());
+    }
+    buf.extend_from_slice(values);
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{packets::ComStmtExecuteRequestBuilder, proto::MySerialize, Value};
+
+    use super::{execute_values, rewrite_execute, write_query_prefix};
+
+    #[test]
+    fn should_write_query_attributes() {
+        let mut buf = Vec::new();
+        write_query_prefix(&mut buf, &[]);
+        assert_eq!(buf, [0x00, 0x01]);
+
+        let mut buf = Vec::new();
+        write_query_prefix(&mut buf, &[("a".into(), Value::from("xy"))]);
+        assert_eq!(
+            buf,
+            [
+                0x01, 0x01, // parameter count, parameter set count
+                0x00, 0x01, // null bitmap, new params bound flag
+                0xfd, 0x00, 0x01, b'a', // type, flags, name
+                0x02, b'x', b'y', // value
+            ]
+        );
+    }
+
+    #[test]
+    fn should_rewrite_execute() {
+        let params = [Value::Int(1), Value::NULL];
+        let attributes = [("trace".to_string(), Value::from("t"))];
+        let values = execute_values(&params, &attributes);
+        let (request, _) = ComStmtExecuteRequestBuilder::new(7).build(&values);
+        let mut buf = Vec::new();
+        request.serialize(&mut buf);
+        rewrite_execute(&mut buf, params.len(), &attributes);
+        assert_eq!(
+            &buf[10..],
+            &[
+                0x03, // parameter count
+                0x02, 0x01, // null bitmap, new params bound flag
+                0x08, 0x00, 0x00, // longlong, flags, empty name
+                0x06, 0x00, 0x00, // null, flags, empty name
+                0xfd, 0x00, 0x05, b't', b'r', b'a', b'c', b'e', // var string, flags, name
+                0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // int value
+                0x01, b't', // string value
+            ][..]
+        );
+        assert_eq!(buf[5], 0x00);
+
+        // parameter count is available for a statement without parameters
+        let values = execute_values(&[], &attributes);
+        let (request, _) = ComStmtExecuteRequestBuilder::new(7).build(&values);
+        let mut buf = Vec::new();
+        request.serialize(&mut buf);
+        rewrite_execute(&mut buf, 0, &attributes);
+        assert_eq!(buf[5], 0x08);
+        assert_eq!(buf[10], 0x01);
+
+        // nothing to rewrite
+        let (request, _) = ComStmtExecuteRequestBuilder::new(7).build(&[]);
+        let mut buf = Vec::new();
+        request.serialize(&mut buf);
+        rewrite_execute(&mut buf, 0, &[]);
+        assert_eq!(buf.len(), 10);
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Support code for compile-time checks emitted by `query_as!` (not a public API).
+//!
+//! The macro classifies every result set column into one of the kinds below
+//! and requires the corresponding row field to implement `FromColumn<Kind>`.
+
+use crate::{prelude::FromValue, Deserialized, Value};
+
+/// `TINYINT` .. `BIGINT` and `YEAR` columns.
+pub struct Integer;
+/// `FLOAT` and `DOUBLE` columns.
+pub struct Float;
+/// `DECIMAL` columns.
+pub struct Decimal;
+/// Character strings, `ENUM` and `SET` columns.
+pub struct Text;
+/// Binary strings, `BIT` and `GEOMETRY` columns.
+pub struct Bytes;
+/// `DATE` columns.
+pub struct Date;
+/// `DATETIME` and `TIMESTAMP` columns.
+pub struct DateTime;
+/// `TIME` columns.
+pub struct Time;
+/// `JSON` columns.
+pub struct Json;
+
+/// Implemented for row field types, that could be converted from a column of kind `K`.
+pub trait FromColumn<K> {}
+
+/// Implemented for row field types, that could hold `NULL`.
+pub trait FromNullableColumn {}
+
+impl<K> FromColumn<K> for Value {}
+impl<K, T: FromColumn<K>> FromColumn<K> for Option<T> {}
+
+impl FromNullableColumn for Value {}
+impl<T> FromNullableColumn for Option<T> {}
+
+macro_rules! from_column {
+    ($kind:ty => $($(#[$attr:meta])* $ty:ty),* $(,)?) => {
+        $( $(#[$attr])* impl FromColumn<$kind> for $ty {} )*
+    };
+}
+
+from_column!(Integer =>
+    i8, i16, i32, i64, i128, isize,
+    u8, u16, u32, u64, u128, usize,
+    bool,
+    #[cfg(feature = "rust_decimal")] crate::myc::rust_decimal::Decimal,
+    #[cfg(feature = "bigdecimal")] crate::myc::bigdecimal::BigDecimal,
+);
+from_column!(Float => f32, f64);
+from_column!(Decimal =>
+    f32, f64, String, Vec<u8>,
+    #[cfg(feature = "rust_decimal")] crate::myc::rust_decimal::Decimal,
+    #[cfg(feature = "bigdecimal")] crate::myc::bigdecimal::BigDecimal,
+);
+from_column!(Text => String, Vec<u8>, serde_json::Value);
+from_column!(Bytes => String, Vec<u8>, serde_json::Value);
+from_column!(Json => String, Vec<u8>, serde_json::Value);
+from_column!(Date =>
+    #[cfg(feature = "chrono")] crate::myc::chrono::NaiveDate,
+    #[cfg(feature = "chrono")] crate::myc::chrono::NaiveDateTime,
+    #[cfg(feature = "time")] crate::myc::time::Date,
+    #[cfg(feature = "time")] crate::myc::time::PrimitiveDateTime,
+);
+from_column!(DateTime =>
+    #[cfg(feature = "chrono")] crate::myc::chrono::NaiveDateTime,
+    #[cfg(feature = "time")] crate::myc::time::PrimitiveDateTime,
+);
+from_column!(Time =>
+    std::time::Duration,
+    #[cfg(feature = "chrono")] crate::myc::chrono::NaiveTime,
+    #[cfg(feature = "time")] crate::myc::time::Time,
+    #[cfg(feature = "time")] crate::myc::time::Duration,
+);
+
+impl<T: serde::de::DeserializeOwned> FromColumn<Text> for Deserialized<T> {}
+impl<T: serde::de::DeserializeOwned> FromColumn<Bytes> for Deserialized<T> {}
+impl<T: serde::de::DeserializeOwned> FromColumn<Json> for Deserialized<T> {}
+
+/// Field of a `NOT NULL` column of kind `K`.
+pub fn column<K, T: FromColumn<K>>() -> T {
+    unreachable!()
+}
+
+/// Field of a nullable column of kind `K`.
+pub fn nullable_column<K, T: FromColumn<K> + FromNullableColumn>() -> T {
+    unreachable!()
+}
+
+/// Field of a column, that is not checked (e.g. a column of unknown type).
+pub fn any_column<T: FromValue>() -> T {
+    unreachable!()
+}
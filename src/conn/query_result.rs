@@ -10,9 +10,15 @@ pub use mysql_common::proto::{Binary, Text};
 
 use mysql_common::{io::ParseBuf, packets::OkPacket, row::RowDeserializer, value::ServerSide};
 
-use std::{borrow::Cow, marker::PhantomData, sync::Arc};
+use std::{borrow::Cow, io, marker::PhantomData, ops::Range, sync::Arc};
 
-use crate::{conn::ConnMut, Column, Conn, Error, Result, Row};
+use crate::{
+    conn::{
+        row_ref::{RowKind, RowRef},
+        ConnMut,
+    },
+    Column, Conn, Error, Result, Row, Warning,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Or<A, B> {
@@ -23,6 +29,22 @@ pub enum Or<A, B> {
 /// Result set kind.
 pub trait Protocol: 'static + Send + Sync {
     fn next(conn: &mut Conn, columns: Arc<[Column]>) -> Result<Option<Row>>;
+
+    /// Parses a row packet without copying its values (see [`ResultSet::for_each_ref`]).
+    ///
+    /// The default implementation reports that borrowed rows are not supported.
+    fn row_ref<'a>(
+        columns: &'a [Column],
+        packet: &'a [u8],
+        ranges: &'a mut Vec<Option<Range<usize>>>,
+    ) -> Result<RowRef<'a>> {
+        let _ = (columns, packet, ranges);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "borrowed rows are not supported by this protocol",
+        )
+        .into())
+    }
 }
 
 impl Protocol for Text {
@@ -35,6 +57,14 @@ impl Protocol for Text {
             None => Ok(None),
         }
     }
+
+    fn row_ref<'a>(
+        columns: &'a [Column],
+        packet: &'a [u8],
+        ranges: &'a mut Vec<Option<Range<usize>>>,
+    ) -> Result<RowRef<'a>> {
+        Ok(RowRef::parse(RowKind::Text, columns, packet, ranges)?)
+    }
 }
 
 impl Protocol for Binary {
@@ -47,6 +77,14 @@ impl Protocol for Binary {
             None => Ok(None),
         }
     }
+
+    fn row_ref<'a>(
+        columns: &'a [Column],
+        packet: &'a [u8],
+        ranges: &'a mut Vec<Option<Range<usize>>>,
+    ) -> Result<RowRef<'a>> {
+        Ok(RowRef::parse(RowKind::Binary, columns, packet, ranges)?)
+    }
 }
 
 /// State of a result set iterator.
@@ -82,9 +120,9 @@ impl SetIteratorState {
     }
 }
 
-impl From<Vec<Column>> for SetIteratorState {
-    fn from(columns: Vec<Column>) -> Self {
-        Self::InSet(columns.into())
+impl From<Arc<[Column]>> for SetIteratorState {
+    fn from(columns: Arc<[Column]>) -> Self {
+        Self::InSet(columns)
     }
 }
 
@@ -100,8 +138,8 @@ impl From<Error> for SetIteratorState {
     }
 }
 
-impl From<Or<Vec<Column>, OkPacket<'static>>> for SetIteratorState {
-    fn from(or: Or<Vec<Column>, OkPacket<'static>>) -> Self {
+impl From<Or<Arc<[Column]>, OkPacket<'static>>> for SetIteratorState {
+    fn from(or: Or<Arc<[Column]>, OkPacket<'static>>) -> Self {
         match or {
             Or::A(cols) => Self::from(cols),
             Or::B(ok) => Self::from(ok),
@@ -137,7 +175,7 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
 
     pub(crate) fn new(
         conn: ConnMut<'c, 't, 'tc>,
-        meta: Or<Vec<Column>, OkPacket<'static>>,
+        meta: Or<Arc<[Column]>, OkPacket<'static>>,
     ) -> QueryResult<'c, 't, 'tc, T> {
         Self::from_state(conn, meta.into())
     }
@@ -154,7 +192,7 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
         );
 
         if self.conn.more_results_exists() {
-            match self.conn.handle_result_set() {
+            match self.conn.handle_result_set(None) {
                 Ok(meta) => self.state = meta.into(),
                 Err(err) => self.state = err.into(),
             }
@@ -268,6 +306,13 @@ impl<'c, 't, 'tc, T: crate::prelude::Protocol> QueryResult<'c, 't, 'tc, T> {
             .unwrap_or_default()
     }
 
+    /// Returns warnings fetched automatically (see [`crate::OptsBuilder::fetch_warnings`]).
+    ///
+    /// Warnings are fetched once the last result set is consumed.
+    pub fn fetched_warnings(&self) -> &[Warning] {
+        self.conn.fetched_warnings()
+    }
+
     /// [Info] for the current result set.
     ///
     /// Will be empty if not defined.
@@ -320,6 +365,46 @@ impl<'a, 'b, 'c, T: crate::prelude::Protocol> std::ops::Deref for ResultSet<'a,
     }
 }
 
+impl<T: crate::prelude::Protocol> ResultSet<'_, '_, '_, '_, T> {
+    /// Calls `f` for each remaining row of this result set.
+    ///
+    /// Unlike the `Iterator` impl it won't allocate a `Row` for every row – values of
+    /// the given [`RowRef`] are borrowed from the packet buffer, that is reused
+    /// (see the [Buffer Pool](crate#buffer-pool) section of the crate docs).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let pool = Pool::new(get_opts())?;
+    /// # let mut conn = pool.get_conn()?;
+    /// let mut total_len = 0;
+    /// let mut result = conn.query_iter("SELECT 'foo' UNION ALL SELECT 'quux'")?;
+    /// result.iter().unwrap().for_each_ref(|row| {
+    ///     total_len += row.get_str(0).map(str::len).unwrap_or_default();
+    /// })?;
+    /// assert_eq!(total_len, 7);
+    /// # });
+    /// ```
+    pub fn for_each_ref<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(RowRef<'_>),
+    {
+        let mut ranges = Vec::new();
+        while self.set_index == self.inner.set_index {
+            let next = self
+                .inner
+                .next_with(|conn, columns| Ok(conn.next_row_packet()?.map(|pld| (pld, columns))));
+            match next {
+                Some(Ok((pld, columns))) => f(T::row_ref(&columns, &pld, &mut ranges)?),
+                Some(Err(err)) => return Err(err),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<T: crate::prelude::Protocol> Iterator for ResultSet<'_, '_, '_, '_, T> {
     type Item = Result<Row>;
 
@@ -332,16 +417,26 @@ impl<T: crate::prelude::Protocol> Iterator for ResultSet<'_, '_, '_, '_, T> {
     }
 }
 
-impl<T: crate::prelude::Protocol> Iterator for QueryResult<'_, '_, '_, T> {
-    type Item = Result<Row>;
+impl<T: crate::prelude::Protocol> QueryResult<'_, '_, '_, T> {
+    /// Returns an index of the current result set.
+    #[cfg(feature = "arrow")]
+    pub(crate) fn set_index(&self) -> usize {
+        self.set_index
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    /// Advances the current result set using `f` to read the next row.
+    ///
+    /// This is the same as `Iterator::next` except for the row reading part.
+    pub(crate) fn next_with<U, F>(&mut self, f: F) -> Option<Result<U>>
+    where
+        F: FnOnce(&mut Conn, Arc<[Column]>) -> Result<Option<U>>,
+    {
         use SetIteratorState::*;
 
         let state = std::mem::replace(&mut self.state, OnBoundary);
 
         match state {
-            InSet(cols) => match T::next(&mut self.conn, cols.clone()) {
+            InSet(cols) => match f(&mut self.conn, cols.clone()) {
                 Ok(Some(row)) => {
                     self.state = InSet(cols);
                     Some(Ok(row))
@@ -372,6 +467,14 @@ impl<T: crate::prelude::Protocol> Iterator for QueryResult<'_, '_, '_, T> {
     }
 }
 
+impl<T: crate::prelude::Protocol> Iterator for QueryResult<'_, '_, '_, T> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with(T::next)
+    }
+}
+
 impl<T: crate::prelude::Protocol> Drop for ResultSet<'_, '_, '_, '_, T> {
     fn drop(&mut self) {
         while self.next().is_some() {}
@@ -393,6 +496,15 @@ impl<'a> SetColumns<'a> {
     }
 }
 
+#[cfg(feature = "arrow")]
+impl SetColumns<'_> {
+    /// Returns an arrow schema that corresponds to these columns.
+    #[cfg_attr(docsrs, doc(cfg(feature = "arrow")))]
+    pub fn arrow_schema(&self) -> arrow_schema::Schema {
+        crate::conn::record_batch::columns_to_schema(self.as_ref())
+    }
+}
+
 impl AsRef<[Column]> for SetColumns<'_> {
     fn as_ref(&self) -> &[Column] {
         self.inner
@@ -401,3 +513,47 @@ impl AsRef<[Column]> for SetColumns<'_> {
             .unwrap_or(&[][..])
     }
 }
+
+/// Result of a text query that owns its pooled connection.
+///
+/// It iterates over rows of the first result set. The connection goes back to the pool
+/// as soon as the set is exhausted or this value is dropped, so it could be freely moved
+/// across function boundaries (see [`crate::PooledConn::query_stream`]).
+#[derive(Debug)]
+pub struct OwnedQueryResult<T: crate::prelude::Protocol = Text> {
+    inner: Option<QueryResult<'static, 'static, 'static, T>>,
+}
+
+impl<T: crate::prelude::Protocol> OwnedQueryResult<T> {
+    pub(crate) fn new(inner: QueryResult<'static, 'static, 'static, T>) -> Self {
+        Self { inner: Some(inner) }
+    }
+
+    /// Returns columns of the result set.
+    ///
+    /// Will be empty if the result set is exhausted.
+    pub fn columns(&self) -> SetColumns<'_> {
+        match self.inner {
+            Some(ref inner) => inner.columns(),
+            None => SetColumns { inner: None },
+        }
+    }
+
+    /// Returns `true` if the connection is already released.
+    pub fn is_exhausted(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+impl<T: crate::prelude::Protocol> Iterator for OwnedQueryResult<T> {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.inner.as_mut()?.next();
+        if row.is_none() {
+            // drops the rest of the result and releases the connection
+            self.inner = None;
+        }
+        row
+    }
+}
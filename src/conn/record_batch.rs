@@ -0,0 +1,706 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use arrow_array::{
+    builder::{
+        ArrayBuilder, BinaryBuilder, BooleanBuilder, Date32Builder, Decimal128Builder,
+        DurationMicrosecondBuilder, Float32Builder, Float64Builder, Int16Builder, Int32Builder,
+        Int64Builder, Int8Builder, NullBuilder, StringBuilder, TimestampMicrosecondBuilder,
+        TimestampMillisecondBuilder, TimestampSecondBuilder, UInt16Builder, UInt32Builder,
+        UInt64Builder, UInt8Builder,
+    },
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use mysql_common::{
+    constants::{ColumnFlags, ColumnType},
+    io::ParseBuf,
+};
+
+use std::{collections::HashMap, io, sync::Arc};
+
+use crate::{conn::stmt::BINARY_CHARSET, Binary, Column, QueryResult, Result};
+
+/// Maximum precision of the `Decimal128` arrow type.
+const DECIMAL128_MAX_PRECISION: u32 = 38;
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "can't parse binary row value")
+}
+
+/// Returns `(precision, scale)` for a DECIMAL column.
+fn decimal_precision(column: &Column) -> (u32, u8) {
+    let scale = column.decimals();
+    let mut precision = column.column_length();
+    if scale > 0 {
+        // decimal point
+        precision = precision.saturating_sub(1);
+    }
+    if !column.flags().contains(ColumnFlags::UNSIGNED_FLAG) {
+        // sign
+        precision = precision.saturating_sub(1);
+    }
+    (precision.max(1), scale)
+}
+
+/// Returns timestamp unit for the given fractional seconds precision.
+fn time_unit(fsp: u8) -> TimeUnit {
+    match fsp {
+        0 => TimeUnit::Second,
+        1..=3 => TimeUnit::Millisecond,
+        _ => TimeUnit::Microsecond,
+    }
+}
+
+/// Converts a column definition into an arrow field.
+///
+/// Temporal fields are always nullable because zero dates are represented as nulls.
+pub(crate) fn column_to_field(column: &Column) -> Field {
+    use ColumnType::*;
+
+    let unsigned = column.flags().contains(ColumnFlags::UNSIGNED_FLAG);
+    let binary = column.character_set() == BINARY_CHARSET;
+    let mut nullable = !column.flags().contains(ColumnFlags::NOT_NULL_FLAG);
+    let mut metadata = HashMap::new();
+
+    let data_type = match column.column_type() {
+        MYSQL_TYPE_NULL => {
+            nullable = true;
+            DataType::Null
+        }
+        MYSQL_TYPE_TINY if unsigned => DataType::UInt8,
+        MYSQL_TYPE_TINY => DataType::Int8,
+        MYSQL_TYPE_SHORT if unsigned => DataType::UInt16,
+        MYSQL_TYPE_SHORT => DataType::Int16,
+        MYSQL_TYPE_YEAR => DataType::UInt16,
+        MYSQL_TYPE_INT24 | MYSQL_TYPE_LONG if unsigned => DataType::UInt32,
+        MYSQL_TYPE_INT24 | MYSQL_TYPE_LONG => DataType::Int32,
+        MYSQL_TYPE_LONGLONG if unsigned => DataType::UInt64,
+        MYSQL_TYPE_LONGLONG => DataType::Int64,
+        MYSQL_TYPE_FLOAT => DataType::Float32,
+        MYSQL_TYPE_DOUBLE => DataType::Float64,
+        MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => match decimal_precision(column) {
+            (precision, scale) if precision <= DECIMAL128_MAX_PRECISION => {
+                DataType::Decimal128(precision as u8, scale as i8)
+            }
+            // doesn't fit into Decimal128, so it is exported as is
+            _ => DataType::Utf8,
+        },
+        MYSQL_TYPE_DATE | MYSQL_TYPE_NEWDATE => {
+            nullable = true;
+            DataType::Date32
+        }
+        MYSQL_TYPE_DATETIME
+        | MYSQL_TYPE_DATETIME2
+        | MYSQL_TYPE_TIMESTAMP
+        | MYSQL_TYPE_TIMESTAMP2 => {
+            nullable = true;
+            DataType::Timestamp(time_unit(column.decimals()), None)
+        }
+        MYSQL_TYPE_TIME | MYSQL_TYPE_TIME2 => DataType::Duration(TimeUnit::Microsecond),
+        MYSQL_TYPE_BIT if column.column_length() == 1 => DataType::Boolean,
+        MYSQL_TYPE_BIT => DataType::UInt64,
+        MYSQL_TYPE_JSON => {
+            metadata.insert("ARROW:extension:name".into(), "arrow.json".into());
+            DataType::Utf8
+        }
+        MYSQL_TYPE_VARCHAR
+        | MYSQL_TYPE_VAR_STRING
+        | MYSQL_TYPE_STRING
+        | MYSQL_TYPE_ENUM
+        | MYSQL_TYPE_SET
+        | MYSQL_TYPE_TINY_BLOB
+        | MYSQL_TYPE_MEDIUM_BLOB
+        | MYSQL_TYPE_LONG_BLOB
+        | MYSQL_TYPE_BLOB
+            if !binary =>
+        {
+            DataType::Utf8
+        }
+        _ => DataType::Binary,
+    };
+
+    Field::new(column.name_str(), data_type, nullable).with_metadata(metadata)
+}
+
+/// Converts result set columns into an arrow schema.
+pub(crate) fn columns_to_schema(columns: &[Column]) -> Schema {
+    Schema::new(columns.iter().map(column_to_field).collect::<Vec<_>>())
+}
+
+/// Parses textual decimal representation into an integer scaled by `10^scale`.
+fn parse_decimal(bytes: &[u8], scale: u8) -> io::Result<i128> {
+    let (negative, bytes) = match bytes.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        Some((b'+', rest)) => (false, rest),
+        _ => (false, bytes),
+    };
+
+    let mut split = bytes.splitn(2, |x| *x == b'.');
+    let int_part = split.next().unwrap_or_default();
+    let frac_part = split.next().unwrap_or_default();
+
+    if frac_part.len() > scale as usize {
+        return Err(invalid_data("decimal value does not match column scale"));
+    }
+
+    let mut value = 0_i128;
+    let digits = int_part
+        .iter()
+        .chain(frac_part.iter())
+        .copied()
+        .chain(std::iter::repeat_n(b'0', scale as usize - frac_part.len()));
+    for digit in digits {
+        if !digit.is_ascii_digit() {
+            return Err(invalid_data("invalid decimal value"));
+        }
+        value = value
+            .checked_mul(10)
+            .and_then(|x| x.checked_add((digit - b'0') as i128))
+            .ok_or_else(|| invalid_data("decimal value is out of range"))?;
+    }
+
+    Ok(if negative { -value } else { value })
+}
+
+/// Returns the number of days since the unix epoch for the given civil date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Parses binary protocol date/datetime value into `(days since epoch, micros of day)`.
+///
+/// Returns `None` for zero dates.
+fn parse_datetime(buf: &mut ParseBuf<'_>) -> io::Result<Option<(i64, i64)>> {
+    let len = buf.checked_eat_u8().ok_or_else(unexpected_eof)? as usize;
+    let mut value = buf.checked_eat_buf(len).ok_or_else(unexpected_eof)?;
+
+    let (year, month, day) = if len >= 4 {
+        (
+            value.eat_u16_le() as i64,
+            value.eat_u8() as i64,
+            value.eat_u8() as i64,
+        )
+    } else {
+        (0, 0, 0)
+    };
+    let (hour, minute, second) = if len >= 7 {
+        (
+            value.eat_u8() as i64,
+            value.eat_u8() as i64,
+            value.eat_u8() as i64,
+        )
+    } else {
+        (0, 0, 0)
+    };
+    let micros = if len >= 11 {
+        value.eat_u32_le() as i64
+    } else {
+        0
+    };
+
+    if month == 0 || day == 0 {
+        return Ok(None);
+    }
+
+    let days = days_from_civil(year, month, day);
+    let micros = ((hour * 60 + minute) * 60 + second) * 1_000_000 + micros;
+    Ok(Some((days, micros)))
+}
+
+/// Parses binary protocol time value into microseconds.
+fn parse_time(buf: &mut ParseBuf<'_>) -> io::Result<i64> {
+    let len = buf.checked_eat_u8().ok_or_else(unexpected_eof)? as usize;
+    let mut value = buf.checked_eat_buf(len).ok_or_else(unexpected_eof)?;
+
+    if len < 8 {
+        return Ok(0);
+    }
+
+    let negative = value.eat_u8() == 1;
+    let days = value.eat_u32_le() as i64;
+    let hours = value.eat_u8() as i64;
+    let minutes = value.eat_u8() as i64;
+    let seconds = value.eat_u8() as i64;
+    let micros = if len >= 12 {
+        value.eat_u32_le() as i64
+    } else {
+        0
+    };
+
+    let micros = (((days * 24 + hours) * 60 + minutes) * 60 + seconds) * 1_000_000 + micros;
+    Ok(if negative { -micros } else { micros })
+}
+
+fn eat_lenenc_str<'a>(buf: &mut ParseBuf<'a>) -> io::Result<&'a [u8]> {
+    buf.checked_eat_lenenc_str().ok_or_else(unexpected_eof)
+}
+
+fn eat_utf8<'a>(buf: &mut ParseBuf<'a>) -> io::Result<&'a str> {
+    std::str::from_utf8(eat_lenenc_str(buf)?).map_err(invalid_data)
+}
+
+/// Arrow builder for a single column.
+enum ColumnBuilder {
+    Null(NullBuilder),
+    Boolean(BooleanBuilder),
+    Int8(Int8Builder),
+    Int16(Int16Builder),
+    Int32(Int32Builder),
+    Int64(Int64Builder),
+    UInt8(UInt8Builder),
+    UInt16(UInt16Builder),
+    UInt32(UInt32Builder),
+    UInt64(UInt64Builder),
+    Float32(Float32Builder),
+    Float64(Float64Builder),
+    Decimal128(Decimal128Builder, u8),
+    Date32(Date32Builder),
+    TimestampSecond(TimestampSecondBuilder),
+    TimestampMillisecond(TimestampMillisecondBuilder),
+    TimestampMicrosecond(TimestampMicrosecondBuilder),
+    Duration(DurationMicrosecondBuilder),
+    Utf8(StringBuilder),
+    Binary(BinaryBuilder),
+}
+
+impl ColumnBuilder {
+    fn new(data_type: &DataType, capacity: usize) -> io::Result<Self> {
+        Ok(match data_type {
+            DataType::Null => Self::Null(NullBuilder::new()),
+            DataType::Boolean => Self::Boolean(BooleanBuilder::with_capacity(capacity)),
+            DataType::Int8 => Self::Int8(Int8Builder::with_capacity(capacity)),
+            DataType::Int16 => Self::Int16(Int16Builder::with_capacity(capacity)),
+            DataType::Int32 => Self::Int32(Int32Builder::with_capacity(capacity)),
+            DataType::Int64 => Self::Int64(Int64Builder::with_capacity(capacity)),
+            DataType::UInt8 => Self::UInt8(UInt8Builder::with_capacity(capacity)),
+            DataType::UInt16 => Self::UInt16(UInt16Builder::with_capacity(capacity)),
+            DataType::UInt32 => Self::UInt32(UInt32Builder::with_capacity(capacity)),
+            DataType::UInt64 => Self::UInt64(UInt64Builder::with_capacity(capacity)),
+            DataType::Float32 => Self::Float32(Float32Builder::with_capacity(capacity)),
+            DataType::Float64 => Self::Float64(Float64Builder::with_capacity(capacity)),
+            DataType::Decimal128(precision, scale) => Self::Decimal128(
+                Decimal128Builder::with_capacity(capacity)
+                    .with_precision_and_scale(*precision, *scale)
+                    .map_err(invalid_data)?,
+                *scale as u8,
+            ),
+            DataType::Date32 => Self::Date32(Date32Builder::with_capacity(capacity)),
+            DataType::Timestamp(TimeUnit::Second, _) => {
+                Self::TimestampSecond(TimestampSecondBuilder::with_capacity(capacity))
+            }
+            DataType::Timestamp(TimeUnit::Millisecond, _) => {
+                Self::TimestampMillisecond(TimestampMillisecondBuilder::with_capacity(capacity))
+            }
+            DataType::Timestamp(_, _) => {
+                Self::TimestampMicrosecond(TimestampMicrosecondBuilder::with_capacity(capacity))
+            }
+            DataType::Duration(_) => {
+                Self::Duration(DurationMicrosecondBuilder::with_capacity(capacity))
+            }
+            DataType::Utf8 => Self::Utf8(StringBuilder::with_capacity(capacity, capacity * 16)),
+            _ => Self::Binary(BinaryBuilder::with_capacity(capacity, capacity * 16)),
+        })
+    }
+
+    fn append_null(&mut self) {
+        match self {
+            Self::Null(b) => b.append_null(),
+            Self::Boolean(b) => b.append_null(),
+            Self::Int8(b) => b.append_null(),
+            Self::Int16(b) => b.append_null(),
+            Self::Int32(b) => b.append_null(),
+            Self::Int64(b) => b.append_null(),
+            Self::UInt8(b) => b.append_null(),
+            Self::UInt16(b) => b.append_null(),
+            Self::UInt32(b) => b.append_null(),
+            Self::UInt64(b) => b.append_null(),
+            Self::Float32(b) => b.append_null(),
+            Self::Float64(b) => b.append_null(),
+            Self::Decimal128(b, _) => b.append_null(),
+            Self::Date32(b) => b.append_null(),
+            Self::TimestampSecond(b) => b.append_null(),
+            Self::TimestampMillisecond(b) => b.append_null(),
+            Self::TimestampMicrosecond(b) => b.append_null(),
+            Self::Duration(b) => b.append_null(),
+            Self::Utf8(b) => b.append_null(),
+            Self::Binary(b) => b.append_null(),
+        }
+    }
+
+    /// Decodes a binary protocol value of the given column and appends it to the builder.
+    fn append_value(&mut self, column: &Column, buf: &mut ParseBuf<'_>) -> io::Result<()> {
+        use ColumnType::*;
+
+        let eof = unexpected_eof;
+
+        match self {
+            Self::Null(b) => b.append_null(),
+            Self::Boolean(b) => b.append_value(eat_lenenc_str(buf)?.iter().any(|x| *x != 0)),
+            Self::Int8(b) => b.append_value(buf.checked_eat_i8().ok_or_else(eof)?),
+            Self::UInt8(b) => b.append_value(buf.checked_eat_u8().ok_or_else(eof)?),
+            Self::Int16(b) => b.append_value(buf.checked_eat_i16_le().ok_or_else(eof)?),
+            Self::UInt16(b) => b.append_value(buf.checked_eat_u16_le().ok_or_else(eof)?),
+            Self::Int32(b) => b.append_value(buf.checked_eat_i32_le().ok_or_else(eof)?),
+            Self::UInt32(b) => b.append_value(buf.checked_eat_u32_le().ok_or_else(eof)?),
+            Self::Int64(b) => b.append_value(buf.checked_eat_i64_le().ok_or_else(eof)?),
+            Self::UInt64(b) if column.column_type() == MYSQL_TYPE_BIT => {
+                // BIT value is a big-endian byte string
+                let bytes = eat_lenenc_str(buf)?;
+                if bytes.len() > 8 {
+                    return Err(invalid_data("BIT value is too long"));
+                }
+                b.append_value(bytes.iter().fold(0, |acc, x| (acc << 8) | *x as u64));
+            }
+            Self::UInt64(b) => b.append_value(buf.checked_eat_u64_le().ok_or_else(eof)?),
+            Self::Float32(b) => b.append_value(buf.checked_eat_f32_le().ok_or_else(eof)?),
+            Self::Float64(b) => b.append_value(buf.checked_eat_f64_le().ok_or_else(eof)?),
+            Self::Decimal128(b, scale) => {
+                b.append_value(parse_decimal(eat_lenenc_str(buf)?, *scale)?)
+            }
+            Self::Date32(b) => match parse_datetime(buf)? {
+                Some((days, _)) => b.append_value(days as i32),
+                None => b.append_null(),
+            },
+            Self::TimestampSecond(b) => match parse_datetime(buf)? {
+                Some((days, micros)) => b.append_value(days * 86_400 + micros / 1_000_000),
+                None => b.append_null(),
+            },
+            Self::TimestampMillisecond(b) => match parse_datetime(buf)? {
+                Some((days, micros)) => b.append_value(days * 86_400_000 + micros / 1_000),
+                None => b.append_null(),
+            },
+            Self::TimestampMicrosecond(b) => match parse_datetime(buf)? {
+                Some((days, micros)) => b.append_value(days * 86_400_000_000 + micros),
+                None => b.append_null(),
+            },
+            Self::Duration(b) => b.append_value(parse_time(buf)?),
+            Self::Utf8(b) => b.append_value(eat_utf8(buf)?),
+            Self::Binary(b) => b.append_value(eat_lenenc_str(buf)?),
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::Null(b) => ArrayBuilder::finish(b),
+            Self::Boolean(b) => ArrayBuilder::finish(b),
+            Self::Int8(b) => ArrayBuilder::finish(b),
+            Self::Int16(b) => ArrayBuilder::finish(b),
+            Self::Int32(b) => ArrayBuilder::finish(b),
+            Self::Int64(b) => ArrayBuilder::finish(b),
+            Self::UInt8(b) => ArrayBuilder::finish(b),
+            Self::UInt16(b) => ArrayBuilder::finish(b),
+            Self::UInt32(b) => ArrayBuilder::finish(b),
+            Self::UInt64(b) => ArrayBuilder::finish(b),
+            Self::Float32(b) => ArrayBuilder::finish(b),
+            Self::Float64(b) => ArrayBuilder::finish(b),
+            Self::Decimal128(b, _) => ArrayBuilder::finish(b),
+            Self::Date32(b) => ArrayBuilder::finish(b),
+            Self::TimestampSecond(b) => ArrayBuilder::finish(b),
+            Self::TimestampMillisecond(b) => ArrayBuilder::finish(b),
+            Self::TimestampMicrosecond(b) => ArrayBuilder::finish(b),
+            Self::Duration(b) => ArrayBuilder::finish(b),
+            Self::Utf8(b) => ArrayBuilder::finish(b),
+            Self::Binary(b) => ArrayBuilder::finish(b),
+        }
+    }
+}
+
+/// Set of column builders for a single record batch.
+struct BatchBuilder {
+    columns: Arc<[Column]>,
+    builders: Vec<ColumnBuilder>,
+}
+
+impl BatchBuilder {
+    fn new(schema: &Schema, columns: Arc<[Column]>, capacity: usize) -> io::Result<Self> {
+        let builders = schema
+            .fields()
+            .iter()
+            .map(|field| ColumnBuilder::new(field.data_type(), capacity))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self { columns, builders })
+    }
+
+    /// Decodes a binary protocol row packet.
+    fn append_row(&mut self, packet: &[u8]) -> io::Result<()> {
+        let mut buf = ParseBuf(packet);
+        // packet header
+        buf.checked_skip(1)
+            .then_some(())
+            .ok_or_else(unexpected_eof)?;
+        // null bitmap with two bits offset
+        let bitmap = buf
+            .checked_eat((self.columns.len() + 7 + 2) / 8)
+            .ok_or_else(unexpected_eof)?;
+
+        for (i, (column, builder)) in self.columns.iter().zip(&mut self.builders).enumerate() {
+            let bit = i + 2;
+            if bitmap[bit / 8] & (1 << (bit % 8)) != 0 {
+                builder.append_null();
+            } else {
+                builder.append_value(column, &mut buf)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, schema: &SchemaRef) -> io::Result<RecordBatch> {
+        let arrays = self
+            .builders
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect();
+        RecordBatch::try_new(schema.clone(), arrays).map_err(invalid_data)
+    }
+}
+
+/// Iterator over arrow record batches of a result set
+/// (see [`QueryResult::into_record_batches`]).
+///
+/// It covers the result set, that was current at the moment of creation.
+/// The rest of the query result is dropped along with this iterator.
+#[cfg_attr(docsrs, doc(cfg(feature = "arrow")))]
+pub struct RecordBatches<'c, 't, 'tc> {
+    result: QueryResult<'c, 't, 'tc, Binary>,
+    schema: SchemaRef,
+    columns: Arc<[Column]>,
+    batch_size: usize,
+    set_index: usize,
+    done: bool,
+}
+
+impl RecordBatches<'_, '_, '_> {
+    /// Returns the arrow schema of produced batches.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Iterator for RecordBatches<'_, '_, '_> {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut batch = match BatchBuilder::new(&self.schema, self.columns.clone(), self.batch_size)
+        {
+            Ok(batch) => batch,
+            Err(err) => {
+                self.done = true;
+                return Some(Err(err.into()));
+            }
+        };
+
+        let mut num_rows = 0;
+        while num_rows < self.batch_size {
+            if self.result.set_index() != self.set_index {
+                self.done = true;
+                break;
+            }
+
+            match self.result.next_with(|conn, _| conn.next_row_packet()) {
+                Some(Ok(packet)) => {
+                    if let Err(err) = batch.append_row(&packet) {
+                        self.done = true;
+                        return Some(Err(err.into()));
+                    }
+                    num_rows += 1;
+                }
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+
+        if num_rows == 0 {
+            None
+        } else {
+            Some(batch.finish(&self.schema).map_err(Into::into))
+        }
+    }
+}
+
+impl<'c, 't, 'tc> QueryResult<'c, 't, 'tc, Binary> {
+    /// Turns the current result set into an iterator over arrow record batches
+    /// of at most `batch_size` rows.
+    ///
+    /// Rows are decoded straight into arrow builders, without intermediate `Row` values.
+    /// Schema is derived from the column definitions (see [`crate::SetColumns::arrow_schema`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let pool = Pool::new(get_opts())?;
+    /// let mut conn = pool.get_conn()?;
+    /// let batches = conn
+    ///     .exec_iter("SELECT 1 UNION ALL SELECT 2 UNION ALL SELECT 3", ())?
+    ///     .into_record_batches(2)
+    ///     .collect::<Result<Vec<_>>>()?;
+    /// assert_eq!(batches.len(), 2);
+    /// assert_eq!(batches[0].num_rows(), 2);
+    /// assert_eq!(batches[1].num_rows(), 1);
+    /// # });
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "arrow")))]
+    pub fn into_record_batches(self, batch_size: usize) -> RecordBatches<'c, 't, 'tc> {
+        let columns: Arc<[Column]> = self.columns().as_ref().into();
+        RecordBatches {
+            schema: Arc::new(columns_to_schema(&columns)),
+            set_index: self.set_index(),
+            batch_size: batch_size.max(1),
+            done: columns.is_empty(),
+            result: self,
+            columns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arrow_array::{
+        cast::AsArray,
+        types::{Date32Type, Decimal128Type, TimestampMicrosecondType, UInt64Type},
+    };
+    use arrow_schema::{DataType, TimeUnit};
+    use mysql_common::constants::{ColumnFlags, ColumnType};
+
+    use std::sync::Arc;
+
+    use super::{columns_to_schema, parse_decimal, BatchBuilder};
+    use crate::Column;
+
+    #[test]
+    fn should_map_columns_to_arrow_schema() {
+        let columns = [
+            Column::new(ColumnType::MYSQL_TYPE_LONGLONG)
+                .with_name(b"id")
+                .with_flags(ColumnFlags::UNSIGNED_FLAG | ColumnFlags::NOT_NULL_FLAG),
+            Column::new(ColumnType::MYSQL_TYPE_NEWDECIMAL)
+                .with_name(b"price")
+                .with_column_length(12)
+                .with_decimals(2),
+            Column::new(ColumnType::MYSQL_TYPE_DATETIME)
+                .with_name(b"created")
+                .with_decimals(3),
+            Column::new(ColumnType::MYSQL_TYPE_JSON).with_name(b"doc"),
+            Column::new(ColumnType::MYSQL_TYPE_BIT)
+                .with_name(b"flag")
+                .with_column_length(1),
+            Column::new(ColumnType::MYSQL_TYPE_BLOB)
+                .with_name(b"data")
+                .with_character_set(63),
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING)
+                .with_name(b"name")
+                .with_character_set(45),
+        ];
+
+        let schema = columns_to_schema(&columns);
+        let types = schema
+            .fields()
+            .iter()
+            .map(|f| f.data_type().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                DataType::UInt64,
+                DataType::Decimal128(10, 2),
+                DataType::Timestamp(TimeUnit::Millisecond, None),
+                DataType::Utf8,
+                DataType::Boolean,
+                DataType::Binary,
+                DataType::Utf8,
+            ]
+        );
+        assert!(!schema.field(0).is_nullable());
+        assert!(schema.field(1).is_nullable());
+        assert_eq!(
+            schema.field(3).metadata().get("ARROW:extension:name"),
+            Some(&"arrow.json".to_string())
+        );
+    }
+
+    #[test]
+    fn should_parse_decimals() {
+        assert_eq!(parse_decimal(b"123.45", 2).unwrap(), 12345);
+        assert_eq!(parse_decimal(b"-0.5", 3).unwrap(), -500);
+        assert_eq!(parse_decimal(b"42", 0).unwrap(), 42);
+        assert!(parse_decimal(b"1.234", 2).is_err());
+        assert!(parse_decimal(b"1x", 0).is_err());
+    }
+
+    #[test]
+    fn should_decode_binary_rows() {
+        let columns: Arc<[Column]> = vec![
+            Column::new(ColumnType::MYSQL_TYPE_LONGLONG).with_flags(ColumnFlags::UNSIGNED_FLAG),
+            Column::new(ColumnType::MYSQL_TYPE_NEWDECIMAL)
+                .with_column_length(6)
+                .with_decimals(2),
+            Column::new(ColumnType::MYSQL_TYPE_DATE),
+            Column::new(ColumnType::MYSQL_TYPE_DATETIME2).with_decimals(6),
+            Column::new(ColumnType::MYSQL_TYPE_BIT).with_column_length(16),
+        ]
+        .into();
+        let schema = Arc::new(columns_to_schema(&columns));
+
+        let mut batch = BatchBuilder::new(&schema, columns.clone(), 2).unwrap();
+
+        let mut row = vec![0x00, 0b0000_0000];
+        row.extend_from_slice(&7_u64.to_le_bytes());
+        row.extend_from_slice(b"\x06-12.50");
+        row.extend_from_slice(&[4, 0xd0, 0x07, 1, 2]);
+        row.extend_from_slice(&[11, 0xd0, 0x07, 1, 2, 3, 4, 5, 6, 0, 0, 0]);
+        row.extend_from_slice(&[2, 0x01, 0x02]);
+        batch.append_row(&row).unwrap();
+
+        // every column is NULL
+        batch.append_row(&[0x00, 0b0111_1100]).unwrap();
+
+        let batch = batch.finish(&schema).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.column(0).as_primitive::<UInt64Type>().value(0), 7);
+        assert_eq!(
+            batch.column(1).as_primitive::<Decimal128Type>().value(0),
+            -1250
+        );
+        assert_eq!(batch.column(2).as_primitive::<Date32Type>().value(0), 10958);
+        assert_eq!(
+            batch
+                .column(3)
+                .as_primitive::<TimestampMicrosecondType>()
+                .value(0),
+            946_782_245_000_006
+        );
+        assert_eq!(batch.column(4).as_primitive::<UInt64Type>().value(0), 258);
+        assert!((0..5).all(|i| batch.column(i).is_null(1)));
+    }
+}
@@ -0,0 +1,370 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    constants::{ColumnFlags, ColumnType},
+    io::ParseBuf,
+};
+
+use std::{io, ops::Range};
+
+use crate::{Column, Value};
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "can't parse row packet")
+}
+
+/// Protocol of a row packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RowKind {
+    Text,
+    Binary,
+}
+
+/// Borrowed value of a row (see [`RowRef`]).
+///
+/// It mirrors [`Value`] except that byte strings are borrowed from the row packet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueRef<'a> {
+    NULL,
+    Bytes(&'a [u8]),
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    /// year, month, day, hour, minutes, seconds, micro seconds
+    Date(u16, u8, u8, u8, u8, u8, u32),
+    /// is negative, days, hours, minutes, seconds, micro seconds
+    Time(bool, u32, u8, u8, u8, u32),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Returns the byte string, if this is a `ValueRef::Bytes`.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            ValueRef::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the string, if this is a `ValueRef::Bytes` with a valid utf8 content.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    /// Returns `true` if this is a `ValueRef::NULL`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, ValueRef::NULL)
+    }
+
+    /// Converts this value to an owned [`Value`].
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ValueRef::NULL => Value::NULL,
+            ValueRef::Bytes(bytes) => Value::Bytes(bytes.to_vec()),
+            ValueRef::Int(x) => Value::Int(x),
+            ValueRef::UInt(x) => Value::UInt(x),
+            ValueRef::Float(x) => Value::Float(x),
+            ValueRef::Double(x) => Value::Double(x),
+            ValueRef::Date(y, m, d, h, i, s, u) => Value::Date(y, m, d, h, i, s, u),
+            ValueRef::Time(n, d, h, i, s, u) => Value::Time(n, d, h, i, s, u),
+        }
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        value.to_value()
+    }
+}
+
+/// Row that borrows its values from the underlying packet buffer.
+///
+/// Use it to avoid per-cell allocations (see [`crate::ResultSet::for_each_ref`]).
+#[derive(Debug, Clone, Copy)]
+pub struct RowRef<'a> {
+    kind: RowKind,
+    columns: &'a [Column],
+    packet: &'a [u8],
+    ranges: &'a [Option<Range<usize>>],
+}
+
+impl<'a> RowRef<'a> {
+    /// Parses the given row packet.
+    ///
+    /// `ranges` is a scratch buffer, that is reused between rows.
+    pub(crate) fn parse(
+        kind: RowKind,
+        columns: &'a [Column],
+        packet: &'a [u8],
+        ranges: &'a mut Vec<Option<Range<usize>>>,
+    ) -> io::Result<Self> {
+        ranges.clear();
+        match kind {
+            RowKind::Text => parse_text_ranges(columns, packet, ranges)?,
+            RowKind::Binary => parse_binary_ranges(columns, packet, ranges)?,
+        }
+        Ok(Self {
+            kind,
+            columns,
+            packet,
+            ranges,
+        })
+    }
+
+    /// Returns the number of columns.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns `true` if the row has no columns.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns columns of this row.
+    pub fn columns_ref(&self) -> &'a [Column] {
+        self.columns
+    }
+
+    /// Returns an index of a column by its name.
+    pub fn column_index<U: AsRef<str>>(&self, name: U) -> Option<usize> {
+        let name = name.as_ref().as_bytes();
+        self.columns.iter().position(|col| col.name_ref() == name)
+    }
+
+    /// Returns `true` if the value at the given index is `NULL`.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    pub fn is_null(&self, index: usize) -> Option<bool> {
+        self.ranges.get(index).map(Option::is_none)
+    }
+
+    /// Returns the raw bytes of the value at the given index.
+    ///
+    /// Returns `None` if the value is `NULL` or if the index is out of bounds.
+    /// Note that binary protocol values of non-string columns are in the wire format.
+    pub fn get_bytes(&self, index: usize) -> Option<&'a [u8]> {
+        let range = self.ranges.get(index)?.clone()?;
+        Some(&self.packet[range])
+    }
+
+    /// Returns the value at the given index as a string.
+    ///
+    /// Returns `None` if the value is `NULL`, is not a valid utf8 string, or if the index is out
+    /// of bounds.
+    pub fn get_str(&self, index: usize) -> Option<&'a str> {
+        self.get(index).and_then(|value| value.as_str())
+    }
+
+    /// Returns the value at the given index.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    pub fn get(&self, index: usize) -> Option<ValueRef<'a>> {
+        let range = self.ranges.get(index)?.clone();
+        let bytes = match range {
+            Some(range) => &self.packet[range],
+            None => return Some(ValueRef::NULL),
+        };
+        Some(match self.kind {
+            RowKind::Text => ValueRef::Bytes(bytes),
+            RowKind::Binary => decode_binary(&self.columns[index], bytes),
+        })
+    }
+
+    /// Converts this row to an owned [`crate::Row`].
+    pub fn to_row(&self) -> crate::Row {
+        let values = (0..self.len())
+            .map(|i| self.get(i).map(|v| v.to_value()).unwrap_or(Value::NULL))
+            .collect();
+        mysql_common::row::new_row(values, self.columns.into())
+    }
+}
+
+fn parse_text_ranges(
+    columns: &[Column],
+    packet: &[u8],
+    ranges: &mut Vec<Option<Range<usize>>>,
+) -> io::Result<()> {
+    let mut buf = ParseBuf(packet);
+    for _ in columns {
+        if buf.0.first() == Some(&0xfb) {
+            buf.skip(1);
+            ranges.push(None);
+        } else {
+            let value = buf.checked_eat_lenenc_str().ok_or_else(unexpected_eof)?;
+            let end = packet.len() - buf.len();
+            ranges.push(Some(end - value.len()..end));
+        }
+    }
+    Ok(())
+}
+
+fn parse_binary_ranges(
+    columns: &[Column],
+    packet: &[u8],
+    ranges: &mut Vec<Option<Range<usize>>>,
+) -> io::Result<()> {
+    use ColumnType::*;
+
+    let mut buf = ParseBuf(packet);
+    // packet header
+    if !buf.checked_skip(1) {
+        return Err(unexpected_eof());
+    }
+    // null bitmap with two bits offset
+    let bitmap = buf
+        .checked_eat((columns.len() + 7 + 2) / 8)
+        .ok_or_else(unexpected_eof)?;
+
+    for (i, column) in columns.iter().enumerate() {
+        let bit = i + 2;
+        if bitmap[bit / 8] & (1 << (bit % 8)) != 0 {
+            ranges.push(None);
+            continue;
+        }
+
+        let value = match column.column_type() {
+            MYSQL_TYPE_NULL => Some(&[][..]),
+            MYSQL_TYPE_TINY => buf.checked_eat(1),
+            MYSQL_TYPE_SHORT | MYSQL_TYPE_YEAR => buf.checked_eat(2),
+            MYSQL_TYPE_LONG | MYSQL_TYPE_INT24 | MYSQL_TYPE_FLOAT => buf.checked_eat(4),
+            MYSQL_TYPE_LONGLONG | MYSQL_TYPE_DOUBLE => buf.checked_eat(8),
+            MYSQL_TYPE_TIMESTAMP | MYSQL_TYPE_DATE | MYSQL_TYPE_DATETIME | MYSQL_TYPE_TIME => {
+                buf.checked_eat_u8_str()
+            }
+            _ => buf.checked_eat_lenenc_str(),
+        }
+        .ok_or_else(unexpected_eof)?;
+
+        let end = packet.len() - buf.len();
+        ranges.push(Some(end - value.len()..end));
+    }
+
+    Ok(())
+}
+
+/// Decodes a binary protocol value (without a length prefix).
+fn decode_binary<'a>(column: &Column, bytes: &'a [u8]) -> ValueRef<'a> {
+    use ColumnType::*;
+
+    let unsigned = column.flags().contains(ColumnFlags::UNSIGNED_FLAG);
+    let mut buf = ParseBuf(bytes);
+
+    match column.column_type() {
+        MYSQL_TYPE_NULL => ValueRef::NULL,
+        MYSQL_TYPE_TINY if unsigned => ValueRef::Int(buf.eat_u8() as i64),
+        MYSQL_TYPE_TINY => ValueRef::Int(buf.eat_i8() as i64),
+        MYSQL_TYPE_SHORT | MYSQL_TYPE_YEAR if unsigned => ValueRef::Int(buf.eat_u16_le() as i64),
+        MYSQL_TYPE_SHORT | MYSQL_TYPE_YEAR => ValueRef::Int(buf.eat_i16_le() as i64),
+        MYSQL_TYPE_LONG | MYSQL_TYPE_INT24 if unsigned => ValueRef::Int(buf.eat_u32_le() as i64),
+        MYSQL_TYPE_LONG | MYSQL_TYPE_INT24 => ValueRef::Int(buf.eat_i32_le() as i64),
+        MYSQL_TYPE_LONGLONG if unsigned => {
+            let x = buf.eat_u64_le();
+            i64::try_from(x)
+                .map(ValueRef::Int)
+                .unwrap_or(ValueRef::UInt(x))
+        }
+        MYSQL_TYPE_LONGLONG => ValueRef::Int(buf.eat_i64_le()),
+        MYSQL_TYPE_FLOAT => ValueRef::Float(buf.eat_f32_le()),
+        MYSQL_TYPE_DOUBLE => ValueRef::Double(buf.eat_f64_le()),
+        MYSQL_TYPE_TIMESTAMP | MYSQL_TYPE_DATE | MYSQL_TYPE_DATETIME => {
+            let (mut y, mut m, mut d, mut h, mut i, mut s, mut u) = (0, 0, 0, 0, 0, 0, 0);
+            if bytes.len() >= 4 {
+                y = buf.eat_u16_le();
+                m = buf.eat_u8();
+                d = buf.eat_u8();
+            }
+            if bytes.len() >= 7 {
+                h = buf.eat_u8();
+                i = buf.eat_u8();
+                s = buf.eat_u8();
+            }
+            if bytes.len() == 11 {
+                u = buf.eat_u32_le();
+            }
+            ValueRef::Date(y, m, d, h, i, s, u)
+        }
+        MYSQL_TYPE_TIME => {
+            let (mut n, mut d, mut h, mut i, mut s, mut u) = (false, 0, 0, 0, 0, 0);
+            if bytes.len() >= 8 {
+                n = buf.eat_u8() == 1;
+                d = buf.eat_u32_le();
+                h = buf.eat_u8();
+                i = buf.eat_u8();
+                s = buf.eat_u8();
+            }
+            if bytes.len() == 12 {
+                u = buf.eat_u32_le();
+            }
+            ValueRef::Time(n, d, h, i, s, u)
+        }
+        _ => ValueRef::Bytes(bytes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::constants::{ColumnFlags, ColumnType};
+
+    use super::{RowKind, RowRef, ValueRef};
+    use crate::{Column, Value};
+
+    #[test]
+    fn should_parse_text_row_ref() {
+        let columns = [
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_name(b"a"),
+            Column::new(ColumnType::MYSQL_TYPE_LONG).with_name(b"b"),
+            Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_name(b"c"),
+        ];
+        let packet = b"\x03foo\xfb\x0242";
+        let mut ranges = Vec::new();
+        let row = RowRef::parse(RowKind::Text, &columns, packet, &mut ranges).unwrap();
+
+        assert_eq!(row.len(), 3);
+        assert_eq!(row.get_str(0), Some("foo"));
+        assert_eq!(row.get(1), Some(ValueRef::NULL));
+        assert_eq!(row.get_bytes(2), Some(&b"42"[..]));
+        assert_eq!(row.column_index("c"), Some(2));
+        assert_eq!(row.get(3), None);
+        assert_eq!(
+            row.to_row().unwrap(),
+            vec![Value::from("foo"), Value::NULL, Value::from("42")]
+        );
+    }
+
+    #[test]
+    fn should_parse_binary_row_ref() {
+        let columns = [
+            Column::new(ColumnType::MYSQL_TYPE_LONGLONG).with_flags(ColumnFlags::UNSIGNED_FLAG),
+            Column::new(ColumnType::MYSQL_TYPE_TINY),
+            Column::new(ColumnType::MYSQL_TYPE_BLOB),
+            Column::new(ColumnType::MYSQL_TYPE_DATETIME),
+            Column::new(ColumnType::MYSQL_TYPE_DOUBLE),
+        ];
+        let mut packet = vec![0x00, 0b0000_1000];
+        packet.extend_from_slice(&u64::MAX.to_le_bytes());
+        packet.extend_from_slice(b"\x03bar");
+        packet.extend_from_slice(&[7, 0xd0, 0x07, 1, 2, 3, 4, 5]);
+        packet.extend_from_slice(&1.5_f64.to_le_bytes());
+
+        let mut ranges = Vec::new();
+        let row = RowRef::parse(RowKind::Binary, &columns, &packet, &mut ranges).unwrap();
+
+        assert_eq!(row.get(0), Some(ValueRef::UInt(u64::MAX)));
+        assert_eq!(row.is_null(1), Some(true));
+        assert_eq!(row.get_str(2), Some("bar"));
+        assert_eq!(row.get(3), Some(ValueRef::Date(2000, 1, 2, 3, 4, 5, 0)));
+        assert_eq!(row.get(4), Some(ValueRef::Double(1.5)));
+
+        // truncated packet
+        let mut ranges = Vec::new();
+        assert!(RowRef::parse(RowKind::Binary, &columns, &packet[..12], &mut ranges).is_err());
+    }
+}
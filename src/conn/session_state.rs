@@ -0,0 +1,296 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Tracking of session state changes made by a pool user (see
+//! [`crate::PoolOpts::with_restore_session_state`]).
+
+use mysql_common::packets::session_state_change::SessionStateChange;
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::Value;
+
+/// Enables server-side trackers used by [`SessionStateTracker`].
+pub(crate) const ENABLE_TRACKERS: &str = "SET SESSION session_track_system_variables = '*', \
+    session_track_schema = ON, session_track_state_change = ON";
+
+/// Enables tracking of the default schema only.
+pub(crate) const TRACK_SCHEMA: &str = "SET SESSION session_track_schema = ON";
+
+/// Statements, that will restore the session state.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Restore {
+    /// `SET SESSION ...` statement, if any variable was changed.
+    pub(crate) variables: Option<String>,
+    /// Schema to switch to, if schema was changed.
+    pub(crate) schema: Option<String>,
+}
+
+/// Records session state changes reported via `CLIENT_SESSION_TRACK`.
+///
+/// Initial values of variables are given by [`SessionStateTracker::set_initial_variables`]
+/// once the connection is set up. Changes reported before [`SessionStateTracker::start`]
+/// (i.e. during the connection setup) update the initial state.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SessionStateTracker {
+    recording: bool,
+    initial_schema: Option<String>,
+    initial_variables: HashMap<String, Vec<u8>>,
+    changed_schema: bool,
+    changed_variables: BTreeSet<String>,
+    /// Something that couldn't be restored selectively was changed (e.g. a user variable).
+    unrestorable: bool,
+}
+
+impl SessionStateTracker {
+    pub(crate) fn new(initial_schema: Option<String>) -> Self {
+        Self {
+            initial_schema,
+            ..Default::default()
+        }
+    }
+
+    /// Sets initial values of session variables (e.g. the result of `SHOW SESSION VARIABLES`).
+    pub(crate) fn set_initial_variables<I>(&mut self, variables: I)
+    where
+        I: IntoIterator<Item = (String, Vec<u8>)>,
+    {
+        self.initial_variables.extend(variables);
+    }
+
+    /// Starts recording of changes.
+    pub(crate) fn start(&mut self) {
+        self.recording = true;
+    }
+
+    /// Handles changes reported by a single OK packet.
+    ///
+    /// `is_text_query` should be `true` for `COM_QUERY` responses – a state change without
+    /// any variable or schema change is only treated as an unrestorable change for text queries,
+    /// because preparing a statement is a state change by itself.
+    pub(crate) fn handle<'a, I>(&mut self, changes: I, is_text_query: bool)
+    where
+        I: IntoIterator<Item = SessionStateChange<'a>>,
+    {
+        let mut state_changed = false;
+        let mut restorable_change = false;
+        for change in changes {
+            match change {
+                SessionStateChange::SystemVariables(vars) => {
+                    restorable_change = true;
+                    for var in vars {
+                        let name = var.name_str().into_owned();
+                        if !self.recording {
+                            self.initial_variables
+                                .insert(name, var.value_bytes().to_vec());
+                        } else if is_valid_name(&name) {
+                            self.changed_variables.insert(name);
+                        } else {
+                            self.unrestorable = true;
+                        }
+                    }
+                }
+                SessionStateChange::Schema(schema) => {
+                    restorable_change = true;
+                    let schema = schema.as_str().into_owned();
+                    if !self.recording {
+                        self.initial_schema = Some(schema);
+                    } else {
+                        self.changed_schema = self.initial_schema.as_ref() != Some(&schema);
+                    }
+                }
+                SessionStateChange::IsTracked(changed) => state_changed |= changed,
+                _ => (),
+            }
+        }
+        if self.recording && is_text_query && state_changed && !restorable_change {
+            self.unrestorable = true;
+        }
+    }
+
+    /// Returns `true` if something was changed since the last restore.
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.unrestorable || self.changed_schema || !self.changed_variables.is_empty()
+    }
+
+    /// Takes recorded changes and returns statements that will restore the initial state
+    /// (`None` means that the state couldn't be restored selectively).
+    pub(crate) fn take_restore(&mut self, no_backslash_escape: bool) -> Option<Restore> {
+        let unrestorable = std::mem::take(&mut self.unrestorable);
+        let changed_schema = std::mem::take(&mut self.changed_schema);
+        let changed_variables = std::mem::take(&mut self.changed_variables);
+
+        if unrestorable || (changed_schema && self.initial_schema.is_none()) {
+            return None;
+        }
+
+        // `DEFAULT` is the global value, that might differ from the initial one
+        // (e.g. for `character_set_*` variables set during the handshake),
+        // so a variable with unknown initial value requires a reset
+        if changed_variables
+            .iter()
+            .any(|name| !self.initial_variables.contains_key(name))
+        {
+            return None;
+        }
+
+        let variables = if changed_variables.is_empty() {
+            None
+        } else {
+            let assignments = changed_variables
+                .iter()
+                .filter_map(|name| {
+                    let value = self.initial_variables.get(name)?;
+                    Some(format!(
+                        "{} = {}",
+                        name,
+                        literal(value, no_backslash_escape)
+                    ))
+                })
+                .collect::<Vec<_>>();
+            Some(format!("SET SESSION {}", assignments.join(", ")))
+        };
+
+        Some(Restore {
+            variables,
+            schema: changed_schema
+                .then(|| self.initial_schema.clone())
+                .flatten(),
+        })
+    }
+
+    /// Temporarily stops recording (changes will update the initial state).
+    pub(crate) fn pause(&mut self) {
+        self.recording = false;
+    }
+}
+
+/// Returns `true` if the name could be used in a `SET` statement as is.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'.')
+}
+
+/// Converts a tracked value into an SQL literal (numbers are kept unquoted).
+fn literal(value: &[u8], no_backslash_escape: bool) -> String {
+    let is_number = !value.is_empty()
+        && value
+            .iter()
+            .enumerate()
+            .all(|(i, b)| b.is_ascii_digit() || (i == 0 && *b == b'-') || *b == b'.')
+        && value.iter().filter(|b| **b == b'.').count() <= 1
+        && value.iter().any(u8::is_ascii_digit);
+    if is_number {
+        String::from_utf8_lossy(value).into_owned()
+    } else {
+        Value::Bytes(value.to_vec()).as_sql(no_backslash_escape)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::packets::session_state_change::{Schema, SessionStateChange, SystemVariable};
+
+    use super::{literal, Restore, SessionStateTracker};
+
+    fn vars(vars: &[(&'static str, &'static str)]) -> SessionStateChange<'static> {
+        SessionStateChange::SystemVariables(
+            vars.iter()
+                .map(|(name, value)| SystemVariable::new(name.as_bytes(), value.as_bytes()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn should_restore_variables_and_schema() {
+        let mut tracker = SessionStateTracker::new(Some("db".into()));
+        tracker.set_initial_variables(vec![
+            ("sql_mode".into(), b"STRICT_TRANS_TABLES".to_vec()),
+            ("time_zone".into(), b"UTC".to_vec()),
+        ]);
+        // changes made by init commands
+        tracker.handle(
+            vec![
+                vars(&[("time_zone", "SYSTEM"), ("wait_timeout", "28800")]),
+                SessionStateChange::IsTracked(true),
+            ],
+            true,
+        );
+        tracker.start();
+        assert!(!tracker.is_dirty());
+
+        tracker.handle(
+            vec![
+                vars(&[("wait_timeout", "10"), ("time_zone", "+01:00")]),
+                SessionStateChange::IsTracked(true),
+            ],
+            true,
+        );
+        tracker.handle(vec![vars(&[("sql_mode", "")])], true);
+        tracker.handle(
+            vec![
+                SessionStateChange::Schema(Schema::new(&b"other"[..])),
+                SessionStateChange::IsTracked(true),
+            ],
+            true,
+        );
+        assert!(tracker.is_dirty());
+
+        assert_eq!(
+            tracker.take_restore(false),
+            Some(Restore {
+                variables: Some(
+                    "SET SESSION sql_mode = 'STRICT_TRANS_TABLES', time_zone = 'SYSTEM', \
+                    wait_timeout = 28800"
+                        .into()
+                ),
+                schema: Some("db".into()),
+            })
+        );
+        assert!(!tracker.is_dirty());
+        assert_eq!(tracker.take_restore(false), Some(Restore::default()));
+    }
+
+    #[test]
+    fn should_detect_unrestorable_changes() {
+        let mut tracker = SessionStateTracker::new(None);
+        tracker.start();
+
+        // e.g. `SET @foo = 1`
+        tracker.handle(vec![SessionStateChange::IsTracked(true)], true);
+        assert!(tracker.is_dirty());
+        assert_eq!(tracker.take_restore(false), None);
+
+        // state changes reported for prepared statements are ignored
+        tracker.handle(vec![SessionStateChange::IsTracked(true)], false);
+        assert!(!tracker.is_dirty());
+
+        // initial value of a variable is unknown
+        tracker.handle(vec![vars(&[("character_set_client", "latin1")])], true);
+        assert!(tracker.is_dirty());
+        assert_eq!(tracker.take_restore(false), None);
+
+        // there is no way to return to the "no schema" state
+        tracker.handle(
+            vec![SessionStateChange::Schema(Schema::new(&b"db"[..]))],
+            true,
+        );
+        assert_eq!(tracker.take_restore(false), None);
+    }
+
+    #[test]
+    fn should_quote_non_numeric_values() {
+        assert_eq!(literal(b"42", false), "42");
+        assert_eq!(literal(b"-0.5", false), "-0.5");
+        assert_eq!(literal(b"1.2.3", false), "'1.2.3'");
+        assert_eq!(literal(b"1-2", false), "'1-2'");
+        assert_eq!(literal(b"ON", false), "'ON'");
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::Params;
+
+/// Statistics of a connection (see [`crate::Conn::stats`]).
+///
+/// Counters are accumulated over the whole lifetime of a connection,
+/// i.e. they survive [`crate::Conn::reset`] and returns to a pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ConnStats {
+    /// Number of commands sent to the server (including commands issued by the driver itself).
+    pub commands_sent: u64,
+    /// Number of request-response round trips.
+    pub round_trips: u64,
+    /// Number of bytes written to the stream (after compression, if enabled).
+    pub bytes_written: u64,
+    /// Number of bytes read from the stream (before decompression, if enabled).
+    pub bytes_read: u64,
+    /// Number of payload bytes written (i.e. uncompressed and without packet headers).
+    pub payload_bytes_written: u64,
+    /// Number of payload bytes read (i.e. uncompressed and without packet headers).
+    pub payload_bytes_read: u64,
+    /// Number of result set rows received.
+    pub rows_received: u64,
+    /// Number of statements prepared on the server side (`COM_STMT_PREPARE`).
+    pub prepares: u64,
+    /// Number of statements taken from the statement cache instead of being prepared.
+    pub prepare_cache_hits: u64,
+    /// Time spent writing to and reading from the stream
+    /// (this includes time spent waiting for the server).
+    pub io_time: Duration,
+}
+
+/// Query reported by the client-side slow query log
+/// (see [`crate::OptsBuilder::slow_query_threshold`]).
+#[derive(Debug, Clone, Copy)]
+pub struct SlowQuery<'a> {
+    pub(crate) query: &'a str,
+    pub(crate) params: &'a Params,
+    pub(crate) elapsed: Duration,
+    pub(crate) warnings: u16,
+    pub(crate) connection_id: u32,
+}
+
+impl SlowQuery<'_> {
+    /// Text of the query (or of the executed statement).
+    pub fn query(&self) -> &str {
+        self.query
+    }
+
+    /// Statement parameters ([`Params::Empty`] for text queries).
+    pub fn params(&self) -> &Params {
+        self.params
+    }
+
+    /// Time elapsed since the query was sent till the whole response was read.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Number of warnings reported by the server (see [`crate::Conn::warnings`]).
+    pub fn warnings(&self) -> u16 {
+        self.warnings
+    }
+
+    /// Identifier of the connection, that ran the query.
+    pub fn connection_id(&self) -> u32 {
+        self.connection_id
+    }
+}
+
+/// Client-side slow query log (see [`crate::OptsBuilder::slow_query_threshold`]).
+#[derive(Clone)]
+pub(crate) struct SlowQueryLog {
+    pub(crate) threshold: Duration,
+    pub(crate) handler: Arc<dyn Fn(&SlowQuery<'_>) + Send + Sync>,
+}
+
+impl PartialEq for SlowQueryLog {
+    fn eq(&self, other: &SlowQueryLog) -> bool {
+        self.threshold == other.threshold && Arc::ptr_eq(&self.handler, &other.handler)
+    }
+}
+
+impl Eq for SlowQueryLog {}
+
+impl fmt::Debug for SlowQueryLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlowQueryLog")
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+/// Query, that is being timed by the slow query log.
+#[derive(Debug)]
+pub(crate) struct PendingQuery {
+    pub(crate) query: String,
+    pub(crate) params: Params,
+    pub(crate) start: Instant,
+}
@@ -6,7 +6,12 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use mysql_common::{io::ParseBuf, packets::StmtPacket, proto::MyDeserialize};
+use mysql_common::{
+    constants::{ColumnFlags, ColumnType},
+    io::ParseBuf,
+    packets::StmtPacket,
+    proto::MyDeserialize,
+};
 
 use std::{borrow::Cow, io, sync::Arc};
 
@@ -14,10 +19,12 @@ use crate::{prelude::*, Column, Result};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct InnerStmt {
-    columns: Option<Vec<Column>>,
-    params: Option<Vec<Column>>,
+    columns: Option<Arc<[Column]>>,
+    params: Option<Arc<[Column]>>,
     stmt_packet: StmtPacket,
     connection_id: u32,
+    /// Prepared query (used to re-prepare the statement).
+    query: Option<Arc<Vec<u8>>>,
 }
 
 impl<'de> MyDeserialize<'de> for InnerStmt {
@@ -32,21 +39,36 @@ impl<'de> MyDeserialize<'de> for InnerStmt {
             params: None,
             stmt_packet,
             connection_id,
+            query: None,
         })
     }
 }
 
 impl InnerStmt {
-    pub fn with_params(mut self, params: Option<Vec<Column>>) -> Self {
+    pub fn with_params(mut self, params: Option<Arc<[Column]>>) -> Self {
         self.params = params;
         self
     }
 
-    pub fn with_columns(mut self, columns: Option<Vec<Column>>) -> Self {
+    pub fn with_columns(mut self, columns: Option<Arc<[Column]>>) -> Self {
         self.columns = columns;
         self
     }
 
+    pub fn with_query(mut self, query: Arc<Vec<u8>>) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    pub fn query(&self) -> Option<&Arc<Vec<u8>>> {
+        self.query.as_ref()
+    }
+
+    /// Returns shared columns metadata.
+    pub fn shared_columns(&self) -> Option<Arc<[Column]>> {
+        self.columns.clone()
+    }
+
     pub fn columns(&self) -> &[Column] {
         self.columns.as_ref().map(AsRef::as_ref).unwrap_or(&[])
     }
@@ -129,3 +151,237 @@ impl<T: AsRef<str>> AsStatement for T {
         Ok(Cow::Owned(statement))
     }
 }
+
+/// Statement cache counters (see [`crate::Conn::stmt_cache_stats`]
+/// and [`crate::Pool::stmt_cache_stats`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct StmtCacheStats {
+    /// Number of lookups that found a cached entry.
+    pub hits: u64,
+    /// Number of lookups that didn't find a cached entry.
+    pub misses: u64,
+    /// Number of entries evicted to respect the cache size.
+    pub evictions: u64,
+}
+
+/// Parameter and result set metadata of a query, as returned by [`crate::Conn::describe`].
+///
+/// The statement used to obtain this metadata is closed right away and never reaches
+/// the statement cache. Use [`Queryable::prep`] to get an executable statement.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Describe {
+    pub(crate) params: Vec<Column>,
+    pub(crate) columns: Vec<Column>,
+    pub(crate) named_params: Option<Vec<Vec<u8>>>,
+}
+
+impl Describe {
+    pub(crate) fn new(inner: InnerStmt, named_params: Option<Vec<Vec<u8>>>) -> Self {
+        Self {
+            params: inner.params().to_vec(),
+            columns: inner.columns().to_vec(),
+            named_params,
+        }
+    }
+
+    /// Metadata of statement parameters.
+    ///
+    /// Note that the server does not report real types for parameters,
+    /// so this is mostly useful to get the number of parameters.
+    pub fn params(&self) -> &[Column] {
+        &self.params
+    }
+
+    /// Metadata of result set columns (empty if the query produces no result set).
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Names of named parameters in order of appearance (`None` for positional parameters).
+    pub fn named_params(&self) -> Option<&[Vec<u8>]> {
+        self.named_params.as_deref()
+    }
+
+    /// Returns the number of statement parameters.
+    pub fn num_params(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Returns the number of result set columns.
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+}
+
+/// Collation id of the `binary` character set.
+pub(crate) const BINARY_CHARSET: u16 = 63;
+
+/// Helper methods to inspect [`Column`] metadata.
+pub trait ColumnExt {
+    /// Returns `true` if the column may contain `NULL` (`NOT_NULL_FLAG` is not set).
+    fn is_nullable(&self) -> bool;
+
+    /// Returns `true` if the column is `UNSIGNED`.
+    fn is_unsigned(&self) -> bool;
+
+    /// Returns `true` if the column holds binary data (i.e. uses the `binary` character set).
+    fn is_binary(&self) -> bool;
+
+    /// Returns `true` if the column is a part of the primary key.
+    fn is_primary_key(&self) -> bool;
+
+    /// Returns `true` if the column is `AUTO_INCREMENT`.
+    fn is_auto_increment(&self) -> bool;
+
+    /// Returns `true` for integer, floating point and decimal columns.
+    fn is_numeric(&self) -> bool;
+
+    /// Returns `true` for date and time columns.
+    fn is_temporal(&self) -> bool;
+
+    /// Returns the SQL name of the column type, e.g. `BIGINT UNSIGNED` or `VARBINARY`.
+    fn type_name(&self) -> &'static str;
+}
+
+impl ColumnExt for Column {
+    fn is_nullable(&self) -> bool {
+        !self.flags().contains(ColumnFlags::NOT_NULL_FLAG)
+    }
+
+    fn is_unsigned(&self) -> bool {
+        self.flags().contains(ColumnFlags::UNSIGNED_FLAG)
+    }
+
+    fn is_binary(&self) -> bool {
+        self.character_set() == BINARY_CHARSET
+    }
+
+    fn is_primary_key(&self) -> bool {
+        self.flags().contains(ColumnFlags::PRI_KEY_FLAG)
+    }
+
+    fn is_auto_increment(&self) -> bool {
+        self.flags().contains(ColumnFlags::AUTO_INCREMENT_FLAG)
+    }
+
+    fn is_numeric(&self) -> bool {
+        use ColumnType::*;
+        matches!(
+            self.column_type(),
+            MYSQL_TYPE_TINY
+                | MYSQL_TYPE_SHORT
+                | MYSQL_TYPE_INT24
+                | MYSQL_TYPE_LONG
+                | MYSQL_TYPE_LONGLONG
+                | MYSQL_TYPE_FLOAT
+                | MYSQL_TYPE_DOUBLE
+                | MYSQL_TYPE_DECIMAL
+                | MYSQL_TYPE_NEWDECIMAL
+        )
+    }
+
+    fn is_temporal(&self) -> bool {
+        use ColumnType::*;
+        matches!(
+            self.column_type(),
+            MYSQL_TYPE_DATE
+                | MYSQL_TYPE_NEWDATE
+                | MYSQL_TYPE_TIME
+                | MYSQL_TYPE_TIME2
+                | MYSQL_TYPE_DATETIME
+                | MYSQL_TYPE_DATETIME2
+                | MYSQL_TYPE_TIMESTAMP
+                | MYSQL_TYPE_TIMESTAMP2
+                | MYSQL_TYPE_YEAR
+        )
+    }
+
+    fn type_name(&self) -> &'static str {
+        use ColumnType::*;
+        let unsigned = self.is_unsigned();
+        let binary = self.is_binary();
+        match self.column_type() {
+            MYSQL_TYPE_TINY if unsigned => "TINYINT UNSIGNED",
+            MYSQL_TYPE_TINY => "TINYINT",
+            MYSQL_TYPE_SHORT if unsigned => "SMALLINT UNSIGNED",
+            MYSQL_TYPE_SHORT => "SMALLINT",
+            MYSQL_TYPE_INT24 if unsigned => "MEDIUMINT UNSIGNED",
+            MYSQL_TYPE_INT24 => "MEDIUMINT",
+            MYSQL_TYPE_LONG if unsigned => "INT UNSIGNED",
+            MYSQL_TYPE_LONG => "INT",
+            MYSQL_TYPE_LONGLONG if unsigned => "BIGINT UNSIGNED",
+            MYSQL_TYPE_LONGLONG => "BIGINT",
+            MYSQL_TYPE_FLOAT => "FLOAT",
+            MYSQL_TYPE_DOUBLE => "DOUBLE",
+            MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => "DECIMAL",
+            MYSQL_TYPE_NULL => "NULL",
+            MYSQL_TYPE_TIMESTAMP | MYSQL_TYPE_TIMESTAMP2 => "TIMESTAMP",
+            MYSQL_TYPE_DATE | MYSQL_TYPE_NEWDATE => "DATE",
+            MYSQL_TYPE_TIME | MYSQL_TYPE_TIME2 => "TIME",
+            MYSQL_TYPE_DATETIME | MYSQL_TYPE_DATETIME2 => "DATETIME",
+            MYSQL_TYPE_YEAR => "YEAR",
+            MYSQL_TYPE_BIT => "BIT",
+            MYSQL_TYPE_JSON => "JSON",
+            MYSQL_TYPE_ENUM => "ENUM",
+            MYSQL_TYPE_SET => "SET",
+            MYSQL_TYPE_GEOMETRY => "GEOMETRY",
+            MYSQL_TYPE_VARCHAR | MYSQL_TYPE_VAR_STRING if binary => "VARBINARY",
+            MYSQL_TYPE_VARCHAR | MYSQL_TYPE_VAR_STRING => "VARCHAR",
+            MYSQL_TYPE_STRING if binary => "BINARY",
+            MYSQL_TYPE_STRING => "CHAR",
+            MYSQL_TYPE_TINY_BLOB
+            | MYSQL_TYPE_MEDIUM_BLOB
+            | MYSQL_TYPE_LONG_BLOB
+            | MYSQL_TYPE_BLOB
+                if binary =>
+            {
+                "BLOB"
+            }
+            MYSQL_TYPE_TINY_BLOB
+            | MYSQL_TYPE_MEDIUM_BLOB
+            | MYSQL_TYPE_LONG_BLOB
+            | MYSQL_TYPE_BLOB => "TEXT",
+            _ => "UNKNOWN",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::constants::{ColumnFlags, ColumnType};
+
+    use super::ColumnExt;
+    use crate::Column;
+
+    #[test]
+    fn should_inspect_column_metadata() {
+        let column = Column::new(ColumnType::MYSQL_TYPE_LONGLONG)
+            .with_flags(
+                ColumnFlags::NOT_NULL_FLAG
+                    | ColumnFlags::UNSIGNED_FLAG
+                    | ColumnFlags::PRI_KEY_FLAG
+                    | ColumnFlags::AUTO_INCREMENT_FLAG,
+            )
+            .with_character_set(63);
+        assert!(!column.is_nullable());
+        assert!(column.is_unsigned());
+        assert!(column.is_primary_key());
+        assert!(column.is_auto_increment());
+        assert!(column.is_numeric());
+        assert!(!column.is_temporal());
+        assert_eq!(column.type_name(), "BIGINT UNSIGNED");
+
+        let column = Column::new(ColumnType::MYSQL_TYPE_VAR_STRING).with_character_set(45);
+        assert!(column.is_nullable());
+        assert!(!column.is_binary());
+        assert_eq!(column.type_name(), "VARCHAR");
+
+        let column = Column::new(ColumnType::MYSQL_TYPE_BLOB).with_character_set(63);
+        assert!(column.is_binary());
+        assert_eq!(column.type_name(), "BLOB");
+
+        let column = Column::new(ColumnType::MYSQL_TYPE_DATETIME2);
+        assert!(column.is_temporal());
+        assert_eq!(column.type_name(), "DATETIME");
+    }
+}
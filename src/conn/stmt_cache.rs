@@ -13,10 +13,14 @@ use std::{
     borrow::Borrow,
     collections::HashMap,
     hash::{BuildHasherDefault, Hash},
-    sync::Arc,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
 };
 
-use crate::conn::stmt::InnerStmt;
+use crate::{
+    conn::stmt::{InnerStmt, StmtCacheStats},
+    Column,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct QueryString(pub Arc<Vec<u8>>);
@@ -43,6 +47,7 @@ pub struct StmtCache {
     cap: usize,
     cache: LruCache<u32, Entry>,
     query_map: HashMap<QueryString, u32, BuildHasherDefault<XxHash>>,
+    stats: StmtCacheStats,
 }
 
 impl StmtCache {
@@ -51,6 +56,7 @@ impl StmtCache {
             cap,
             cache: LruCache::unbounded(),
             query_map: Default::default(),
+            stats: StmtCacheStats::default(),
         }
     }
 
@@ -72,8 +78,14 @@ impl StmtCache {
     {
         let id = self.query_map.get(query).cloned();
         match id {
-            Some(id) => self.cache.get(&id),
-            None => None,
+            Some(id) => {
+                self.stats.hits += 1;
+                self.cache.get(&id)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
         }
     }
 
@@ -90,6 +102,7 @@ impl StmtCache {
         if self.cache.len() > self.cap {
             if let Some((_, entry)) = self.cache.pop_lru() {
                 self.query_map.remove(&**entry.query.0.as_ref());
+                self.stats.evictions += 1;
                 return Some(entry.stmt);
             }
         }
@@ -97,11 +110,32 @@ impl StmtCache {
         None
     }
 
+    pub fn stats(&self) -> StmtCacheStats {
+        self.stats
+    }
+
     pub fn clear(&mut self) {
         self.query_map.clear();
         self.cache.clear();
     }
 
+    /// Returns the query the statement with the given id is cached by, if any.
+    pub fn query(&self, id: u32) -> Option<Arc<Vec<u8>>> {
+        self.cache.peek(&id).map(|entry| entry.query.0.clone())
+    }
+
+    /// Returns the cached statement with the given id, if any.
+    pub fn by_id(&self, id: u32) -> Option<&Arc<InnerStmt>> {
+        self.cache.peek(&id).map(|entry| &entry.stmt)
+    }
+
+    /// Replaces result set metadata of the cached statement with the given id.
+    pub fn set_columns(&mut self, id: u32, columns: Arc<[Column]>) {
+        if let Some(entry) = self.cache.peek_mut(&id) {
+            entry.stmt = Arc::new(InnerStmt::clone(&entry.stmt).with_columns(Some(columns)));
+        }
+    }
+
     pub fn remove(&mut self, id: u32) {
         if let Some(entry) = self.cache.pop(&id) {
             self.query_map.remove::<[u8]>(entry.query.borrow());
@@ -117,3 +151,155 @@ impl StmtCache {
         std::iter::from_fn(move || self.cache.pop_lru())
     }
 }
+
+/// Parsed column definitions along with raw packets they were parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDefs {
+    pub packets: Arc<[Vec<u8>]>,
+    pub columns: Arc<[Column]>,
+}
+
+/// Statement metadata shared between connections of a pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StmtMetadata {
+    /// Query with named parameters replaced by `?`.
+    pub query: Vec<u8>,
+    pub named_params: Option<Vec<Vec<u8>>>,
+    pub params: Option<ColumnDefs>,
+    pub columns: Option<ColumnDefs>,
+}
+
+/// Pool-wide cache of statement metadata keyed by the default schema and the original query.
+///
+/// It allows a connection to skip parsing of named parameters and of column definitions
+/// for queries that were already prepared by another connection of the pool.
+#[derive(Debug)]
+pub struct SharedStmtCache {
+    inner: Mutex<(MetadataCache, StmtCacheStats)>,
+}
+
+type MetadataKey = (Option<String>, Vec<u8>);
+type MetadataCache = LruCache<MetadataKey, Arc<StmtMetadata>, BuildHasherDefault<XxHash>>;
+
+impl SharedStmtCache {
+    pub fn new(cap: NonZeroUsize) -> Self {
+        Self {
+            inner: Mutex::new((
+                LruCache::with_hasher(cap, Default::default()),
+                StmtCacheStats::default(),
+            )),
+        }
+    }
+
+    pub fn get(&self, schema: Option<&str>, query: &[u8]) -> Option<Arc<StmtMetadata>> {
+        let mut inner = self.inner.lock().ok()?;
+        let (cache, stats) = &mut *inner;
+        match cache.get(&(schema.map(String::from), query.to_vec())) {
+            Some(metadata) => {
+                stats.hits += 1;
+                Some(metadata.clone())
+            }
+            None => {
+                stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, schema: Option<&str>, query: Vec<u8>, metadata: Arc<StmtMetadata>) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let (cache, stats) = &mut *inner;
+            if let Some((evicted, _)) = cache.push((schema.map(String::from), query), metadata) {
+                if !cache.contains(&evicted) {
+                    stats.evictions += 1;
+                }
+            }
+        }
+    }
+
+    /// Removes metadata of the given prepared query (in every schema).
+    pub fn remove(&self, prepared_query: &[u8]) {
+        if let Ok(mut inner) = self.inner.lock() {
+            let (cache, _) = &mut *inner;
+            let keys = cache
+                .iter()
+                .filter(|(_, metadata)| metadata.query == prepared_query)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            for key in keys {
+                cache.pop(&key);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> StmtCacheStats {
+        self.inner.lock().map(|inner| inner.1).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{num::NonZeroUsize, sync::Arc};
+
+    use super::{SharedStmtCache, StmtMetadata};
+    use crate::StmtCacheStats;
+
+    fn metadata(query: &str) -> Arc<StmtMetadata> {
+        Arc::new(StmtMetadata {
+            query: query.as_bytes().to_vec(),
+            named_params: None,
+            params: None,
+            columns: None,
+        })
+    }
+
+    #[test]
+    fn should_count_shared_cache_hits_misses_and_evictions() {
+        let cache = SharedStmtCache::new(NonZeroUsize::new(2).unwrap());
+
+        assert!(cache.get(None, b"DO 1").is_none());
+        cache.put(None, b"DO 1".to_vec(), metadata("DO 1"));
+        cache.put(None, b"DO 2".to_vec(), metadata("DO 2"));
+        assert_eq!(cache.get(None, b"DO 1").unwrap().query, b"DO 1");
+
+        // replacing an entry is not an eviction
+        cache.put(None, b"DO 1".to_vec(), metadata("DO 1"));
+        cache.put(None, b"DO 3".to_vec(), metadata("DO 3"));
+        assert!(cache.get(None, b"DO 2").is_none());
+        assert!(cache.get(None, b"DO 3").is_some());
+
+        assert_eq!(
+            cache.stats(),
+            StmtCacheStats {
+                hits: 2,
+                misses: 2,
+                evictions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn should_key_shared_cache_by_schema() {
+        let cache = SharedStmtCache::new(NonZeroUsize::new(4).unwrap());
+
+        cache.put(
+            Some("foo"),
+            b"SELECT * FROM t".to_vec(),
+            metadata("SELECT * FROM t"),
+        );
+        assert!(cache.get(Some("foo"), b"SELECT * FROM t").is_some());
+        assert!(cache.get(Some("bar"), b"SELECT * FROM t").is_none());
+        assert!(cache.get(None, b"SELECT * FROM t").is_none());
+
+        cache.put(
+            Some("bar"),
+            b"SELECT * FROM t".to_vec(),
+            metadata("SELECT * FROM t"),
+        );
+        cache.put(Some("bar"), b"DO 1".to_vec(), metadata("DO 1"));
+        cache.remove(b"SELECT * FROM t");
+        assert!(cache.get(Some("foo"), b"SELECT * FROM t").is_none());
+        assert!(cache.get(Some("bar"), b"SELECT * FROM t").is_none());
+        assert!(cache.get(Some("bar"), b"DO 1").is_some());
+    }
+}
@@ -12,11 +12,12 @@ use std::{borrow::Cow, fmt};
 
 use crate::{
     conn::{
+        instrument,
         query_result::{Binary, Text},
-        ConnMut,
+        quote_identifier, ConnMut,
     },
     prelude::*,
-    LocalInfileHandler, Params, QueryResult, Result, Statement,
+    Conn, LocalInfileHandler, Params, QueryResult, Result, Statement,
 };
 
 /// MySql transaction options.
@@ -112,7 +113,8 @@ impl Transaction<'_> {
 
     /// Will consume and commit transaction.
     pub fn commit(mut self) -> Result<()> {
-        self.conn.query_drop("COMMIT")?;
+        let span = instrument::transaction_span(&self.conn, "COMMIT");
+        instrument::in_span(span, || self.conn.query_drop("COMMIT"))?;
         self.committed = true;
         Ok(())
     }
@@ -120,11 +122,44 @@ impl Transaction<'_> {
     /// Will consume and rollback transaction. You also can rely on `Drop` implementation but it
     /// will swallow errors.
     pub fn rollback(mut self) -> Result<()> {
-        self.conn.query_drop("ROLLBACK")?;
+        let span = instrument::transaction_span(&self.conn, "ROLLBACK");
+        instrument::in_span(span, || self.conn.query_drop("ROLLBACK"))?;
         self.rolled_back = true;
         Ok(())
     }
 
+    /// Creates a savepoint with the given name (see [`Savepoint`]).
+    ///
+    /// ```rust
+    /// # mysql::doctest_wrapper!(__result, {
+    /// # use mysql::*;
+    /// # use mysql::prelude::*;
+    /// # let mut conn = Conn::new(get_opts())?;
+    /// # conn.query_drop("CREATE TEMPORARY TABLE mysql.tbl(a INT)")?;
+    /// let mut tx = conn.start_transaction(TxOpts::default())?;
+    /// tx.query_drop("INSERT INTO mysql.tbl (a) VALUES (1)")?;
+    ///
+    /// let mut savepoint = tx.savepoint("before_second_insert")?;
+    /// savepoint.query_drop("INSERT INTO mysql.tbl (a) VALUES (2)")?;
+    /// savepoint.rollback()?;
+    ///
+    /// let values: Vec<u8> = tx.query("SELECT a FROM mysql.tbl")?;
+    /// assert_eq!(values, vec![1]);
+    /// tx.commit()?;
+    /// # });
+    /// ```
+    pub fn savepoint<T: Into<String>>(&mut self, name: T) -> Result<Savepoint<'_>> {
+        Savepoint::new(&mut self.conn, name.into(), 0)
+    }
+
+    /// Creates a savepoint with a generated name.
+    ///
+    /// This allows a function that takes `&mut Transaction` to get transactional semantics
+    /// without committing or rolling back the whole transaction.
+    pub fn nested(&mut self) -> Result<Savepoint<'_>> {
+        Savepoint::new(&mut self.conn, Savepoint::nested_name(1), 1)
+    }
+
     /// A way to override local infile handler for this transaction.
     /// Destructor of transaction will restore original handler.
     pub fn set_local_infile_handler(&mut self, handler: Option<LocalInfileHandler>) {
@@ -195,8 +230,145 @@ impl<'a> Drop for Transaction<'a> {
     /// Will rollback transaction.
     fn drop(&mut self) {
         if !self.committed && !self.rolled_back {
-            let _ = self.conn.query_drop("ROLLBACK");
+            let span = instrument::transaction_span(&self.conn, "ROLLBACK");
+            let _ = instrument::in_span(span, || self.conn.query_drop("ROLLBACK"));
         }
         self.conn.0.local_infile_handler = self.restore_local_infile_handler.take();
     }
 }
+
+/// Transaction savepoint.
+///
+/// Savepoint will be rolled back to on drop unless it's released or rolled back explicitly.
+#[derive(Debug)]
+pub struct Savepoint<'a> {
+    conn: &'a mut Conn,
+    name: String,
+    depth: usize,
+    released: bool,
+    rolled_back: bool,
+}
+
+impl<'a> Savepoint<'a> {
+    fn new(conn: &'a mut Conn, name: String, depth: usize) -> Result<Self> {
+        conn.query_drop(format!("SAVEPOINT {}", quote_identifier(&name)))?;
+        Ok(Self {
+            conn,
+            name,
+            depth,
+            released: false,
+            rolled_back: false,
+        })
+    }
+
+    fn nested_name(depth: usize) -> String {
+        format!("__mysql_nested_{}", depth)
+    }
+
+    /// Returns the name of this savepoint.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Creates a savepoint with the given name within this savepoint.
+    pub fn savepoint<T: Into<String>>(&mut self, name: T) -> Result<Savepoint<'_>> {
+        Savepoint::new(self.conn, name.into(), self.depth)
+    }
+
+    /// Creates a savepoint with a generated name within this savepoint.
+    pub fn nested(&mut self) -> Result<Savepoint<'_>> {
+        let depth = self.depth + 1;
+        Savepoint::new(self.conn, Savepoint::nested_name(depth), depth)
+    }
+
+    /// Will consume and release the savepoint (changes are kept within the transaction).
+    pub fn release(mut self) -> Result<()> {
+        self.conn.query_drop(format!(
+            "RELEASE SAVEPOINT {}",
+            quote_identifier(&self.name)
+        ))?;
+        self.released = true;
+        Ok(())
+    }
+
+    /// Will consume and rollback the transaction to the savepoint. You also can rely on `Drop`
+    /// implementation but it will swallow errors.
+    pub fn rollback(mut self) -> Result<()> {
+        self.conn.query_drop(format!(
+            "ROLLBACK TO SAVEPOINT {}",
+            quote_identifier(&self.name)
+        ))?;
+        self.rolled_back = true;
+        Ok(())
+    }
+
+    /// Returns the number of affected rows, reported by the server.
+    pub fn affected_rows(&self) -> u64 {
+        self.conn.affected_rows()
+    }
+
+    /// Returns the last insert id of the last query, if any.
+    pub fn last_insert_id(&self) -> Option<u64> {
+        self.conn
+            .0
+            .ok_packet
+            .as_ref()
+            .and_then(OkPacket::last_insert_id)
+    }
+
+    /// Returns the warnings count, reported by the server.
+    pub fn warnings(&self) -> u16 {
+        self.conn.warnings()
+    }
+}
+
+impl<'a> Queryable for Savepoint<'a> {
+    fn query_iter<T: AsRef<str>>(&mut self, query: T) -> Result<QueryResult<'_, '_, '_, Text>> {
+        self.conn.query_iter(query)
+    }
+
+    fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
+        self.conn.prep(query)
+    }
+
+    fn close(&mut self, stmt: Statement) -> Result<()> {
+        self.conn.close(stmt)
+    }
+
+    fn exec_iter<S, P>(&mut self, stmt: S, params: P) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.conn.exec_iter(stmt, params)
+    }
+}
+
+impl<'a, 'b> From<&'a mut Savepoint<'b>> for ConnMut<'a, 'static, 'static> {
+    fn from(savepoint: &'a mut Savepoint<'b>) -> Self {
+        ConnMut::Mut(savepoint.conn)
+    }
+}
+
+impl<'a> Drop for Savepoint<'a> {
+    /// Will rollback to the savepoint.
+    fn drop(&mut self) {
+        if !self.released && !self.rolled_back {
+            let _ = self.conn.query_drop(format!(
+                "ROLLBACK TO SAVEPOINT {}",
+                quote_identifier(&self.name)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::conn::quote_identifier;
+
+    #[test]
+    fn should_quote_savepoint_names() {
+        assert_eq!(quote_identifier("foo"), "`foo`");
+        assert_eq!(quote_identifier("fo`o"), "`fo``o`");
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::fmt;
+
+/// Level of a [`Warning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WarningLevel {
+    Note,
+    Warning,
+    Error,
+}
+
+impl WarningLevel {
+    /// Parses the `Level` column of `SHOW WARNINGS`.
+    pub(crate) fn parse(level: &str) -> Self {
+        match level {
+            "Note" => WarningLevel::Note,
+            "Error" => WarningLevel::Error,
+            _ => WarningLevel::Warning,
+        }
+    }
+}
+
+impl fmt::Display for WarningLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningLevel::Note => f.write_str("Note"),
+            WarningLevel::Warning => f.write_str("Warning"),
+            WarningLevel::Error => f.write_str("Error"),
+        }
+    }
+}
+
+/// Warning reported by the server (a row of `SHOW WARNINGS`).
+///
+/// See [`crate::Conn::fetch_warnings`] and [`crate::OptsBuilder::fetch_warnings`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Warning {
+    pub level: WarningLevel,
+    pub code: u16,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.level, self.code, self.message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Warning, WarningLevel};
+
+    #[test]
+    fn should_display_warning() {
+        let warning = Warning {
+            level: WarningLevel::parse("Warning"),
+            code: 1265,
+            message: "Data truncated for column 'a' at row 1".into(),
+        };
+        assert_eq!(
+            warning.to_string(),
+            "Warning 1265: Data truncated for column 'a' at row 1"
+        );
+        assert_eq!(WarningLevel::parse("Note"), WarningLevel::Note);
+        assert_eq!(WarningLevel::parse("Error"), WarningLevel::Error);
+    }
+}
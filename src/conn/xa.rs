@@ -0,0 +1,270 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::packets::OkPacket;
+
+use std::fmt::{self, Write};
+
+use crate::{
+    conn::{
+        query_result::{Binary, Text},
+        ConnMut,
+    },
+    prelude::*,
+    Params, QueryResult, Result, Statement,
+};
+
+/// XA transaction identifier.
+///
+/// Consists of a global transaction identifier (`gtrid`), a branch qualifier (`bqual`)
+/// and a format identifier (`formatID`), that identifies the format used by
+/// `gtrid` and `bqual`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Xid {
+    gtrid: Vec<u8>,
+    bqual: Vec<u8>,
+    format_id: u32,
+}
+
+impl Xid {
+    /// Creates new XID with the given `gtrid`, empty `bqual` and `formatID` equal to `1`.
+    pub fn new<T: Into<Vec<u8>>>(gtrid: T) -> Self {
+        Self {
+            gtrid: gtrid.into(),
+            bqual: Vec::new(),
+            format_id: 1,
+        }
+    }
+
+    /// Defines the branch qualifier (defaults to empty).
+    pub fn with_bqual<T: Into<Vec<u8>>>(mut self, bqual: T) -> Self {
+        self.bqual = bqual.into();
+        self
+    }
+
+    /// Defines the format identifier (defaults to `1`).
+    pub fn with_format_id(mut self, format_id: u32) -> Self {
+        self.format_id = format_id;
+        self
+    }
+
+    /// Returns the global transaction identifier.
+    pub fn gtrid(&self) -> &[u8] {
+        &self.gtrid
+    }
+
+    /// Returns the branch qualifier.
+    pub fn bqual(&self) -> &[u8] {
+        &self.bqual
+    }
+
+    /// Returns the format identifier.
+    pub fn format_id(&self) -> u32 {
+        self.format_id
+    }
+
+    /// Parses a row of the `XA RECOVER` output
+    /// (`None` if lengths don't match the data or `formatID` is out of range).
+    pub(crate) fn from_recover_row(
+        format_id: i64,
+        gtrid_length: usize,
+        bqual_length: usize,
+        mut data: Vec<u8>,
+    ) -> Option<Self> {
+        if data.len() != gtrid_length + bqual_length {
+            return None;
+        }
+        let format_id = u32::try_from(format_id).ok()?;
+        let bqual = data.split_off(gtrid_length);
+        Some(Self {
+            gtrid: data,
+            bqual,
+            format_id,
+        })
+    }
+
+    /// Returns the XID in the form suitable for XA statements.
+    pub(crate) fn to_sql(&self) -> String {
+        fn hex(out: &mut String, bytes: &[u8]) {
+            out.push_str("X'");
+            for byte in bytes {
+                let _ = write!(out, "{:02x}", byte);
+            }
+            out.push('\'');
+        }
+
+        let mut out = String::with_capacity(16 + (self.gtrid.len() + self.bqual.len()) * 2);
+        hex(&mut out, &self.gtrid);
+        out.push(',');
+        hex(&mut out, &self.bqual);
+        let _ = write!(out, ",{}", self.format_id);
+        out
+    }
+}
+
+impl fmt::Display for Xid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_sql())
+    }
+}
+
+/// XA transaction branch.
+///
+/// It's started with `XA START` and will be rolled back on drop,
+/// unless it's committed, rolled back or prepared.
+///
+/// Prepared transaction branch outlives the connection, so it could be committed
+/// or rolled back on any connection (see [`crate::Conn::xa_commit`]
+/// and [`crate::Conn::xa_rollback`]).
+#[derive(Debug)]
+pub struct XaTransaction<'a> {
+    pub(crate) conn: ConnMut<'a, 'static, 'static>,
+    xid: Xid,
+    /// `XA END` is executed.
+    ended: bool,
+    /// The branch is committed, rolled back or prepared.
+    finished: bool,
+}
+
+impl XaTransaction<'_> {
+    pub(crate) fn new<'a>(conn: ConnMut<'a, 'static, 'static>, xid: Xid) -> XaTransaction<'a> {
+        XaTransaction {
+            conn,
+            xid,
+            ended: false,
+            finished: false,
+        }
+    }
+
+    /// Returns the XID of this transaction.
+    pub fn xid(&self) -> &Xid {
+        &self.xid
+    }
+
+    /// Will consume and prepare the transaction (first phase of the two-phase commit).
+    ///
+    /// Returns the XID, that should be used to commit or rollback the prepared transaction.
+    pub fn prepare(mut self) -> Result<Xid> {
+        let xid = self.end()?;
+        self.conn.query_drop(format!("XA PREPARE {}", xid))?;
+        self.finished = true;
+        Ok(self.xid.clone())
+    }
+
+    /// Will consume and commit the transaction using the one-phase commit.
+    pub fn commit(mut self) -> Result<()> {
+        let xid = self.end()?;
+        self.conn
+            .query_drop(format!("XA COMMIT {} ONE PHASE", xid))?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Will consume and rollback the transaction. You also can rely on `Drop` implementation but
+    /// it will swallow errors.
+    pub fn rollback(mut self) -> Result<()> {
+        let xid = self.end()?;
+        self.conn.query_drop(format!("XA ROLLBACK {}", xid))?;
+        self.finished = true;
+        Ok(())
+    }
+
+    /// Executes `XA END` (unless it's already executed) and returns the XID as SQL.
+    fn end(&mut self) -> Result<String> {
+        let xid = self.xid.to_sql();
+        if !self.ended {
+            self.conn.query_drop(format!("XA END {}", xid))?;
+            self.ended = true;
+        }
+        Ok(xid)
+    }
+
+    /// Returns the number of affected rows, reported by the server.
+    pub fn affected_rows(&self) -> u64 {
+        self.conn.affected_rows()
+    }
+
+    /// Returns the last insert id of the last query, if any.
+    pub fn last_insert_id(&self) -> Option<u64> {
+        self.conn
+            .0
+            .ok_packet
+            .as_ref()
+            .and_then(OkPacket::last_insert_id)
+    }
+
+    /// Returns the warnings count, reported by the server.
+    pub fn warnings(&self) -> u16 {
+        self.conn.warnings()
+    }
+}
+
+impl<'a> Queryable for XaTransaction<'a> {
+    fn query_iter<T: AsRef<str>>(&mut self, query: T) -> Result<QueryResult<'_, '_, '_, Text>> {
+        self.conn.query_iter(query)
+    }
+
+    fn prep<T: AsRef<str>>(&mut self, query: T) -> Result<Statement> {
+        self.conn.prep(query)
+    }
+
+    fn close(&mut self, stmt: Statement) -> Result<()> {
+        self.conn.close(stmt)
+    }
+
+    fn exec_iter<S, P>(&mut self, stmt: S, params: P) -> Result<QueryResult<'_, '_, '_, Binary>>
+    where
+        S: AsStatement,
+        P: Into<Params>,
+    {
+        self.conn.exec_iter(stmt, params)
+    }
+}
+
+impl<'a, 'b> From<&'a mut XaTransaction<'b>> for ConnMut<'a, 'static, 'static> {
+    fn from(tx: &'a mut XaTransaction<'b>) -> Self {
+        ConnMut::Mut(&mut tx.conn)
+    }
+}
+
+impl<'a> Drop for XaTransaction<'a> {
+    /// Will rollback the transaction.
+    fn drop(&mut self) {
+        if !self.finished {
+            // the branch might be ended but not prepared if `XA PREPARE` or `XA COMMIT` failed
+            if let Ok(xid) = self.end() {
+                let _ = self.conn.query_drop(format!("XA ROLLBACK {}", xid));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Xid;
+
+    #[test]
+    fn should_format_xid() {
+        assert_eq!(Xid::new("foo").to_sql(), "X'666f6f',X'',1");
+        assert_eq!(
+            Xid::new(vec![0x00, 0xff])
+                .with_bqual("b")
+                .with_format_id(42)
+                .to_sql(),
+            "X'00ff',X'62',42"
+        );
+    }
+
+    #[test]
+    fn should_parse_xa_recover_row() {
+        let xid = Xid::from_recover_row(7, 3, 2, b"foobq".to_vec()).unwrap();
+        assert_eq!(xid, Xid::new("foo").with_bqual("bq").with_format_id(7));
+        assert!(Xid::from_recover_row(1, 3, 3, b"foobq".to_vec()).is_none());
+        assert!(Xid::from_recover_row(-1, 3, 2, b"foobq".to_vec()).is_none());
+    }
+}
@@ -0,0 +1,481 @@
+// Copyright (c) 2020 rust-mysql-simple contributors
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Classification of errors (see [`crate::Error::kind`]).
+
+use mysql_common::proto::codec::error::PacketCodecError;
+
+use std::io;
+
+use super::{DriverError, Error, MySqlError, ServerError::*};
+
+/// Server error codes, that are missing in [`crate::ServerError`].
+mod codes {
+    pub const ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION: u16 = 1792;
+    pub const ER_READ_ONLY_MODE: u16 = 1836;
+    /// MariaDB
+    pub const ER_CONNECTION_KILLED: u16 = 1927;
+    /// MariaDB
+    pub const ER_STATEMENT_TIMEOUT: u16 = 1969;
+    pub const ER_QUERY_TIMEOUT: u16 = 3024;
+    pub const ER_LOCK_NOWAIT: u16 = 3572;
+    pub const ER_CHECK_CONSTRAINT_VIOLATED: u16 = 3819;
+    /// MariaDB
+    pub const ER_CONSTRAINT_FAILED: u16 = 4025;
+    pub const ER_CLIENT_INTERACTION_TIMEOUT: u16 = 4031;
+}
+
+/// Kind of an error (see [`crate::Error::kind`]).
+///
+/// Server errors are classified by the error code, falling back to the SQLSTATE class
+/// for unknown codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Connection is lost (I/O error, server shutdown, connection killed, etc.).
+    ConnectionLost,
+    /// Could not establish a connection.
+    ConnectionFailed,
+    /// Server or user connection limit is reached.
+    TooManyConnections,
+    /// Operation timed out (statement timeout, read/write timeout or pool timeout).
+    Timeout,
+    /// Statement was interrupted (e.g. by `KILL QUERY`).
+    QueryInterrupted,
+    /// Deadlock or serialization failure (the transaction is rolled back).
+    Deadlock,
+    /// Lock wait timeout (only the statement is rolled back by default).
+    LockWaitTimeout,
+    /// Unique or primary key violation.
+    DuplicateKey,
+    /// Foreign key constraint violation.
+    ForeignKeyViolation,
+    /// Check constraint violation.
+    CheckViolation,
+    /// `NULL` value for a `NOT NULL` column.
+    NotNullViolation,
+    /// Other integrity constraint violation (SQLSTATE class `23`).
+    ConstraintViolation,
+    /// Data is too long for a column.
+    DataTooLong,
+    /// Invalid or out of range value (SQLSTATE class `22`).
+    InvalidData,
+    /// Syntax error.
+    SyntaxError,
+    /// Unknown database, table or column.
+    UnknownObject,
+    /// Access denied.
+    AccessDenied,
+    /// Server or transaction is read-only.
+    ReadOnly,
+    /// Statement parameters doesn't match the statement.
+    InvalidParams,
+    /// Could not convert a value or a row.
+    Conversion,
+    /// Invalid options or connection URL.
+    Configuration,
+    /// Operation or protocol is not supported by the server or by the client.
+    Unsupported,
+    /// Protocol violation.
+    Protocol,
+    /// Statement was rejected by an [`crate::Interceptor`].
+    Rejected,
+    /// Statement produced warnings (see [`crate::OptsBuilder::warnings_as_errors`]).
+    Warnings,
+    /// Unclassified error.
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns `true` if the failed operation could succeed if retried.
+    ///
+    /// Note that a deadlock rolls back the whole transaction, so the whole transaction
+    /// should be retried. Also note that a statement that has failed with
+    /// [`ErrorKind::ConnectionLost`] may or may not have been applied.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::ConnectionLost
+                | ErrorKind::ConnectionFailed
+                | ErrorKind::TooManyConnections
+                | ErrorKind::Deadlock
+                | ErrorKind::LockWaitTimeout
+        )
+    }
+}
+
+impl MySqlError {
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        classify_server_error(self.code, &self.message)
+            .unwrap_or_else(|| classify_sql_state(&self.state))
+    }
+
+    /// Returns `true` if the connection is unusable after this error.
+    pub fn is_connection_broken(&self) -> bool {
+        self.kind() == ErrorKind::ConnectionLost
+            || self.code == ER_NET_PACKET_TOO_LARGE as u16
+            || self.code == ER_NET_READ_INTERRUPTED as u16
+            || self.code == ER_NET_WRITE_INTERRUPTED as u16
+    }
+
+    /// Returns the name of the key or constraint, that was violated,
+    /// if it could be parsed from the message.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// let err = MySqlError {
+    ///     state: "23000".into(),
+    ///     message: "Duplicate entry '1' for key 'tbl.PRIMARY'".into(),
+    ///     code: 1062,
+    /// };
+    /// assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+    /// assert_eq!(err.constraint(), Some("tbl.PRIMARY"));
+    /// ```
+    pub fn constraint(&self) -> Option<&str> {
+        let message = self.message.as_str();
+        match self.kind() {
+            ErrorKind::DuplicateKey => {
+                // the entry itself may contain anything, so the key is searched from the end
+                let start = message.rfind(" for key '")? + " for key '".len();
+                let end = start + message[start..].rfind('\'')?;
+                Some(&message[start..end])
+            }
+            ErrorKind::ForeignKeyViolation | ErrorKind::CheckViolation => {
+                between(message, "CONSTRAINT `", '`')
+                    .or_else(|| between(message, "constraint '", '\''))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the substring of `message` between `prefix` and `end` (exclusive).
+fn between<'a>(message: &'a str, prefix: &str, end: char) -> Option<&'a str> {
+    let start = message.find(prefix)? + prefix.len();
+    let len = message[start..].find(end)?;
+    Some(&message[start..start + len])
+}
+
+fn classify_server_error(code: u16, message: &str) -> Option<ErrorKind> {
+    use codes::*;
+
+    let kind = match code {
+        x if x == ER_SERVER_SHUTDOWN as u16
+            || x == ER_ABORTING_CONNECTION as u16
+            || x == ER_NEW_ABORTING_CONNECTION as u16
+            || x == ER_CONNECTION_KILLED
+            || x == ER_CLIENT_INTERACTION_TIMEOUT =>
+        {
+            ErrorKind::ConnectionLost
+        }
+        x if x == ER_CON_COUNT_ERROR as u16 || x == ER_TOO_MANY_USER_CONNECTIONS as u16 => {
+            ErrorKind::TooManyConnections
+        }
+        ER_QUERY_TIMEOUT | ER_STATEMENT_TIMEOUT => ErrorKind::Timeout,
+        x if x == ER_QUERY_INTERRUPTED as u16 => ErrorKind::QueryInterrupted,
+        x if x == ER_LOCK_DEADLOCK as u16 => ErrorKind::Deadlock,
+        x if x == ER_LOCK_WAIT_TIMEOUT as u16 || x == ER_LOCK_NOWAIT => ErrorKind::LockWaitTimeout,
+        x if x == ER_DUP_KEY as u16
+            || x == ER_DUP_ENTRY as u16
+            || x == ER_DUP_UNIQUE as u16
+            || x == ER_FOREIGN_DUPLICATE_KEY as u16
+            || x == ER_DUP_ENTRY_WITH_KEY_NAME as u16 =>
+        {
+            ErrorKind::DuplicateKey
+        }
+        x if x == ER_NO_REFERENCED_ROW as u16
+            || x == ER_ROW_IS_REFERENCED as u16
+            || x == ER_ROW_IS_REFERENCED_2 as u16
+            || x == ER_NO_REFERENCED_ROW_2 as u16 =>
+        {
+            ErrorKind::ForeignKeyViolation
+        }
+        ER_CHECK_CONSTRAINT_VIOLATED | ER_CONSTRAINT_FAILED => ErrorKind::CheckViolation,
+        x if x == ER_BAD_NULL_ERROR as u16 => ErrorKind::NotNullViolation,
+        x if x == ER_DATA_TOO_LONG as u16 => ErrorKind::DataTooLong,
+        x if x == ER_WARN_DATA_OUT_OF_RANGE as u16
+            || x == ER_TRUNCATED_WRONG_VALUE as u16
+            || x == ER_TRUNCATED_WRONG_VALUE_FOR_FIELD as u16 =>
+        {
+            ErrorKind::InvalidData
+        }
+        x if x == ER_PARSE_ERROR as u16 || x == ER_SYNTAX_ERROR as u16 => ErrorKind::SyntaxError,
+        x if x == ER_BAD_DB_ERROR as u16
+            || x == ER_BAD_FIELD_ERROR as u16
+            || x == ER_NO_SUCH_TABLE as u16 =>
+        {
+            ErrorKind::UnknownObject
+        }
+        x if x == ER_DBACCESS_DENIED_ERROR as u16
+            || x == ER_ACCESS_DENIED_ERROR as u16
+            || x == ER_TABLEACCESS_DENIED_ERROR as u16
+            || x == ER_COLUMNACCESS_DENIED_ERROR as u16
+            || x == ER_SPECIFIC_ACCESS_DENIED_ERROR as u16
+            || x == ER_ACCESS_DENIED_NO_PASSWORD_ERROR as u16 =>
+        {
+            ErrorKind::AccessDenied
+        }
+        // the same code is reported for other options (e.g. `--skip-grant-tables`),
+        // so the message is checked for `--read-only` or `--super-read-only`
+        x if x == ER_OPTION_PREVENTS_STATEMENT as u16 && message.contains("read-only") => {
+            ErrorKind::ReadOnly
+        }
+        x if x == ER_READ_ONLY_TRANSACTION as u16
+            || x == ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION
+            || x == ER_READ_ONLY_MODE =>
+        {
+            ErrorKind::ReadOnly
+        }
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn classify_sql_state(state: &str) -> ErrorKind {
+    match state {
+        "40001" => ErrorKind::Deadlock,
+        "22001" => ErrorKind::DataTooLong,
+        "25006" => ErrorKind::ReadOnly,
+        "70100" => ErrorKind::QueryInterrupted,
+        "HYT00" => ErrorKind::Timeout,
+        "42S01" | "42S02" | "42S12" | "42S22" => ErrorKind::UnknownObject,
+        _ => match state.get(..2) {
+            Some("08") => ErrorKind::ConnectionLost,
+            Some("22") => ErrorKind::InvalidData,
+            Some("23") => ErrorKind::ConstraintViolation,
+            Some("28") => ErrorKind::AccessDenied,
+            Some("42") => ErrorKind::SyntaxError,
+            _ => ErrorKind::Other,
+        },
+    }
+}
+
+fn classify_io_error(err: &io::Error) -> ErrorKind {
+    match err.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => ErrorKind::Timeout,
+        io::ErrorKind::ConnectionRefused => ErrorKind::ConnectionFailed,
+        _ => ErrorKind::ConnectionLost,
+    }
+}
+
+impl DriverError {
+    /// Returns the kind of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DriverError::CouldNotConnect(_)
+            | DriverError::ConnectTimeout
+            | DriverError::SetupError => ErrorKind::ConnectionFailed,
+            DriverError::Timeout => ErrorKind::Timeout,
+            DriverError::PacketOutOfSync
+            | DriverError::PacketTooLarge
+            | DriverError::UnexpectedPacket => ErrorKind::Protocol,
+            DriverError::UnsupportedProtocol(_)
+            | DriverError::Protocol41NotSet
+            | DriverError::TlsNotSupported
+            | DriverError::CouldNotParseVersion
+            | DriverError::ReadOnlyTransNotSupported => ErrorKind::Unsupported,
+            DriverError::InvalidPoolConstraints
+            | DriverError::UnknownAuthPlugin(_)
+            | DriverError::OldMysqlPasswordDisabled
+            | DriverError::CleartextPluginDisabled => ErrorKind::Configuration,
+            DriverError::MismatchedStmtParams(..)
+            | DriverError::MissingNamedParameter(_)
+            | DriverError::NamedParamsForPositionalQuery
+            | DriverError::MixedParams => ErrorKind::InvalidParams,
+            DriverError::QueryRejected(_) => ErrorKind::Rejected,
+            DriverError::Warnings(_) => ErrorKind::Warnings,
+            DriverError::PoisonedPoolMutex => ErrorKind::Other,
+        }
+    }
+}
+
+impl Error {
+    /// Returns the kind of this error.
+    ///
+    /// ```
+    /// # use mysql::*;
+    /// fn should_retry(err: &Error) -> bool {
+    ///     match err.kind() {
+    ///         ErrorKind::Deadlock | ErrorKind::LockWaitTimeout => true,
+    ///         ErrorKind::DuplicateKey => {
+    ///             eprintln!("duplicate key: {:?}", err.constraint());
+    ///             false
+    ///         }
+    ///         _ => false,
+    ///     }
+    /// }
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::IoError(err) | Error::CodecError(PacketCodecError::Io(err)) => {
+                classify_io_error(err)
+            }
+            Error::CodecError(_) => ErrorKind::Protocol,
+            Error::MySqlError(err) => err.kind(),
+            Error::DriverError(err) => err.kind(),
+            Error::UrlError(_) => ErrorKind::Configuration,
+            #[cfg(any(feature = "native-tls", feature = "rustls"))]
+            Error::TlsError(_) => ErrorKind::ConnectionFailed,
+            Error::FromValueError(_) | Error::FromRowError(_) => ErrorKind::Conversion,
+        }
+    }
+
+    /// Returns `true` if the failed operation could succeed if retried
+    /// (see [`ErrorKind::is_retryable`]).
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
+    /// Returns `true` if the connection is unusable after this error,
+    /// i.e. it should be dropped rather than reused.
+    pub fn is_connection_broken(&self) -> bool {
+        match self {
+            Error::IoError(_) | Error::CodecError(_) => true,
+            #[cfg(any(feature = "native-tls", feature = "rustls"))]
+            Error::TlsError(_) => true,
+            Error::MySqlError(err) => err.is_connection_broken(),
+            Error::DriverError(err) => err.kind() == ErrorKind::Protocol,
+            Error::UrlError(_) | Error::FromValueError(_) | Error::FromRowError(_) => false,
+        }
+    }
+
+    /// Returns the name of the key or constraint, that was violated, if any
+    /// (see [`MySqlError::constraint`]).
+    pub fn constraint(&self) -> Option<&str> {
+        match self {
+            Error::MySqlError(err) => err.constraint(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::ErrorKind;
+    use crate::{DriverError, Error, MySqlError};
+
+    fn server_error(code: u16, state: &str, message: &str) -> MySqlError {
+        MySqlError {
+            state: state.into(),
+            message: message.into(),
+            code,
+        }
+    }
+
+    #[test]
+    fn should_classify_server_errors() {
+        let cases = [
+            (1213, "40001", ErrorKind::Deadlock),
+            (1205, "HY000", ErrorKind::LockWaitTimeout),
+            (1062, "23000", ErrorKind::DuplicateKey),
+            (1452, "23000", ErrorKind::ForeignKeyViolation),
+            (3819, "HY000", ErrorKind::CheckViolation),
+            (1048, "23000", ErrorKind::NotNullViolation),
+            (1406, "22001", ErrorKind::DataTooLong),
+            (1064, "42000", ErrorKind::SyntaxError),
+            (1146, "42S02", ErrorKind::UnknownObject),
+            (1045, "28000", ErrorKind::AccessDenied),
+            (1792, "25006", ErrorKind::ReadOnly),
+            (3024, "HY000", ErrorKind::Timeout),
+            (1053, "08S01", ErrorKind::ConnectionLost),
+            (1040, "08004", ErrorKind::TooManyConnections),
+            // unknown codes fall back to SQLSTATE
+            (9999, "40001", ErrorKind::Deadlock),
+            (9999, "08S01", ErrorKind::ConnectionLost),
+            (9999, "23000", ErrorKind::ConstraintViolation),
+            (9999, "HY000", ErrorKind::Other),
+        ];
+        for (code, state, kind) in cases {
+            assert_eq!(server_error(code, state, "").kind(), kind, "{}", code);
+        }
+
+        let read_only = "The MySQL server is running with the --super-read-only option \
+            so it cannot execute this statement";
+        assert_eq!(
+            server_error(1290, "HY000", read_only).kind(),
+            ErrorKind::ReadOnly
+        );
+        let skip_grant_tables = "The MySQL server is running with the --skip-grant-tables option \
+            so it cannot execute this statement";
+        assert_eq!(
+            server_error(1290, "HY000", skip_grant_tables).kind(),
+            ErrorKind::Other
+        );
+
+        let err = Error::MySqlError(server_error(1213, "40001", ""));
+        assert!(err.is_retryable());
+        assert!(!err.is_connection_broken());
+        let err = Error::MySqlError(server_error(1053, "08S01", ""));
+        assert!(err.is_retryable());
+        assert!(err.is_connection_broken());
+        let err = Error::MySqlError(server_error(1062, "23000", ""));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn should_classify_client_errors() {
+        let err = Error::from(io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert!(err.is_connection_broken());
+        assert_eq!(
+            Error::server_disconnected().kind(),
+            ErrorKind::ConnectionLost
+        );
+
+        let err = Error::from(DriverError::UnexpectedPacket);
+        assert_eq!(err.kind(), ErrorKind::Protocol);
+        assert!(err.is_connection_broken());
+
+        let err = Error::from(DriverError::MismatchedStmtParams(1, 2));
+        assert_eq!(err.kind(), ErrorKind::InvalidParams);
+        assert!(!err.is_connection_broken());
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn should_parse_constraint() {
+        let cases = [
+            (
+                1062,
+                "Duplicate entry 'a' for key 'x' for key 'tbl.uniq'",
+                Some("tbl.uniq"),
+            ),
+            (
+                1062,
+                "Duplicate entry '1' for key 'PRIMARY'",
+                Some("PRIMARY"),
+            ),
+            (
+                1452,
+                "Cannot add or update a child row: a foreign key constraint fails \
+                 (`db`.`child`, CONSTRAINT `child_ibfk_1` FOREIGN KEY (`pid`) \
+                 REFERENCES `parent` (`id`))",
+                Some("child_ibfk_1"),
+            ),
+            (
+                3819,
+                "Check constraint 'positive' is violated.",
+                Some("positive"),
+            ),
+            (
+                4025,
+                "CONSTRAINT `positive` failed for `db`.`t`",
+                Some("positive"),
+            ),
+            (1048, "Column 'a' cannot be null", None),
+            (1062, "Duplicate entry", None),
+        ];
+        for (code, message, constraint) in cases {
+            let err = server_error(code, "23000", message);
+            assert_eq!(err.constraint(), constraint, "{}", message);
+        }
+    }
+}
@@ -15,10 +15,13 @@ use url::ParseError;
 
 use std::{error, fmt, io, result, sync};
 
-use crate::{Row, Value};
+use crate::{Row, Value, Warning};
 
+mod kind;
 pub mod tls;
 
+pub use self::kind::ErrorKind;
+
 impl<'a> From<packets::ServerError<'a>> for MySqlError {
     fn from(x: packets::ServerError<'a>) -> MySqlError {
         MySqlError {
@@ -220,6 +223,10 @@ pub enum DriverError {
     UnknownAuthPlugin(String),
     OldMysqlPasswordDisabled,
     CleartextPluginDisabled,
+    /// Statement was rejected by an [`crate::Interceptor`].
+    QueryRejected(String),
+    /// Statement produced warnings (see [`crate::OptsBuilder::warnings_as_errors`]).
+    Warnings(Vec<Warning>),
 }
 
 impl error::Error for DriverError {
@@ -286,6 +293,16 @@ impl fmt::Display for DriverError {
             DriverError::CleartextPluginDisabled => {
                 write!(f, "mysql_clear_password must be enabled on the client side")
             }
+            DriverError::QueryRejected(ref reason) => {
+                write!(f, "Query rejected by an interceptor: {}", reason)
+            }
+            DriverError::Warnings(ref warnings) => {
+                write!(f, "Statement produced warnings:")?;
+                for warning in warnings {
+                    write!(f, " {};", warning)?;
+                }
+                Ok(())
+            }
         }
     }
 }
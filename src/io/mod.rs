@@ -151,6 +151,62 @@ impl Stream {
     }
 }
 
+/// Stream wrapper, that counts bytes read from and written to the wrapped stream.
+#[derive(Debug)]
+pub struct Metered<T> {
+    inner: T,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+impl<T> Metered<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns the number of bytes read and written so far.
+    pub fn counters(&self) -> (u64, u64) {
+        (self.bytes_read, self.bytes_written)
+    }
+
+    /// Replaces the wrapped stream keeping the counters.
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Metered<U>, E> {
+        Ok(Metered {
+            inner: f(self.inner)?,
+            bytes_read: self.bytes_read,
+            bytes_written: self.bytes_written,
+        })
+    }
+}
+
+impl<T: io::Read> io::Read for Metered<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
+}
+
+impl<T: io::Write> io::Write for Metered<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes_written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 #[cfg(unix)]
 impl AsRawFd for Stream {
     fn as_raw_fd(&self) -> RawFd {
@@ -23,7 +23,8 @@
 //! *   buffer pool (see the [Buffer Pool](#buffer-pool) section);
 //! *   support of MySql packets larger than 2^24;
 //! *   support of Unix sockets and Windows named pipes;
-//! *   support of custom LOCAL INFILE handlers;
+//! *   support of custom LOCAL INFILE handlers (including allow-listed directories
+//!     and named `Read` sources), `Conn::load_data` and `BulkLoader`;
 //! *   support of MySql protocol compression;
 //! *   support of auth plugins:
 //!     *   **mysql_native_password** - for MySql prior to v8;
@@ -127,6 +128,13 @@
 //!     *   **buffer-pool** (enabled by default) – enables buffer pooling
 //!         (see the [Buffer Pool](#buffer-pool) section)
 //!     *   **derive** (enabled by default) – reexports derive macros under `prelude`
+//!     *   **arrow** (disabled by default) – enables export of query results into
+//!         Apache Arrow record batches (see `QueryResult::into_record_batches`)
+//!     *   **tracing** (disabled by default) – emits `tracing` spans for connection setup,
+//!         queries, statements, transactions and `Pool::get_conn`
+//!         (see `OptsBuilder::statement_sanitizer`)
+//!     *   **macros** (disabled by default) – reexports the `query_as!` macro
+//!         of the `mysql-macros` crate (compile-time checked queries)
 //!
 //! * external features enabled by default:
 //!
@@ -205,8 +213,14 @@
 //! *   `stmt_cache_size: u32` - defines the value of the same field in the `Opts` structure;
 //! *   `enable_cleartext_plugin` – see [`Opts::get_enable_cleartext_plugin`];
 //! *   `secure_auth` – see [`Opts::get_secure_auth`];
+//! *   `optional_resultset_metadata` – see [`OptsBuilder::optional_resultset_metadata`];
+//! *   `statement_sanitizer` – see [`OptsBuilder::statement_sanitizer`];
+//! *   `fetch_warnings` – see [`OptsBuilder::fetch_warnings`];
+//! *   `warnings_as_errors` – see [`OptsBuilder::warnings_as_errors`];
 //! *   `reset_connection` – see [`PoolOpts::reset_connection`];
+//! *   `restore_session_state` – see [`PoolOpts::restore_session_state`];
 //! *   `check_health` – see [`PoolOpts::check_health`];
+//! *   `shared_stmt_cache_size` – see [`PoolOpts::with_shared_stmt_cache_size`];
 //! *   `compress` - defines the value of the same field in the `Opts` structure.
 //!     Supported value are:
 //!     *  `true` - enables compression with the default compression level;
@@ -263,6 +277,9 @@
 //! # });
 //! ```
 //!
+//! Transactions could not be nested, but `Transaction::savepoint` and `Transaction::nested`
+//! give a [`Savepoint`] that is released or rolled back to independently of the transaction.
+//!
 //! ### `Pool`
 //!
 //! It's a reference to a connection pool, that can be cloned and shared between threads.
@@ -923,30 +940,61 @@ pub use crate::myc::packets::{session_state_change, SessionStateInfo};
 
 #[cfg(feature = "binlog")]
 #[doc(inline)]
-pub use crate::conn::binlog_stream::BinlogStream;
+pub use crate::conn::binlog_file::{BinlogFile, BinlogWriter};
+#[cfg(feature = "binlog")]
+#[doc(inline)]
+pub use crate::conn::binlog_stream::{
+    BinlogStream, BinlogStreamOpts, RowChange, RowChangeKind, RowChanges, TransactionChanges,
+};
+#[doc(inline)]
+pub use crate::conn::bulk_loader::BulkLoader;
+#[doc(inline)]
+pub use crate::conn::gtid::GtidSet;
 #[doc(inline)]
-pub use crate::conn::local_infile::{LocalInfile, LocalInfileHandler};
+pub use crate::conn::instrument::StatementSanitizer;
+#[doc(inline)]
+pub use crate::conn::interceptor::{Interceptor, QueryContext, QueryKind, QueryOutcome};
+#[doc(inline)]
+pub use crate::conn::local_infile::{
+    LoadDataFormat, LoadDataInfo, LocalInfile, LocalInfileHandler, LocalInfileSources,
+};
 #[doc(inline)]
 pub use crate::conn::opts::SslOpts;
 #[doc(inline)]
 pub use crate::conn::opts::{
-    pool_opts::{PoolConstraints, PoolOpts},
+    pool_opts::{PoolConstraints, PoolOpts, DEFAULT_SHARED_STMT_CACHE_SIZE},
     ChangeUserOpts, Opts, OptsBuilder, DEFAULT_STMT_CACHE_SIZE,
 };
 #[doc(inline)]
-pub use crate::conn::pool::{Pool, PooledConn};
+pub use crate::conn::pool::{
+    retry::{Retried, RetryPolicy},
+    Pool, PooledConn,
+};
+#[doc(inline)]
+pub use crate::conn::query::{QueryWithParams, TypedQuery};
 #[doc(inline)]
-pub use crate::conn::query::QueryWithParams;
+pub use crate::conn::query_result::{
+    Binary, OwnedQueryResult, QueryResult, ResultSet, SetColumns, Text,
+};
+#[cfg(feature = "arrow")]
+#[doc(inline)]
+pub use crate::conn::record_batch::RecordBatches;
+#[doc(inline)]
+pub use crate::conn::row_ref::{RowRef, ValueRef};
 #[doc(inline)]
-pub use crate::conn::query_result::{Binary, QueryResult, ResultSet, SetColumns, Text};
+pub use crate::conn::stats::{ConnStats, SlowQuery};
 #[doc(inline)]
-pub use crate::conn::stmt::Statement;
+pub use crate::conn::stmt::{Describe, Statement, StmtCacheStats};
 #[doc(inline)]
-pub use crate::conn::transaction::{AccessMode, IsolationLevel, Transaction, TxOpts};
+pub use crate::conn::transaction::{AccessMode, IsolationLevel, Savepoint, Transaction, TxOpts};
+#[doc(inline)]
+pub use crate::conn::warning::{Warning, WarningLevel};
+#[doc(inline)]
+pub use crate::conn::xa::{XaTransaction, Xid};
 #[doc(inline)]
 pub use crate::conn::Conn;
 #[doc(inline)]
-pub use crate::error::{DriverError, Error, MySqlError, Result, ServerError, UrlError};
+pub use crate::error::{DriverError, Error, ErrorKind, MySqlError, Result, ServerError, UrlError};
 #[doc(inline)]
 pub use crate::myc::packets::Column;
 #[doc(inline)]
@@ -970,6 +1018,8 @@ pub mod prelude {
     #[doc(inline)]
     pub use crate::conn::queryable::{AsStatement, Queryable};
     #[doc(inline)]
+    pub use crate::conn::stmt::ColumnExt;
+    #[doc(inline)]
     pub use crate::myc::prelude::FromRow;
     #[doc(inline)]
     pub use crate::myc::prelude::{FromValue, ToValue};
@@ -985,6 +1035,17 @@ pub mod prelude {
 #[doc(inline)]
 pub use crate::myc::params;
 
+#[cfg(feature = "macros")]
+#[cfg_attr(docsrs, doc(cfg(feature = "macros")))]
+#[doc(inline)]
+pub use mysql_macros::query_as;
+
+/// Used by the code generated by `query_as!`.
+#[doc(hidden)]
+pub mod __macros {
+    pub use crate::conn::query_check::*;
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! def_database_url {